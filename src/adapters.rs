@@ -0,0 +1,830 @@
+//! Registry of channel adapters: long-running processes (`acomm --discord`, or any command
+//! declared in config.toml) that connect a chat service to the bridge.
+//!
+//! Every adapter is defined once here and used by `start` / `daemon run` (which adapters to
+//! launch and how), `stop`, `status` (which channels to show) and `doctor` (which
+//! credentials are missing). `[adapters.<name>]` sections in config.toml can override the
+//! built-in definitions or declare extra adapters without recompiling.
+
+use crate::bridge::BridgeClient;
+use crate::config::{self, AdapterConfig, Config};
+use crate::email;
+use crate::matrix;
+use crate::process;
+use crate::state;
+use crate::supervisor::{self, RestartMode, SUPERVISOR_RECORD};
+use crate::telegram;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long a `health_command` may run before the adapter is reported as unhealthy.
+const HEALTH_COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long `yuiclaw adapter` waits for the supervisor to start or stop an adapter.
+const SUPERVISOR_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Ownership record names used by yuiclaw itself, which adapter labels must not shadow.
+const RESERVED_LABELS: [&str; 2] = ["bridge", "supervisor"];
+
+/// How long an adapter built into yuiclaw waits for the agent to answer one message.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Program that runs the built-in adapters and, unless `command` is set, declared ones.
+const DEFAULT_COMMAND: &str = "acomm";
+
+/// Program that runs a built-in adapter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Program {
+    /// [`DEFAULT_COMMAND`] with the adapter's flag.
+    Acomm,
+    /// This yuiclaw binary (`yuiclaw adapter run <name>`), for channels acomm does not
+    /// speak.
+    Yuiclaw,
+}
+
+/// Static definition of a built-in adapter.
+struct Builtin {
+    name: &'static str,
+    label: &'static str,
+    required_env: &'static [&'static str],
+    optional_env: &'static [&'static str],
+    program: Program,
+    args: &'static [&'static str],
+    docs_url: &'static str,
+}
+
+const BUILTINS: [Builtin; 7] = [
+    Builtin {
+        name: "ntfy",
+        label: "ntfy",
+        required_env: &["NTFY_TOPIC"],
+        optional_env: &[],
+        program: Program::Acomm,
+        args: &["--ntfy"],
+        docs_url: "https://docs.ntfy.sh/",
+    },
+    Builtin {
+        name: "discord",
+        label: "Discord",
+        required_env: &["DISCORD_BOT_TOKEN"],
+        optional_env: &[],
+        program: Program::Acomm,
+        args: &["--discord"],
+        docs_url: "https://discord.com/developers/docs/quick-start/getting-started",
+    },
+    Builtin {
+        name: "slack",
+        label: "Slack",
+        required_env: &["SLACK_APP_TOKEN", "SLACK_BOT_TOKEN"],
+        optional_env: &[],
+        program: Program::Acomm,
+        args: &["--slack"],
+        docs_url: "https://api.slack.com/apis/socket-mode",
+    },
+    Builtin {
+        name: "telegram",
+        label: "Telegram",
+        // Without an allow-list anyone who finds the bot could talk to the agent.
+        required_env: &["TELEGRAM_BOT_TOKEN", "TELEGRAM_ALLOWED_CHAT_IDS"],
+        optional_env: &["TELEGRAM_API_URL"],
+        program: Program::Yuiclaw,
+        args: &["adapter", "run", "telegram"],
+        docs_url: "https://core.telegram.org/bots/tutorial#obtain-your-bot-token",
+    },
+    Builtin {
+        name: "matrix",
+        label: "Matrix",
+        required_env: &[
+            "MATRIX_HOMESERVER_URL",
+            "MATRIX_ACCESS_TOKEN",
+            "MATRIX_ROOM_IDS",
+        ],
+        optional_env: &[],
+        program: Program::Yuiclaw,
+        args: &["adapter", "run", "matrix"],
+        docs_url: "https://spec.matrix.org/latest/client-server-api/#using-access-tokens",
+    },
+    Builtin {
+        name: "email",
+        label: "Email",
+        required_env: &[
+            "EMAIL_IMAP_URL",
+            "EMAIL_SMTP_URL",
+            "EMAIL_USERNAME",
+            "EMAIL_PASSWORD",
+            "EMAIL_ALLOWED_SENDERS",
+        ],
+        optional_env: &[
+            "EMAIL_FROM",
+            "EMAIL_FOLDER",
+            "EMAIL_POLL_INTERVAL",
+            "EMAIL_DIGEST_TO",
+            "EMAIL_DIGEST_INTERVAL",
+            "EMAIL_SENDER_AUTH",
+            "EMAIL_AUTHSERV_ID",
+        ],
+        program: Program::Yuiclaw,
+        args: &["adapter", "run", "email"],
+        docs_url: "https://github.com/yuiseki/yuiclaw#email",
+    },
+    Builtin {
+        name: "webhook",
+        label: "Webhook",
+        required_env: &["WEBHOOK_LISTEN"],
+        optional_env: &["WEBHOOK_TOKEN", "WEBHOOK_SECRET"],
+        program: Program::Yuiclaw,
+        args: &["serve-webhook"],
+        docs_url: "https://github.com/yuiseki/yuiclaw#yuiclaw-serve-webhook",
+    },
+];
+
+/// A channel adapter and how to launch and check it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adapter {
+    /// Key of the adapter's `[adapters.<name>]` section (`discord`).
+    pub name: String,
+    /// Display name (`Discord`). Processes are supervised and recorded under `name`.
+    pub label: String,
+    /// Variables that must all be set (and non-empty) for the adapter to be started.
+    pub required_env: Vec<String>,
+    /// Variables the adapter reads if they are set.
+    pub optional_env: Vec<String>,
+    /// Program that runs the adapter (`acomm`).
+    pub command: String,
+    /// Arguments passed to `command` (`--discord`).
+    pub args: Vec<String>,
+    /// Working directory; `None` uses the bridge session workdir (`YUICLAW_HOME`).
+    pub workdir: Option<PathBuf>,
+    pub restart: RestartMode,
+    pub health: HealthCheck,
+    /// Where to find out how to obtain the adapter's credentials.
+    pub docs_url: Option<String>,
+}
+
+/// How `status` decides whether a running adapter is connected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthCheck {
+    /// Connected while the bridge and the adapter process owned by yuiclaw are both up.
+    Process,
+    /// Like `Process`, and additionally `sh -c <command>` must exit successfully.
+    Command(String),
+}
+
+impl Adapter {
+    /// Whether every required variable is in `present_env_keys`.
+    pub fn is_configured(&self, present_env_keys: &HashSet<String>) -> bool {
+        self.required_env
+            .iter()
+            .all(|k| present_env_keys.contains(k))
+    }
+
+    /// Required variables missing from `present_env_keys`.
+    pub fn missing_env<'a>(&'a self, present_env_keys: &HashSet<String>) -> Vec<&'a str> {
+        self.required_env
+            .iter()
+            .filter(|k| !present_env_keys.contains(*k))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Run the health check, given that the adapter process and the bridge are up.
+    pub async fn is_healthy(&self) -> bool {
+        match &self.health {
+            HealthCheck::Process => true,
+            HealthCheck::Command(command) => {
+                let status = Command::new("sh")
+                    .args(["-c", command])
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .kill_on_drop(true)
+                    .status();
+                matches!(
+                    tokio::time::timeout(HEALTH_COMMAND_TIMEOUT, status).await,
+                    Ok(Ok(s)) if s.success()
+                )
+            }
+        }
+    }
+
+    fn from_builtin(builtin: &Builtin) -> Self {
+        Self {
+            name: builtin.name.to_string(),
+            label: builtin.label.to_string(),
+            required_env: builtin.required_env.iter().map(|k| k.to_string()).collect(),
+            optional_env: builtin.optional_env.iter().map(|k| k.to_string()).collect(),
+            command: match builtin.program {
+                Program::Acomm => DEFAULT_COMMAND.to_string(),
+                Program::Yuiclaw => std::env::current_exe()
+                    .map(|exe| exe.display().to_string())
+                    .unwrap_or_else(|_| "yuiclaw".to_string()),
+            },
+            args: builtin.args.iter().map(|a| a.to_string()).collect(),
+            workdir: None,
+            restart: RestartMode::default(),
+            health: HealthCheck::Process,
+            docs_url: Some(builtin.docs_url.to_string()),
+        }
+    }
+
+    /// Apply the definition fields of a config section on top of this adapter.
+    fn with_overrides(mut self, section: &AdapterConfig) -> Self {
+        if let Some(label) = &section.label {
+            self.label = label.clone();
+        }
+        if let Some(keys) = &section.required_env {
+            self.required_env = keys.clone();
+        }
+        if let Some(keys) = &section.optional_env {
+            self.optional_env = keys.clone();
+        }
+        if let Some(command) = &section.command {
+            self.command = command.clone();
+        }
+        if let Some(args) = &section.args {
+            self.args = args.clone();
+        }
+        if let Some(dir) = &section.workdir {
+            self.workdir = Some(PathBuf::from(config::expand_home(dir)));
+        }
+        if let Some(restart) = section.restart {
+            self.restart = restart;
+        }
+        if let Some(command) = &section.health_command {
+            self.health = HealthCheck::Command(command.clone());
+        }
+        if let Some(url) = &section.docs_url {
+            self.docs_url = Some(url.clone());
+        }
+        self
+    }
+}
+
+/// Every adapter known to `config`: the built-ins (with their config overrides applied)
+/// followed by the adapters declared in config.toml, in name order. Disabled adapters are
+/// included; see [`Config::adapter_enabled`].
+pub fn registry(config: &Config) -> Vec<Adapter> {
+    let mut adapters: Vec<Adapter> = BUILTINS
+        .iter()
+        .map(|builtin| {
+            let adapter = Adapter::from_builtin(builtin);
+            match section(config, builtin.name) {
+                Some(section) => adapter.with_overrides(section),
+                None => adapter,
+            }
+        })
+        .collect();
+
+    for (name, section) in &config.adapters {
+        if is_builtin(name) {
+            continue;
+        }
+        let declared = Adapter {
+            name: name.clone(),
+            label: name.clone(),
+            required_env: Vec::new(),
+            optional_env: Vec::new(),
+            command: DEFAULT_COMMAND.to_string(),
+            args: Vec::new(),
+            workdir: None,
+            restart: RestartMode::default(),
+            health: HealthCheck::Process,
+            docs_url: None,
+        };
+        adapters.push(declared.with_overrides(section));
+    }
+    adapters
+}
+
+/// Adapters that should be running: enabled and with all required variables present.
+pub fn to_autostart(config: &Config, present_env_keys: &HashSet<String>) -> Vec<Adapter> {
+    registry(config)
+        .into_iter()
+        .filter(|adapter| adapter.is_configured(present_env_keys))
+        .filter(|adapter| config.adapter_enabled(&adapter.name))
+        .collect()
+}
+
+/// Names of the environment variables that are set to a non-empty value.
+pub fn present_env_keys() -> HashSet<String> {
+    std::env::vars()
+        .filter_map(|(k, v)| if v.trim().is_empty() { None } else { Some(k) })
+        .collect()
+}
+
+/// Whether `name` (case-insensitive) is one of the built-in adapters.
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.iter().any(|b| b.name.eq_ignore_ascii_case(name))
+}
+
+/// Check an `[adapters.<name>]` section. Extra adapters must say how to launch them.
+pub fn validate(name: &str, section: &AdapterConfig) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "invalid adapter name '{}' (use letters, digits, '-' and '_')",
+            name
+        ));
+    }
+    if !is_builtin(name) && section.command.is_none() && section.args.is_none() {
+        return Err(format!(
+            "unknown adapter '{}'; set `command` or `args` to declare a custom adapter (built-in: {})",
+            name,
+            BUILTINS.map(|b| b.name).join(", ")
+        ));
+    }
+    if section
+        .command
+        .as_deref()
+        .is_some_and(|command| command.trim().is_empty())
+    {
+        return Err(format!("empty command for adapter '{}'", name));
+    }
+    let label = section.label.as_deref().unwrap_or(name);
+    if label.is_empty()
+        || label.contains('/')
+        || RESERVED_LABELS
+            .iter()
+            .any(|r| r.eq_ignore_ascii_case(label))
+    {
+        return Err(format!("invalid label '{}' for adapter '{}'", label, name));
+    }
+    Ok(())
+}
+
+/// Check that no two adapters, built-in or declared in `sections`, share a name or a label,
+/// ignoring case: processes are recorded by name, and `yuiclaw adapter` accepts either.
+pub fn validate_unique(sections: &BTreeMap<String, AdapterConfig>) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for name in sections.keys() {
+        if !names.insert(name.to_ascii_lowercase()) {
+            return Err(format!("adapter '{}' is declared twice", name));
+        }
+    }
+
+    let label_of = |name: &str, default: &str| {
+        sections
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, section)| section.label.clone())
+            .unwrap_or_else(|| default.to_string())
+    };
+    let builtins = BUILTINS
+        .iter()
+        .map(|b| (b.name.to_string(), label_of(b.name, b.label)));
+    let custom = sections
+        .keys()
+        .filter(|name| !is_builtin(name))
+        .map(|name| (name.clone(), label_of(name, name)));
+    let mut labels: BTreeMap<String, String> = BTreeMap::new();
+    for (name, label) in builtins.chain(custom) {
+        if let Some(other) = labels.insert(label.to_lowercase(), name.clone()) {
+            return Err(format!(
+                "adapters '{}' and '{}' share the label '{}'",
+                other, name, label
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn section<'a>(config: &'a Config, name: &str) -> Option<&'a AdapterConfig> {
+    config
+        .adapters
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, section)| section)
+}
+
+/// `yuiclaw adapter list`: every known adapter with its enabled state and whether it runs.
+pub fn list() -> Result<(), Box<dyn std::error::Error>> {
+    let config = config::current();
+    let present = present_env_keys();
+    let stopped = state::stopped_adapters();
+    let supervised = state::live_record(SUPERVISOR_RECORD).is_some();
+
+    let rows: Vec<[String; 4]> = registry(config)
+        .iter()
+        .map(|adapter| {
+            let enabled = config.adapter_enabled(&adapter.name);
+            let state = match state::live_record(&adapter.name) {
+                Some(record) => format!("running (pid {})", record.pid),
+                None if !enabled => "disabled".to_string(),
+                None if !adapter.is_configured(&present) => {
+                    format!("needs {}", adapter.missing_env(&present).join(", "))
+                }
+                None if stopped.contains(&adapter.name) => "stopped".to_string(),
+                None if supervised => "not running (see daemon log)".to_string(),
+                None => "-".to_string(),
+            };
+            [
+                adapter.name.clone(),
+                adapter.label.clone(),
+                if enabled { "yes" } else { "no" }.to_string(),
+                state,
+            ]
+        })
+        .collect();
+
+    let header = ["NAME", "LABEL", "ENABLED", "STATUS"].map(str::to_string);
+    let widths: Vec<usize> = (0..3)
+        .map(|i| {
+            std::iter::once(&header)
+                .chain(&rows)
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in std::iter::once(&header).chain(&rows) {
+        println!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2]
+        );
+    }
+    if !supervised {
+        println!();
+        println!("Daemon is not running. Start it with `yuiclaw daemon start`.");
+    }
+    Ok(())
+}
+
+/// `yuiclaw adapter enable NAME`: persist `enabled = true` and start the adapter if the
+/// daemon is running.
+pub async fn enable(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let adapter = resolve(name)?;
+    config::write_adapter_enabled(&section_key(&adapter), true)?;
+    println!("{} enabled.", adapter.label);
+
+    let missing = adapter.missing_env(&present_env_keys());
+    if !missing.is_empty() {
+        println!(
+            "Note: {} will start once {} is set.",
+            adapter.label,
+            missing.join(" and ")
+        );
+        return Ok(());
+    }
+    if state::live_record(SUPERVISOR_RECORD).is_none() {
+        println!("It will start with `yuiclaw daemon start`.");
+        return Ok(());
+    }
+    state::set_adapter_stopped(&adapter.name, false)?;
+    reload_supervisor()?;
+    report_started(&adapter).await
+}
+
+/// `yuiclaw adapter disable NAME`: persist `enabled = false` and stop the adapter.
+pub async fn disable(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let adapter = resolve(name)?;
+    config::write_adapter_enabled(&section_key(&adapter), false)?;
+    println!("{} disabled.", adapter.label);
+
+    if state::live_record(SUPERVISOR_RECORD).is_some() {
+        reload_supervisor()?;
+        report_stopped(&adapter).await?;
+    }
+    Ok(())
+}
+
+/// `yuiclaw adapter start NAME`: start an adapter stopped with `yuiclaw adapter stop` (or
+/// given up after a crash loop) in the running daemon.
+pub async fn start(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let adapter = resolve(name)?;
+    require_supervisor()?;
+    if !config::current().adapter_enabled(&adapter.name) {
+        return Err(format!(
+            "{} is disabled. Enable it with `yuiclaw adapter enable {}`.",
+            adapter.label, adapter.name
+        )
+        .into());
+    }
+    let missing = adapter.missing_env(&present_env_keys());
+    if !missing.is_empty() {
+        let docs = adapter
+            .docs_url
+            .as_ref()
+            .map(|url| format!(" (see {})", url))
+            .unwrap_or_default();
+        return Err(format!(
+            "{} needs {} to start{}.",
+            adapter.label,
+            missing.join(" and "),
+            docs
+        )
+        .into());
+    }
+
+    state::set_adapter_stopped(&adapter.name, false)?;
+    reload_supervisor()?;
+    report_started(&adapter).await
+}
+
+/// `yuiclaw adapter stop NAME`: stop an adapter until it is started again or the daemon
+/// restarts. Use `disable` to keep it stopped.
+pub async fn stop(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let adapter = resolve(name)?;
+    require_supervisor()?;
+    state::set_adapter_stopped(&adapter.name, true)?;
+    reload_supervisor()?;
+    report_stopped(&adapter).await
+}
+
+/// `yuiclaw adapter restart NAME`
+pub async fn restart(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    stop(name).await?;
+    start(name).await
+}
+
+/// `yuiclaw adapter run NAME`: run an adapter built into yuiclaw in the foreground. This
+/// is how the supervisor launches them; it is not meant to be run by hand.
+pub async fn run(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match name.to_ascii_lowercase().as_str() {
+        "telegram" => telegram::run().await,
+        "matrix" => matrix::run().await,
+        "email" => email::run().await,
+        _ => Err(format!("'{}' is not an adapter built into yuiclaw.", name).into()),
+    }
+}
+
+/// Value of a variable that an adapter built into yuiclaw cannot run without.
+pub(crate) fn required_env(key: &str) -> Result<String, String> {
+    std::env::var(key)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .ok_or_else(|| format!("{} is not set.", key))
+}
+
+/// Items of a list-valued variable, separated by commas and/or whitespace.
+pub(crate) fn split_list(raw: &str) -> impl Iterator<Item = &str> {
+    raw.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+}
+
+/// Forward a message received by an adapter built into yuiclaw to the bridge at `socket`,
+/// on a `channel` of its own, and wait for the agent's reply.
+pub(crate) async fn relay_to_bridge(
+    socket: &Path,
+    text: &str,
+    channel: &str,
+) -> Result<String, String> {
+    let mut client = BridgeClient::connect(socket)
+        .await
+        .map_err(|e| e.to_string())?;
+    process::ask_bridge(&mut client, text, None, channel, REPLY_TIMEOUT, |_| {}).await
+}
+
+/// Find an adapter by name or label, case-insensitively.
+fn resolve(name: &str) -> Result<Adapter, String> {
+    let adapters = registry(config::current());
+    let names: Vec<&str> = adapters.iter().map(|a| a.name.as_str()).collect();
+    let known = names.join(", ");
+    adapters
+        .iter()
+        .find(|a| a.name.eq_ignore_ascii_case(name) || a.label.eq_ignore_ascii_case(name))
+        .cloned()
+        .ok_or_else(|| format!("Unknown adapter '{}'. Known adapters: {}", name, known))
+}
+
+/// Key of the adapter's existing config.toml section, keeping the user's spelling.
+fn section_key(adapter: &Adapter) -> String {
+    config::current()
+        .adapters
+        .keys()
+        .find(|key| key.eq_ignore_ascii_case(&adapter.name))
+        .cloned()
+        .unwrap_or_else(|| adapter.name.clone())
+}
+
+fn require_supervisor() -> Result<(), String> {
+    match state::live_record(SUPERVISOR_RECORD) {
+        Some(_) => Ok(()),
+        None => Err("Daemon is not running. Start it with `yuiclaw daemon start`.".to_string()),
+    }
+}
+
+/// Ask the supervisor to reconcile its adapters (see [`supervisor::run`]).
+fn reload_supervisor() -> Result<(), String> {
+    match state::live_record(SUPERVISOR_RECORD) {
+        Some(record) if state::signal(&record, libc::SIGHUP) => Ok(()),
+        _ => Err("Daemon is not running. Start it with `yuiclaw daemon start`.".to_string()),
+    }
+}
+
+async fn report_started(adapter: &Adapter) -> Result<(), Box<dyn std::error::Error>> {
+    match wait_for_record(&adapter.name, true).await {
+        Some(record) => {
+            println!("{} started. (pid {})", adapter.label, record.pid);
+            Ok(())
+        }
+        None => Err(format!(
+            "{} did not start. See {} for details.",
+            adapter.label,
+            supervisor::log_path().display()
+        )
+        .into()),
+    }
+}
+
+async fn report_stopped(adapter: &Adapter) -> Result<(), Box<dyn std::error::Error>> {
+    match wait_for_record(&adapter.name, false).await {
+        None => {
+            println!("{} stopped.", adapter.label);
+            Ok(())
+        }
+        Some(record) => Err(format!(
+            "{} (pid {}) is still running after {} seconds.",
+            adapter.label,
+            record.pid,
+            SUPERVISOR_ACK_TIMEOUT.as_secs()
+        )
+        .into()),
+    }
+}
+
+/// Poll the ownership record of adapter `name` until it is (or is no longer) live. Returns
+/// the last live record, if any.
+async fn wait_for_record(name: &str, running: bool) -> Option<state::ProcessRecord> {
+    let deadline = tokio::time::Instant::now() + SUPERVISOR_ACK_TIMEOUT;
+    loop {
+        let record = state::live_record(name);
+        if record.is_some() == running || tokio::time::Instant::now() >= deadline {
+            return record;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_keys(keys: &[&str]) -> HashSet<String> {
+        keys.iter().map(|k| (*k).to_string()).collect()
+    }
+
+    fn labels(adapters: &[Adapter]) -> Vec<&str> {
+        adapters.iter().map(|a| a.label.as_str()).collect()
+    }
+
+    #[test]
+    fn builtins_launch_acomm_flags() {
+        let adapters = registry(&Config::default());
+        assert_eq!(
+            labels(&adapters),
+            [
+                "ntfy", "Discord", "Slack", "Telegram", "Matrix", "Email", "Webhook"
+            ]
+        );
+        let slack = &adapters[2];
+        assert_eq!(slack.name, "slack");
+        assert_eq!(slack.args, ["--slack"]);
+        assert_eq!(slack.required_env, ["SLACK_APP_TOKEN", "SLACK_BOT_TOKEN"]);
+        assert_eq!(slack.health, HealthCheck::Process);
+        assert!(slack.docs_url.is_some());
+    }
+
+    #[test]
+    fn telegram_runs_in_yuiclaw_and_needs_an_allow_list() {
+        let telegram = &registry(&Config::default())[3];
+        assert_eq!(telegram.args, ["adapter", "run", "telegram"]);
+        assert_ne!(telegram.command, DEFAULT_COMMAND);
+        assert_eq!(telegram.optional_env, ["TELEGRAM_API_URL"]);
+        assert_eq!(
+            telegram.missing_env(&env_keys(&["TELEGRAM_BOT_TOKEN"])),
+            ["TELEGRAM_ALLOWED_CHAT_IDS"]
+        );
+    }
+
+    #[test]
+    fn slack_requires_both_tokens() {
+        let slack = &registry(&Config::default())[2];
+        let present = env_keys(&["SLACK_APP_TOKEN"]);
+        assert!(!slack.is_configured(&present));
+        assert_eq!(slack.missing_env(&present), ["SLACK_BOT_TOKEN"]);
+        assert!(slack.is_configured(&env_keys(&["SLACK_APP_TOKEN", "SLACK_BOT_TOKEN"])));
+    }
+
+    #[test]
+    fn config_declares_extra_adapters_and_overrides_builtins() {
+        let config = Config::parse(
+            "[adapters.Discord]\nargs = [\"--discord\", \"--verbose\"]\n\n\
+             [adapters.mattermost]\nlabel = \"Mattermost\"\nargs = [\"--mattermost\"]\n\
+             required_env = [\"MATTERMOST_TOKEN\"]\nhealth_command = \"true\"\n",
+        )
+        .unwrap();
+        let adapters = registry(&config);
+        assert_eq!(
+            labels(&adapters),
+            [
+                "ntfy",
+                "Discord",
+                "Slack",
+                "Telegram",
+                "Matrix",
+                "Email",
+                "Webhook",
+                "Mattermost"
+            ]
+        );
+        assert_eq!(adapters[1].args, ["--discord", "--verbose"]);
+        assert_eq!(adapters[1].required_env, ["DISCORD_BOT_TOKEN"]);
+
+        let mattermost = &adapters[7];
+        assert_eq!(mattermost.name, "mattermost");
+        assert_eq!(mattermost.args, ["--mattermost"]);
+        assert_eq!(mattermost.health, HealthCheck::Command("true".to_string()));
+        assert_eq!(mattermost.docs_url, None);
+    }
+
+    #[test]
+    fn adapters_to_autostart_uses_only_configured_services() {
+        let config = Config::default();
+        let rows = to_autostart(&config, &env_keys(&[]));
+        assert!(rows.is_empty());
+
+        let discord = to_autostart(&config, &env_keys(&["DISCORD_BOT_TOKEN"]));
+        assert_eq!(discord.len(), 1);
+        assert_eq!(discord[0].label, "Discord");
+
+        let slack_missing_one = to_autostart(&config, &env_keys(&["SLACK_APP_TOKEN"]));
+        assert!(slack_missing_one.is_empty());
+    }
+
+    #[test]
+    fn adapters_disabled_in_config_are_not_autostarted() {
+        let config = Config::parse("[adapters.discord]\nenabled = false\n").unwrap();
+        let rows = to_autostart(&config, &env_keys(&["DISCORD_BOT_TOKEN", "NTFY_TOPIC"]));
+        assert_eq!(labels(&rows), vec!["ntfy"]);
+    }
+
+    #[test]
+    fn declared_adapters_without_required_env_are_always_started() {
+        let config = Config::parse("[adapters.relay]\nargs = [\"--relay\"]\n").unwrap();
+        assert_eq!(
+            labels(&to_autostart(&config, &env_keys(&[]))),
+            vec!["relay"]
+        );
+    }
+
+    #[test]
+    fn unknown_adapters_need_launch_args() {
+        let err = Config::parse("[adapters.mattermost]\nenabled = true\n").unwrap_err();
+        assert!(err.contains("unknown adapter 'mattermost'"), "{}", err);
+        assert!(err.contains("line 1"), "{}", err);
+
+        let err = Config::parse("[adapters.relay]\nargs = []\nlabel = \"bridge\"\n").unwrap_err();
+        assert!(err.contains("invalid label 'bridge'"), "{}", err);
+
+        let err = Config::parse("[adapters.\"my relay\"]\nargs = []\n").unwrap_err();
+        assert!(err.contains("invalid adapter name"), "{}", err);
+    }
+
+    #[test]
+    fn adapter_labels_must_be_unique() {
+        let err = Config::parse("[adapters.relay]\nargs = []\nlabel = \"discord\"\n").unwrap_err();
+        assert!(
+            err.contains("adapters 'discord' and 'relay' share the label 'discord'"),
+            "{}",
+            err
+        );
+
+        let err = Config::parse(
+            "[adapters.a]\nargs = []\nlabel = \"Relay\"\n[adapters.b]\nargs = []\nlabel = \"relay\"\n",
+        )
+        .unwrap_err();
+        assert!(err.contains("share the label 'relay'"), "{}", err);
+
+        let err = Config::parse("[adapters.relay]\nargs = []\n[adapters.Relay]\nargs = []\n")
+            .unwrap_err();
+        assert!(err.contains("declared twice"), "{}", err);
+
+        // Renaming a built-in frees its label.
+        let config = Config::parse(
+            "[adapters.discord]\nlabel = \"Discord (old)\"\n[adapters.relay]\nargs = []\nlabel = \"Discord\"\n",
+        );
+        assert!(config.is_ok(), "{:?}", config.err());
+    }
+
+    #[tokio::test]
+    async fn health_command_decides_health() {
+        let mut adapter = registry(&Config::default()).remove(0);
+        assert!(adapter.is_healthy().await);
+        adapter.health = HealthCheck::Command("exit 1".to_string());
+        assert!(!adapter.is_healthy().await);
+        adapter.health = HealthCheck::Command("true".to_string());
+        assert!(adapter.is_healthy().await);
+    }
+}
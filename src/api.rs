@@ -0,0 +1,380 @@
+//! HTTP control API, served by the daemon when `listen` is set in the `[api]` section of
+//! config.toml, so that dashboards can manage yuiclaw without running the CLI.
+//!
+//! The endpoints mirror `yuiclaw daemon status --json`, `pub`, `reset`,
+//! `adapter restart` and `daemon restart`; `/metrics` serves the same Prometheus text as
+//! `yuiclaw metrics`. They are described by docs/openapi.json, which
+//! is also served at `/v1/openapi.json`. On a Unix socket the API is protected by the
+//! socket's permissions (and the token if one is set); on localhost TCP every request but
+//! the OpenAPI document needs `Authorization: Bearer <token>`.
+
+use crate::bridge::BridgeClient;
+use crate::components::socket_path;
+use crate::config::{ApiConfig, ApiListen};
+use crate::http::{self, Response, error, reply};
+use crate::message::MAX_MESSAGE_BYTES;
+use crate::metrics;
+use crate::process;
+use crate::state;
+use crate::status;
+use hyper::body::Incoming;
+use hyper::{Method, Request, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::net::{TcpListener, UnixListener};
+
+/// OpenAPI 3 description of the API.
+const OPENAPI: &str = include_str!("../docs/openapi.json");
+
+/// Serve the API configured in `[api]` until the daemon exits. Does nothing when the API
+/// is not enabled; errors are logged.
+pub async fn serve(config: ApiConfig) {
+    let Some(listen) = config.listen else {
+        return;
+    };
+    let api = Arc::new(Api {
+        token: config.token,
+        socket: socket_path().to_path_buf(),
+        counters: state::counters_path(),
+    });
+    let served = match listen {
+        ApiListen::Unix(path) => {
+            let path = path.unwrap_or_else(|| state::state_dir().join("api.sock"));
+            serve_unix(api, &path).await
+        }
+        ApiListen::Tcp(addr) => match TcpListener::bind(addr).await {
+            Ok(listener) => {
+                eprintln!("api: listening on http://{}", addr);
+                serve_tcp(api, listener).await
+            }
+            Err(e) => Err(format!("cannot listen on {}: {}", addr, e)),
+        },
+    };
+    if let Err(e) = served {
+        eprintln!("api: {}", e);
+    }
+}
+
+async fn serve_unix(api: Arc<Api>, path: &Path) -> Result<(), String> {
+    // A socket left behind by a daemon that did not shut down cleanly.
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path)
+        .map_err(|e| format!("cannot listen on {}: {}", path.display(), e))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("cannot restrict {}: {}", path.display(), e))?;
+    eprintln!("api: listening on {}", path.display());
+    loop {
+        let (stream, _) = listener.accept().await.map_err(|e| e.to_string())?;
+        http::spawn_connection(stream, Arc::clone(&api));
+    }
+}
+
+async fn serve_tcp(api: Arc<Api>, listener: TcpListener) -> Result<(), String> {
+    loop {
+        let (stream, _) = listener.accept().await.map_err(|e| e.to_string())?;
+        http::spawn_connection(stream, Arc::clone(&api));
+    }
+}
+
+struct Api {
+    token: Option<String>,
+    /// Bridge socket that messages and resets are sent to.
+    socket: PathBuf,
+    /// `counters.json` that published messages are counted in.
+    counters: PathBuf,
+}
+
+/// Body of `POST /v1/publish`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Publish {
+    message: String,
+    #[serde(default)]
+    channel: Option<String>,
+}
+
+impl http::Handler for Api {
+    async fn handle(&self, request: Request<Incoming>) -> Response {
+        let method = request.method().clone();
+        let path = request.uri().path().to_string();
+        if path == "/v1/openapi.json" && method == Method::GET {
+            return reply(
+                StatusCode::OK,
+                serde_json::from_str(OPENAPI).expect("docs/openapi.json is valid JSON"),
+            );
+        }
+        if let Some(token) = &self.token
+            && !http::has_bearer_token(request.headers(), token)
+        {
+            return error(StatusCode::UNAUTHORIZED, "Missing or invalid token.");
+        }
+
+        let adapter = path
+            .strip_prefix("/v1/adapters/")
+            .and_then(|rest| rest.strip_suffix("/restart"))
+            .filter(|name| !name.is_empty() && !name.contains('/'));
+        let expected = match (path.as_str(), adapter) {
+            ("/metrics" | "/v1/status", _) => Method::GET,
+            ("/v1/publish" | "/v1/reset" | "/v1/restart", _) | (_, Some(_)) => Method::POST,
+            _ => return error(StatusCode::NOT_FOUND, "Not found; see /v1/openapi.json."),
+        };
+        if method != expected {
+            return error(
+                StatusCode::METHOD_NOT_ALLOWED,
+                &format!("Use {}.", expected),
+            );
+        }
+
+        match (path.as_str(), adapter) {
+            ("/metrics", _) => http::text(
+                StatusCode::OK,
+                metrics::CONTENT_TYPE,
+                metrics::collect().await,
+            ),
+            ("/v1/status", _) => match serde_json::to_value(status::daemon_status().await) {
+                Ok(status) => reply(StatusCode::OK, status),
+                Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+            },
+            ("/v1/publish", _) => {
+                match http::read_body(request.into_body(), MAX_MESSAGE_BYTES).await {
+                    Ok(body) => self.publish(&body).await,
+                    Err(response) => response,
+                }
+            }
+            ("/v1/reset", _) => match process::clear_session(&self.socket).await {
+                Ok(true) => reply(StatusCode::OK, json!({ "ok": true })),
+                Ok(false) => error(StatusCode::SERVICE_UNAVAILABLE, "Bridge is not running."),
+                Err(e) => error(StatusCode::BAD_GATEWAY, &e),
+            },
+            ("/v1/restart", _) => restart_daemon(),
+            (_, Some(name)) => restart_adapter(name).await,
+            _ => unreachable!("unknown paths are answered above"),
+        }
+    }
+}
+
+impl Api {
+    async fn publish(&self, body: &[u8]) -> Response {
+        let publish: Publish = match serde_json::from_slice(body) {
+            Ok(publish) => publish,
+            Err(e) => return error(StatusCode::BAD_REQUEST, &format!("Invalid body: {}", e)),
+        };
+        if publish.message.trim().is_empty() {
+            return error(StatusCode::BAD_REQUEST, "Message is empty.");
+        }
+        let published = match BridgeClient::connect(&self.socket).await {
+            Ok(mut client) => {
+                client
+                    .prompt(&publish.message, publish.channel.as_deref())
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        match published {
+            Ok(()) => {
+                metrics::record_publish_in(&self.counters, "api", publish.channel.as_deref());
+                reply(StatusCode::ACCEPTED, json!({ "ok": true }))
+            }
+            Err(e) => {
+                eprintln!("api: {}", e);
+                error(StatusCode::SERVICE_UNAVAILABLE, "Bridge is not running.")
+            }
+        }
+    }
+}
+
+/// Run `yuiclaw daemon restart` detached, since it stops this process first.
+fn restart_daemon() -> Response {
+    let spawned = std::env::current_exe().and_then(|exe| {
+        use std::os::unix::process::CommandExt;
+
+        let mut cmd = std::process::Command::new(exe);
+        cmd.args(["daemon", "restart"]).stdin(Stdio::null());
+        // A session of its own, so that stopping the supervisor does not take it along.
+        // SAFETY: setsid(2) is async-signal-safe and touches no Rust state.
+        unsafe {
+            cmd.pre_exec(|| {
+                libc::setsid();
+                Ok(())
+            });
+        }
+        cmd.spawn()
+    });
+    match spawned {
+        Ok(_) => reply(StatusCode::ACCEPTED, json!({ "ok": true })),
+        Err(e) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Cannot run yuiclaw daemon restart: {}", e),
+        ),
+    }
+}
+
+/// Run `yuiclaw adapter restart NAME` and report its output.
+async fn restart_adapter(name: &str) -> Response {
+    let output = match std::env::current_exe() {
+        Ok(exe) => {
+            tokio::process::Command::new(exe)
+                .args(["adapter", "restart", name])
+                .stdin(Stdio::null())
+                .output()
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match output {
+        Ok(output) if output.status.success() => reply(
+            StatusCode::OK,
+            json!({ "ok": true, "output": String::from_utf8_lossy(&output.stdout).trim() }),
+        ),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr.trim().trim_start_matches("Error: ");
+            error(StatusCode::CONFLICT, reason)
+        }
+        Err(e) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Cannot run yuiclaw adapter restart: {}", e),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::Request as BridgeRequest;
+    use crate::bridge::fake::FakeBridge;
+    use serde_json::Value;
+    use tempfile::tempdir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixStream;
+
+    #[test]
+    fn openapi_document_describes_every_route() {
+        let doc: Value = serde_json::from_str(OPENAPI).unwrap();
+        assert_eq!(doc["openapi"], "3.1.0");
+        let paths: Vec<&str> = doc["paths"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "/metrics",
+                "/v1/adapters/{name}/restart",
+                "/v1/openapi.json",
+                "/v1/publish",
+                "/v1/reset",
+                "/v1/restart",
+                "/v1/status",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn publishes_and_resets_with_a_token() {
+        let dir = tempdir().unwrap();
+        let bridge = FakeBridge::start(&dir.path().join("acomm.sock"), &[]);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let api = Arc::new(Api {
+            token: Some("t0ken".to_string()),
+            socket: bridge.path.clone(),
+            counters: dir.path().join("counters.json"),
+        });
+        tokio::spawn(serve_tcp(api, listener));
+        let http = reqwest::Client::new();
+
+        let unauthorized = http.post(format!("{}/v1/reset", url)).send().await.unwrap();
+        assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+
+        let published = http
+            .post(format!("{}/v1/publish", url))
+            .bearer_auth("t0ken")
+            .json(&json!({ "message": "Deploy finished", "channel": "dashboard" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(published.status(), StatusCode::ACCEPTED);
+        let reset = http
+            .post(format!("{}/v1/reset", url))
+            .bearer_auth("t0ken")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(reset.status(), StatusCode::OK);
+
+        let empty = http
+            .post(format!("{}/v1/publish", url))
+            .bearer_auth("t0ken")
+            .json(&json!({ "message": " " }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(empty.status(), StatusCode::BAD_REQUEST);
+        let wrong_method = http
+            .get(format!("{}/v1/reset", url))
+            .bearer_auth("t0ken")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(wrong_method.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let counters = state::read_counters(&dir.path().join("counters.json"));
+        assert_eq!(counters.published.get("api"), Some(&1));
+
+        assert_eq!(
+            bridge.requests(),
+            vec![
+                BridgeRequest::Prompt {
+                    text: "Deploy finished".to_string(),
+                    channel: Some("dashboard".to_string()),
+                    provider: None,
+                },
+                BridgeRequest::Control {
+                    command: "clear".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_on_a_private_unix_socket() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("api.sock");
+        let api = Arc::new(Api {
+            token: None,
+            socket: dir.path().join("missing.sock"),
+            counters: dir.path().join("counters.json"),
+        });
+        let served = path.clone();
+        tokio::spawn(async move { serve_unix(api, &served).await });
+
+        let mut stream = loop {
+            match UnixStream::connect(&path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        stream
+            .write_all(b"POST /v1/reset HTTP/1.1\r\nHost: yuiclaw\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 503"),
+            "{}",
+            response.lines().next().unwrap_or_default()
+        );
+        assert!(response.contains("Bridge is not running."), "{}", response);
+    }
+}
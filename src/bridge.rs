@@ -0,0 +1,572 @@
+//! Async client for the acomm bridge.
+//!
+//! The bridge speaks JSONL over its Unix socket: every message is one JSON object on its own
+//! line, tagged by a `type` field. Clients send [`Request`]s; the bridge broadcasts
+//! [`Event`]s (agent output, system messages, prompts from other clients) to every connected
+//! client. Event types this client does not know are surfaced as [`Event::Unknown`] rather
+//! than treated as errors, so that newer bridges keep working.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::UnixStream;
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+
+/// Message sent from a client to the bridge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Liveness check; the bridge answers with [`Event::Pong`].
+    Ping,
+    /// Prompt for the active agent session.
+    Prompt {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        channel: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        provider: Option<String>,
+    },
+    /// Bridge command such as `clear` (reset the session).
+    Control { command: String },
+}
+
+/// Message broadcast by the bridge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Pong,
+    /// A prompt accepted by the bridge, from this or another client.
+    Prompt {
+        text: String,
+        #[serde(default)]
+        channel: Option<String>,
+    },
+    /// A piece of the agent's streamed reply.
+    AgentChunk {
+        chunk: String,
+        #[serde(default)]
+        channel: Option<String>,
+    },
+    /// The agent finished replying.
+    AgentDone {
+        #[serde(default)]
+        channel: Option<String>,
+    },
+    /// Informational message from the bridge (e.g. "Cleared.").
+    SystemMessage {
+        message: String,
+        #[serde(default)]
+        channel: Option<String>,
+    },
+    /// The bridge rejected a request or the agent failed. `channel` is set when the error
+    /// belongs to a prompt sent on that channel.
+    Error {
+        message: String,
+        #[serde(default)]
+        channel: Option<String>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Errors returned by [`BridgeClient`].
+#[derive(Debug)]
+pub enum BridgeError {
+    /// The socket could not be connected to.
+    Connect {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Io(std::io::Error),
+    /// The bridge sent a line that is not valid JSON.
+    InvalidEvent {
+        line: String,
+        source: serde_json::Error,
+    },
+    /// The bridge closed the connection.
+    Closed,
+}
+
+impl std::fmt::Display for BridgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect { path, source } => write!(
+                f,
+                "cannot connect to bridge at {}: {} (run `yuiclaw start` or `yuiclaw daemon start`)",
+                path.display(),
+                source
+            ),
+            Self::Io(e) => write!(f, "bridge connection error: {}", e),
+            Self::InvalidEvent { line, source } => {
+                write!(f, "invalid message from bridge ({}): {}", source, line)
+            }
+            Self::Closed => write!(f, "bridge closed the connection"),
+        }
+    }
+}
+
+impl std::error::Error for BridgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Connect { source, .. } => Some(source),
+            Self::Io(e) => Some(e),
+            Self::InvalidEvent { source, .. } => Some(source),
+            Self::Closed => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for BridgeError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A connection to the bridge.
+pub struct BridgeClient {
+    sender: BridgeSender,
+    events: EventStream,
+}
+
+/// Write half of a [`BridgeClient`].
+pub struct BridgeSender {
+    writer: OwnedWriteHalf,
+}
+
+/// Read half of a [`BridgeClient`]: the events broadcast by the bridge.
+pub struct EventStream {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+}
+
+impl BridgeClient {
+    pub async fn connect(path: &Path) -> Result<Self, BridgeError> {
+        let stream = UnixStream::connect(path)
+            .await
+            .map_err(|source| BridgeError::Connect {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self {
+            sender: BridgeSender { writer },
+            events: EventStream {
+                lines: BufReader::new(reader).lines(),
+            },
+        })
+    }
+
+    pub async fn send(&mut self, request: &Request) -> Result<(), BridgeError> {
+        self.sender.send(request).await
+    }
+
+    /// Send a prompt, optionally tagged with a channel name (e.g. `heartbeat`).
+    pub async fn prompt(&mut self, text: &str, channel: Option<&str>) -> Result<(), BridgeError> {
+        self.sender.prompt(text, channel, None).await
+    }
+
+    /// Send a bridge command such as `clear`.
+    pub async fn control(&mut self, command: &str) -> Result<(), BridgeError> {
+        self.sender.control(command).await
+    }
+
+    /// Send a ping and return the time until the bridge's next message.
+    ///
+    /// Any event counts as the answer, since the bridge may be broadcasting other traffic
+    /// when the ping arrives.
+    pub async fn ping(&mut self) -> Result<Duration, BridgeError> {
+        let started = Instant::now();
+        self.sender.send(&Request::Ping).await?;
+        match self.events.next().await? {
+            Some(_) => Ok(started.elapsed()),
+            None => Err(BridgeError::Closed),
+        }
+    }
+
+    /// Wait for the next event. `Ok(None)` means the bridge closed the connection.
+    pub async fn next_event(&mut self) -> Result<Option<Event>, BridgeError> {
+        self.events.next().await
+    }
+}
+
+impl BridgeSender {
+    pub async fn send(&mut self, request: &Request) -> Result<(), BridgeError> {
+        let mut line = serde_json::to_string(request)
+            .map_err(|e| BridgeError::Io(std::io::Error::other(e)))?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    pub async fn prompt(
+        &mut self,
+        text: &str,
+        channel: Option<&str>,
+        provider: Option<&str>,
+    ) -> Result<(), BridgeError> {
+        self.send(&Request::Prompt {
+            text: text.to_string(),
+            channel: channel.map(str::to_string),
+            provider: provider.map(str::to_string),
+        })
+        .await
+    }
+
+    pub async fn control(&mut self, command: &str) -> Result<(), BridgeError> {
+        self.send(&Request::Control {
+            command: command.to_string(),
+        })
+        .await
+    }
+}
+
+impl EventStream {
+    /// Wait for the next event. Blank lines are skipped; `Ok(None)` means the bridge closed
+    /// the connection.
+    pub async fn next(&mut self) -> Result<Option<Event>, BridgeError> {
+        loop {
+            let Some(line) = self.lines.next_line().await? else {
+                return Ok(None);
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return serde_json::from_str(&line)
+                .map(Some)
+                .map_err(|source| BridgeError::InvalidEvent { line, source });
+        }
+    }
+}
+
+/// Channel of the prompts sent by the heartbeat job (see `yuiclaw init`).
+pub const HEARTBEAT_CHANNEL: &str = "heartbeat";
+
+/// Answer of a heartbeat that found nothing to report.
+pub const HEARTBEAT_NO_OP: &str = "HEARTBEAT_OK";
+
+/// Follow the bridge at `socket` and pass every heartbeat answer that reports something
+/// (anything but [`HEARTBEAT_NO_OP`]) to `on_report`. Returns when the connection fails or
+/// the bridge closes it.
+pub async fn watch_heartbeats(
+    socket: &Path,
+    mut on_report: impl FnMut(String),
+) -> Result<(), BridgeError> {
+    let mut client = BridgeClient::connect(socket).await?;
+    let is_heartbeat = |c: &Option<String>| c.as_deref() == Some(HEARTBEAT_CHANNEL);
+    let mut current = String::new();
+    loop {
+        match client.next_event().await? {
+            None => return Err(BridgeError::Closed),
+            Some(Event::AgentChunk { chunk, channel }) if is_heartbeat(&channel) => {
+                current.push_str(&chunk);
+            }
+            Some(Event::AgentDone { channel }) if is_heartbeat(&channel) => {
+                let text = std::mem::take(&mut current);
+                let text = text.trim();
+                if !text.is_empty() && text != HEARTBEAT_NO_OP {
+                    on_report(text.to_string());
+                }
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Follow the bridge at `socket` and pass the channel of every prompt it accepts, from any
+/// client, to `on_prompt`. Returns when the connection fails or the bridge closes it.
+pub async fn watch_prompts(
+    socket: &Path,
+    mut on_prompt: impl FnMut(Option<String>),
+) -> Result<(), BridgeError> {
+    let mut client = BridgeClient::connect(socket).await?;
+    loop {
+        match client.next_event().await? {
+            None => return Err(BridgeError::Closed),
+            Some(Event::Prompt { channel, .. }) => on_prompt(channel),
+            Some(_) => {}
+        }
+    }
+}
+
+/// In-process stand-in for `acomm --bridge`, for tests that must not depend on the acomm
+/// binary.
+///
+/// It answers [`Request::Ping`] with [`Event::Pong`], echoes prompts and replies to them
+/// with the configured chunks followed by [`Event::AgentDone`], acknowledges `clear` with a
+/// system message, and records every request it receives. Prompts for the provider `Broken`
+/// fail with an [`Event::Error`].
+#[cfg(test)]
+pub mod fake {
+    use super::{Event, Request};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixListener;
+    use tokio::sync::broadcast;
+
+    pub struct FakeBridge {
+        pub path: PathBuf,
+        requests: Arc<Mutex<Vec<Request>>>,
+        task: tokio::task::JoinHandle<()>,
+    }
+
+    impl FakeBridge {
+        /// Listen on `path`, replying to every prompt with `reply_chunks`.
+        pub fn start(path: &Path, reply_chunks: &[&str]) -> Self {
+            let listener = UnixListener::bind(path).expect("fake bridge should bind");
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let chunks: Vec<String> = reply_chunks.iter().map(|c| c.to_string()).collect();
+            let (events, _) = broadcast::channel::<Event>(64);
+
+            let recorded = Arc::clone(&requests);
+            let task = tokio::spawn(async move {
+                loop {
+                    let Ok((stream, _)) = listener.accept().await else {
+                        return;
+                    };
+                    let (reader, mut writer) = stream.into_split();
+                    let recorded = Arc::clone(&recorded);
+                    let chunks = chunks.clone();
+                    let events = events.clone();
+                    let mut broadcast = events.subscribe();
+
+                    // Forward broadcast events to this client.
+                    tokio::spawn(async move {
+                        while let Ok(event) = broadcast.recv().await {
+                            let mut line = serde_json::to_string(&event).unwrap();
+                            line.push('\n');
+                            if writer.write_all(line.as_bytes()).await.is_err() {
+                                return;
+                            }
+                        }
+                    });
+
+                    tokio::spawn(async move {
+                        let mut lines = BufReader::new(reader).lines();
+                        while let Ok(Some(line)) = lines.next_line().await {
+                            let Ok(request) = serde_json::from_str::<Request>(&line) else {
+                                let _ = events.send(Event::Error {
+                                    message: format!("invalid request: {}", line),
+                                    channel: None,
+                                });
+                                continue;
+                            };
+                            recorded.lock().unwrap().push(request.clone());
+                            for event in respond(&request, &chunks) {
+                                let _ = events.send(event);
+                            }
+                        }
+                    });
+                }
+            });
+
+            Self {
+                path: path.to_path_buf(),
+                requests,
+                task,
+            }
+        }
+
+        /// Requests received so far, in arrival order.
+        pub fn requests(&self) -> Vec<Request> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    impl Drop for FakeBridge {
+        fn drop(&mut self) {
+            self.task.abort();
+        }
+    }
+
+    fn respond(request: &Request, chunks: &[String]) -> Vec<Event> {
+        match request {
+            Request::Ping => vec![Event::Pong],
+            Request::Prompt {
+                provider: Some(provider),
+                channel,
+                ..
+            } if provider == "Broken" => vec![Event::Error {
+                message: format!("{} CLI exited with status 1", provider),
+                channel: channel.clone(),
+            }],
+            Request::Prompt { text, channel, .. } => {
+                let mut events = vec![Event::Prompt {
+                    text: text.clone(),
+                    channel: channel.clone(),
+                }];
+                events.extend(chunks.iter().map(|chunk| Event::AgentChunk {
+                    chunk: chunk.clone(),
+                    channel: channel.clone(),
+                }));
+                events.push(Event::AgentDone {
+                    channel: channel.clone(),
+                });
+                events
+            }
+            Request::Control { command } if command == "clear" => vec![Event::SystemMessage {
+                message: "Cleared.".to_string(),
+                channel: None,
+            }],
+            Request::Control { command } => vec![Event::Error {
+                message: format!("unknown command: {}", command),
+                channel: None,
+            }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::FakeBridge;
+    use super::*;
+
+    #[test]
+    fn requests_serialize_as_tagged_jsonl() {
+        let prompt = Request::Prompt {
+            text: "hi".to_string(),
+            channel: Some("heartbeat".to_string()),
+            provider: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&prompt).unwrap(),
+            r#"{"type":"prompt","text":"hi","channel":"heartbeat"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Request::Ping).unwrap(),
+            r#"{"type":"ping"}"#
+        );
+    }
+
+    #[test]
+    fn unknown_event_types_are_tolerated() {
+        let event: Event =
+            serde_json::from_str(r#"{"type":"model_switched","model":"x"}"#).unwrap();
+        assert_eq!(event, Event::Unknown);
+
+        let event: Event = serde_json::from_str(r#"{"type":"agent_chunk","chunk":"a"}"#).unwrap();
+        assert_eq!(
+            event,
+            Event::AgentChunk {
+                chunk: "a".to_string(),
+                channel: None
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_error_names_the_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing.sock");
+        let err = BridgeClient::connect(&path).await.err().unwrap();
+        assert!(matches!(err, BridgeError::Connect { .. }));
+        assert!(err.to_string().contains("missing.sock"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn ping_measures_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let bridge = FakeBridge::start(&dir.path().join("acomm.sock"), &[]);
+
+        let mut client = BridgeClient::connect(&bridge.path).await.unwrap();
+        let latency = client.ping().await.unwrap();
+        assert!(latency < Duration::from_secs(5));
+        assert_eq!(bridge.requests(), vec![Request::Ping]);
+    }
+
+    #[tokio::test]
+    async fn prompt_streams_reply_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let bridge = FakeBridge::start(&dir.path().join("acomm.sock"), &["Hel", "lo"]);
+
+        let mut client = BridgeClient::connect(&bridge.path).await.unwrap();
+        client.prompt("hi", Some("cli")).await.unwrap();
+
+        let mut reply = String::new();
+        loop {
+            match client.next_event().await.unwrap().unwrap() {
+                Event::AgentChunk { chunk, .. } => reply.push_str(&chunk),
+                Event::AgentDone { channel } => {
+                    assert_eq!(channel.as_deref(), Some("cli"));
+                    break;
+                }
+                Event::Prompt { text, .. } => assert_eq!(text, "hi"),
+                other => panic!("unexpected event {:?}", other),
+            }
+        }
+        assert_eq!(reply, "Hello");
+    }
+
+    #[tokio::test]
+    async fn control_is_broadcast_to_subscribers() {
+        let dir = tempfile::tempdir().unwrap();
+        let bridge = FakeBridge::start(&dir.path().join("acomm.sock"), &[]);
+
+        // A second client subscribed to the bridge sees the acknowledgement too.
+        let mut watcher = BridgeClient::connect(&bridge.path).await.unwrap();
+        let mut client = BridgeClient::connect(&bridge.path).await.unwrap();
+        // Make sure the watcher is registered before the command is sent.
+        client.ping().await.unwrap();
+        assert_eq!(watcher.next_event().await.unwrap(), Some(Event::Pong));
+
+        client.control("clear").await.unwrap();
+        let cleared = Some(Event::SystemMessage {
+            message: "Cleared.".to_string(),
+            channel: None,
+        });
+        assert_eq!(client.next_event().await.unwrap(), cleared);
+        assert_eq!(watcher.next_event().await.unwrap(), cleared);
+        assert_eq!(
+            bridge.requests(),
+            vec![
+                Request::Ping,
+                Request::Control {
+                    command: "clear".to_string()
+                }
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn watch_prompts_reports_channels_of_other_clients() {
+        let dir = tempfile::tempdir().unwrap();
+        let bridge = FakeBridge::start(&dir.path().join("acomm.sock"), &["ok"]);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let path = bridge.path.clone();
+        let watcher = tokio::spawn(async move {
+            watch_prompts(&path, |channel| {
+                let _ = tx.send(channel);
+            })
+            .await
+        });
+
+        // The watcher subscribes asynchronously; prompt until it has seen one.
+        let mut client = BridgeClient::connect(&bridge.path).await.unwrap();
+        let seen = loop {
+            client.prompt("hi", Some("discord")).await.unwrap();
+            if let Ok(seen) = tokio::time::timeout(Duration::from_millis(100), rx.recv()).await {
+                break seen;
+            }
+        };
+        assert_eq!(seen, Some(Some("discord".to_string())));
+        watcher.abort();
+    }
+
+    #[tokio::test]
+    async fn invalid_lines_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("acomm.sock");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(b"\nnot json\n").await.unwrap();
+        });
+
+        let mut client = BridgeClient::connect(&path).await.unwrap();
+        let err = client.next_event().await.unwrap_err();
+        assert!(matches!(err, BridgeError::InvalidEvent { .. }), "{}", err);
+        server.await.unwrap();
+        assert!(client.next_event().await.unwrap().is_none());
+    }
+}
@@ -1,4 +1,6 @@
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -7,14 +9,129 @@ use clap::{Parser, Subcommand};
     about = "YuiClaw — AI執事システム (amem + abeat + acomm + acore)"
 )]
 pub struct Cli {
+    /// 使用するプロファイル名 (ソケット・amem ルート・.env・abeat ジョブを分離する)
+    ///
+    /// 省略時は環境変数 YUICLAW_PROFILE、それもなければ default
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum AdapterCommands {
+    /// アダプターの一覧と状態を表示する
+    List,
+    /// 停止中のアダプターを起動する (デーモン実行中のみ)
+    Start {
+        /// アダプター名 (ntfy, discord, slack など)
+        name: String,
+    },
+    /// アダプターを停止する (次回のデーモン起動までの一時停止)
+    Stop {
+        /// アダプター名 (ntfy, discord, slack など)
+        name: String,
+    },
+    /// アダプターを再起動する
+    Restart {
+        /// アダプター名 (ntfy, discord, slack など)
+        name: String,
+    },
+    /// アダプターを有効にする (config.toml に保存)
+    Enable {
+        /// アダプター名 (ntfy, discord, slack など)
+        name: String,
+    },
+    /// アダプターを無効にする (config.toml に保存し、実行中なら停止する)
+    Disable {
+        /// アダプター名 (ntfy, discord, slack など)
+        name: String,
+    },
+    /// yuiclaw 組み込みのアダプターをフォアグラウンドで実行する (スーパーバイザーが利用)
+    #[command(hide = true)]
+    Run {
+        /// アダプター名 (telegram, matrix, email)
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DndCommands {
+    /// 定期ジョブと heartbeat の送信を止める (ユーザーからのメッセージは通常どおり)
+    On {
+        /// 期間 (例: 30m, 2h, 1d)。省略時は `yuiclaw dnd off` まで
+        #[arg(long = "for", value_name = "DURATION", value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+    /// おやすみモードを解除し、保留中のメッセージを送信する
+    Off,
+    /// おやすみモードと静かな時間帯 (quiet hours) の状態を表示する
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum JobsCommands {
+    /// yuiclaw が管理するジョブ (jobs.toml と組み込みの heartbeat) の一覧と状態を表示する
+    List {
+        /// 結果を JSON で出力する
+        #[arg(long)]
+        json: bool,
+    },
+    /// プロンプトを定期的に bridge に送るジョブを追加する (jobs.toml に保存)
+    Add {
+        /// ジョブ名 (英数字・`-`・`_`)
+        name: String,
+        /// 送信するプロンプト
+        #[arg(long)]
+        prompt: String,
+        /// チャンネル名 (省略時はジョブ名)
+        #[arg(short, long)]
+        channel: Option<String>,
+        /// 実行間隔 (例: 30m, 1h, 1d)
+        #[arg(long, required_unless_present = "cron", conflicts_with = "cron")]
+        every: Option<String>,
+        /// cron 形式のスケジュール (ローカル時刻。例: "0 9 * * 1-5" で平日 9:00)
+        #[arg(long)]
+        cron: Option<String>,
+        /// ジョブの説明
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// ジョブを削除する (jobs.toml から削除し、abeat からも削除する)
+    Remove {
+        /// ジョブ名
+        name: String,
+    },
+    /// ジョブを今すぐ実行する
+    Run {
+        /// ジョブ名
+        name: String,
+        /// スケジュール上の実行時刻を過ぎていなければ何もしない (abeat が利用)
+        #[arg(long, hide = true)]
+        if_due: bool,
+        /// 定期実行として扱う: 一時停止中やおやすみ時間中は何もしない (abeat が利用)
+        #[arg(long, hide = true)]
+        scheduled: bool,
+    },
+    /// ジョブを一時停止する (jobs.toml に enabled = false を保存)
+    Pause {
+        /// ジョブ名
+        name: String,
+    },
+    /// 一時停止したジョブを再開する
+    Resume {
+        /// ジョブ名
+        name: String,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 pub enum DaemonCommands {
     /// デーモン (bridge + adapters) をバックグラウンドで起動する
     Start,
+    /// プロセススーパーバイザーをフォアグラウンドで実行する (systemd などから利用)
+    Run,
     /// デーモンのステータスを表示する
     Status {
         /// ステータスを JSON 形式で出力する
@@ -27,6 +144,35 @@ pub enum DaemonCommands {
     Restart,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// 有効な設定値とその出所 (env / config file / .env / default) を表示する
+    ///
+    /// トークンなどのシークレットはマスクして表示する
+    Show,
+    /// 設定値を1つ表示する (シークレットもそのまま出力する)
+    Get {
+        /// 設定キー (例: provider, heartbeat.interval, DISCORD_BOT_TOKEN)
+        key: String,
+    },
+    /// config.toml に設定値を書き込む
+    Set {
+        /// 設定キー (例: provider, adapters.slack.enabled, DISCORD_BOT_TOKEN)
+        key: String,
+        /// 設定値
+        value: String,
+    },
+    /// config.toml から設定値を削除する
+    Unset {
+        /// 設定キー
+        key: String,
+    },
+    /// config.toml のパスを表示する
+    Path,
+    /// config.toml を $VISUAL / $EDITOR で開き、保存後に検証する
+    Edit,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// デーモン (bridge + adapters) をバックグラウンドで管理する
@@ -34,11 +180,33 @@ pub enum Commands {
         #[command(subcommand)]
         action: DaemonCommands,
     },
+    /// チャンネルアダプターを個別に管理する
+    Adapter {
+        #[command(subcommand)]
+        action: AdapterCommands,
+    },
+    /// スケジュールされたジョブ (jobs.toml) を管理する
+    Jobs {
+        #[command(subcommand)]
+        action: JobsCommands,
+    },
+    /// おやすみモード (do-not-disturb) を切り替える
+    Dnd {
+        #[command(subcommand)]
+        action: DndCommands,
+    },
+    /// 設定 (config.toml) を表示・編集する
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
     /// フルスタックを起動する (bridge + TUI)
     Start {
         /// 使用するAIプロバイダー [Gemini|Claude|Codex|OpenCode|Dummy]
-        #[arg(short, long, default_value = "Gemini")]
-        provider: String,
+        ///
+        /// 省略時は config.toml の provider、それもなければ Gemini
+        #[arg(short, long)]
+        provider: Option<String>,
     },
     /// Gemini プロバイダーで起動する (前回セッションがある場合は復元)
     Gemini {
@@ -76,18 +244,111 @@ pub enum Commands {
     Restart,
     /// 全コンポーネントのステータスを表示する
     Status,
+    /// 環境を診断し、問題ごとに対処方法を表示する
+    Doctor {
+        /// 結果を JSON で出力する
+        #[arg(long)]
+        json: bool,
+    },
     /// yuiclaw システムを初期化する (amem init + abeat init + デフォルトジョブ)
     Init,
     /// abeat の期限切れジョブを実行する
     Tick,
     /// 実行中の bridge にメッセージを送信する
     Pub {
-        /// 送信するメッセージ
-        message: String,
+        /// 送信するメッセージ (`-` で標準入力から読み込む)
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        message: Option<String>,
+        /// メッセージ本文をファイルから読み込む
+        #[arg(short, long, value_name = "PATH")]
+        file: Option<PathBuf>,
+        /// ファイルの内容をコンテキストとして添付する (複数指定可)
+        #[arg(short, long, value_name = "PATH")]
+        attach: Vec<PathBuf>,
         /// チャンネル名 (省略可)
         #[arg(short, long)]
         channel: Option<String>,
+        /// 定期ジョブからの送信として扱い、静かな時間帯とおやすみモードでは保留する
+        #[arg(long, hide = true)]
+        proactive: bool,
+    },
+    /// 実行中の bridge に質問を送り、エージェントの返答を標準出力にストリーミングする
+    Ask {
+        /// 質問
+        question: String,
+        /// 使用するAIプロバイダー [Gemini|Claude|Codex|OpenCode|Dummy] (省略時は現在のプロバイダー)
+        #[arg(short, long)]
+        provider: Option<String>,
+        /// 返答を待つ最大時間 (例: 60s, 5m, 1h。単位省略時は秒)
+        #[arg(long, default_value = "60s", value_parser = parse_duration)]
+        timeout: Duration,
+        /// 返答をまとめて JSON で出力する
+        #[arg(long)]
+        json: bool,
     },
     /// 実行中の対話セッションをリセットする (会話履歴・エージェントセッションをクリア)
     Reset,
+    /// Webhook を受け付け、認証済みの POST /webhook/<name> を bridge に送信する
+    ServeWebhook {
+        /// 待ち受けアドレス (省略時は WEBHOOK_LISTEN、未設定なら 127.0.0.1:8787)
+        #[arg(long, value_name = "ADDR")]
+        listen: Option<String>,
+    },
+    /// Prometheus 形式のメトリクスを出力する
+    Metrics {
+        /// 標準出力の代わりにファイルへアトミックに書き出す (node_exporter の textfile collector 用)
+        #[arg(long, value_name = "PATH")]
+        textfile: Option<PathBuf>,
+    },
+}
+
+/// `90s` / `5m` / `2h` / `1d` 形式、または単位なしの秒数を Duration に変換する
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (digits, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let invalid = || format!("invalid duration '{}' (e.g. 60s, 5m, 1h)", s);
+    let value: u64 = digits.parse().map_err(|_| invalid())?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return Err(invalid()),
+    };
+    let seconds = value.checked_mul(unit_seconds).ok_or_else(invalid)?;
+    if seconds == 0 {
+        return Err("duration must be greater than zero".to_string());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_accepts_units_and_bare_seconds() {
+        assert_eq!(parse_duration("60s"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86400)));
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_invalid_input() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("1.5h").is_err());
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("10w").is_err());
+        assert!(
+            parse_duration("999999999999999999d")
+                .unwrap_err()
+                .starts_with("invalid duration")
+        );
+    }
 }
@@ -1,15 +1,57 @@
+use crate::bridge::BridgeClient;
+use serde::Serialize;
 use std::path::Path;
+use std::time::Duration;
+use tokio::net::UnixStream;
 use tokio::process::Command;
 
-/// acomm bridge の Unix ソケットパス (acomm 内部と一致させる)
-pub const SOCKET_PATH: &str = "/tmp/acomm.sock";
+/// bridge の応答を待つ時間
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// acomm bridge の Unix ソケットパス (プロファイルごとに異なる。default は /tmp/acomm.sock)
+pub fn socket_path() -> &'static Path {
+    crate::profile::socket_path()
+}
 
 /// 各コンポーネントの稼働状況
 pub struct ComponentStatus {
     pub amem_available: bool,
     pub abeat_available: bool,
     pub acomm_available: bool,
+    /// bridge が接続を受け付けている (応答の有無は問わない)
     pub bridge_running: bool,
+    pub bridge: BridgeHealth,
+}
+
+/// bridge の死活確認 (ソケット経由) の結果
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum BridgeHealth {
+    /// ソケットファイルが存在しない
+    NotRunning,
+    /// ソケットファイルはあるが接続できない (クラッシュ後の残骸)
+    SocketFilePresent,
+    /// 接続は受け付けるが ping に応答しない
+    Accepting,
+    /// ping に応答した
+    Responding { latency_ms: f64 },
+}
+
+impl BridgeHealth {
+    /// bridge プロセスが生きているか (接続を受け付けているか)
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Accepting | Self::Responding { .. })
+    }
+
+    /// `status` 表示用の説明
+    pub fn describe(&self) -> String {
+        match self {
+            Self::NotRunning => "✗ not running".to_string(),
+            Self::SocketFilePresent => "✗ stale socket file (connection refused)".to_string(),
+            Self::Accepting => "⚠ accepting connections, not responding".to_string(),
+            Self::Responding { latency_ms } => format!("✓ responding ({:.1} ms)", latency_ms),
+        }
+    }
 }
 
 /// 全コンポーネントの状態を確認する
@@ -20,11 +62,13 @@ pub async fn detect() -> ComponentStatus {
         is_command_available("abeat"),
         is_command_available("acomm"),
     );
+    let bridge = probe_bridge(socket_path(), PROBE_TIMEOUT).await;
     ComponentStatus {
         amem_available: amem,
         abeat_available: abeat,
         acomm_available: acomm,
-        bridge_running: is_bridge_running(),
+        bridge_running: bridge.is_running(),
+        bridge,
     }
 }
 
@@ -40,9 +84,32 @@ pub async fn is_command_available(cmd: &str) -> bool {
         .unwrap_or(false)
 }
 
-/// acomm bridge ソケットが存在するか確認する
-pub fn is_bridge_running() -> bool {
-    Path::new(SOCKET_PATH).exists()
+/// acomm bridge がソケットで接続を受け付けているか確認する
+///
+/// ソケットファイルが残っているだけ (クラッシュ後など) の場合は false。
+pub async fn is_bridge_running() -> bool {
+    UnixStream::connect(socket_path()).await.is_ok()
+}
+
+/// bridge に接続して ping を送り、応答 (任意のイベント) までの往復時間を測る
+///
+/// 接続できなければソケットファイルの有無で NotRunning / SocketFilePresent を返す。
+/// `timeout` 以内に応答がなければ Accepting。
+pub async fn probe_bridge(path: &Path, timeout: Duration) -> BridgeHealth {
+    let Ok(mut client) = BridgeClient::connect(path).await else {
+        return if path.exists() {
+            BridgeHealth::SocketFilePresent
+        } else {
+            BridgeHealth::NotRunning
+        };
+    };
+
+    match tokio::time::timeout(timeout, client.ping()).await {
+        Ok(Ok(latency)) => BridgeHealth::Responding {
+            latency_ms: latency.as_secs_f64() * 1000.0,
+        },
+        _ => BridgeHealth::Accepting,
+    }
 }
 
 #[cfg(test)]
@@ -65,6 +132,57 @@ mod tests {
         let _ = status.bridge_running;
     }
 
+    #[tokio::test]
+    async fn probe_reports_not_running_without_socket_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let health = probe_bridge(&dir.path().join("acomm.sock"), PROBE_TIMEOUT).await;
+        assert_eq!(health, BridgeHealth::NotRunning);
+        assert!(!health.is_running());
+    }
+
+    #[tokio::test]
+    async fn probe_reports_stale_socket_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("acomm.sock");
+        // リスナーを閉じるとソケットファイルだけが残る
+        drop(tokio::net::UnixListener::bind(&path).unwrap());
+
+        let health = probe_bridge(&path, PROBE_TIMEOUT).await;
+        assert_eq!(health, BridgeHealth::SocketFilePresent);
+        assert!(!health.is_running());
+    }
+
+    #[tokio::test]
+    async fn probe_reports_accepting_when_bridge_is_silent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("acomm.sock");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            drop(stream);
+        });
+
+        let health = probe_bridge(&path, Duration::from_millis(100)).await;
+        assert_eq!(health, BridgeHealth::Accepting);
+        assert!(health.is_running());
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn probe_measures_latency_when_bridge_replies() {
+        let dir = tempfile::tempdir().unwrap();
+        let bridge = crate::bridge::fake::FakeBridge::start(&dir.path().join("acomm.sock"), &[]);
+
+        let health = probe_bridge(&bridge.path, PROBE_TIMEOUT).await;
+        assert!(
+            matches!(health, BridgeHealth::Responding { .. }),
+            "{:?}",
+            health
+        );
+        assert!(health.describe().starts_with("✓ responding ("));
+    }
+
     #[tokio::test]
     async fn test_is_command_available_for_nonexistent() {
         // 存在しないコマンドは false を返す
@@ -0,0 +1,1411 @@
+use crate::adapters;
+use crate::notify::EventKind;
+use crate::profile;
+use crate::supervisor::RestartMode;
+use crate::webhook;
+use chrono::NaiveTime;
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use toml_edit::{DocumentMut, Item, TableLike};
+
+/// Providers accepted by `acomm-tui --provider`, in their canonical spelling.
+pub const PROVIDERS: [&str; 5] = ["Gemini", "Claude", "Codex", "OpenCode", "Dummy"];
+
+/// Provider used when neither `--provider` nor `provider` in config.toml is given.
+pub const DEFAULT_PROVIDER: &str = "Gemini";
+
+/// Interval of the heartbeat job when `[heartbeat] interval` is not set.
+pub const DEFAULT_HEARTBEAT_INTERVAL: &str = "30m";
+
+/// Typed settings that are exported to child processes as environment variables.
+const PATH_SETTINGS: [(&str, &str); 3] = [
+    ("socket_path", "ACOMM_SOCKET_PATH"),
+    ("workdir", "YUICLAW_HOME"),
+    ("amem_root", "AMEM_ROOT"),
+];
+
+static CURRENT: OnceLock<Config> = OnceLock::new();
+static ORIGINS: OnceLock<Origins> = OnceLock::new();
+
+/// Which variables came from where before the configuration layers were merged into the
+/// environment. Used by `yuiclaw config show` to attribute each effective value.
+#[derive(Debug, Clone, Default)]
+pub struct Origins {
+    /// Variables already present in the process environment.
+    pub process_env: HashSet<String>,
+    /// Keys defined in the profile `.env`.
+    pub dotenv: HashSet<String>,
+}
+
+/// Where an effective setting comes from, in order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Env,
+    ConfigFile,
+    DotEnv,
+    Default,
+    Unset,
+}
+
+impl Source {
+    fn label(self) -> &'static str {
+        match self {
+            Source::Env => "env",
+            Source::ConfigFile => "config file",
+            Source::DotEnv => ".env",
+            Source::Default => "default",
+            Source::Unset => "-",
+        }
+    }
+}
+
+/// One row of `yuiclaw config show`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub key: String,
+    pub value: Option<String>,
+    pub source: Source,
+}
+
+/// Typed contents of `config.toml` (`~/.config/yuiclaw/config.toml` for the default profile).
+///
+/// Every field is optional; a missing file is the same as an empty one. Settings that
+/// child processes read from the environment (`socket_path`, `workdir`, `amem_root`, the
+/// `[env]` table and each adapter's `env` table) are exported as environment variables by
+/// [`Config::env_defaults`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Provider used by `yuiclaw` / `yuiclaw start` without `--provider`.
+    #[serde(deserialize_with = "deserialize_provider")]
+    pub provider: Option<String>,
+    /// Bridge socket (`ACOMM_SOCKET_PATH`).
+    pub socket_path: Option<PathBuf>,
+    /// Working directory of bridge sessions (`YUICLAW_HOME`).
+    pub workdir: Option<PathBuf>,
+    /// Memory store root (`AMEM_ROOT`).
+    pub amem_root: Option<PathBuf>,
+    pub tui: TuiPreference,
+    pub heartbeat: HeartbeatConfig,
+    /// When proactive prompts are held back (see [`crate::quiet`]).
+    pub quiet_hours: QuietHoursConfig,
+    /// Per-adapter settings keyed by adapter name (`ntfy`, `discord`, `slack`, or a name
+    /// declared here; see [`crate::adapters`]).
+    #[serde(deserialize_with = "deserialize_adapters")]
+    pub adapters: BTreeMap<String, AdapterConfig>,
+    /// Routes of `yuiclaw serve-webhook`.
+    pub webhook: WebhookConfig,
+    /// Outbound webhooks notified of daemon events, keyed by a name of your choice (see
+    /// [`crate::notify`]).
+    pub notify: BTreeMap<String, NotifyTarget>,
+    /// HTTP control API served by the daemon (see [`crate::api`]).
+    #[serde(deserialize_with = "deserialize_api")]
+    pub api: ApiConfig,
+    /// Extra environment variables, e.g. API keys read by provider CLIs.
+    pub env: BTreeMap<String, String>,
+}
+
+/// Which TUI `yuiclaw start` launches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TuiPreference {
+    /// `acomm-tui` if it is in `PATH`, otherwise `acomm`.
+    #[default]
+    Auto,
+    /// Always the TypeScript TUI (`acomm-tui`).
+    AcommTui,
+    /// Always the Rust TUI (`acomm`).
+    Acomm,
+}
+
+impl TuiPreference {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TuiPreference::Auto => "auto",
+            TuiPreference::AcommTui => "acomm-tui",
+            TuiPreference::Acomm => "acomm",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeartbeatConfig {
+    /// abeat interval such as `30m`, `1h` or `90s`.
+    #[serde(deserialize_with = "deserialize_interval")]
+    pub interval: String,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_HEARTBEAT_INTERVAL.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuietHoursConfig {
+    /// IANA time zone of `periods`, such as `Asia/Tokyo`; the system time zone when unset.
+    #[serde(deserialize_with = "deserialize_timezone")]
+    pub timezone: Option<chrono_tz::Tz>,
+    /// What happens to a proactive prompt during quiet hours.
+    pub action: QuietAction,
+    pub periods: Vec<QuietPeriod>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuietAction {
+    /// Keep the latest prompt of each channel and publish it when quiet hours end.
+    #[default]
+    Queue,
+    /// Discard the prompt.
+    Drop,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuietPeriod {
+    /// Days the period starts on, as in cron (`mon-fri`, `sat,sun`); every day when unset.
+    #[serde(default, deserialize_with = "deserialize_weekdays")]
+    pub days: Option<String>,
+    /// Start time, `HH:MM`.
+    #[serde(deserialize_with = "deserialize_clock_time")]
+    pub start: NaiveTime,
+    /// End time, `HH:MM`. An end before the start is on the next day; an end equal to the
+    /// start makes the period last the whole day.
+    #[serde(deserialize_with = "deserialize_clock_time")]
+    pub end: NaiveTime,
+}
+
+impl QuietPeriod {
+    /// The days of [`QuietPeriod::days`], indexed by `Weekday::num_days_from_sunday`.
+    pub fn weekdays(&self) -> u8 {
+        match &self.days {
+            Some(days) => crate::cron::parse_weekdays(days).unwrap_or_default(),
+            None => 0x7f,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// How requests to `POST /webhook/<name>` become messages, keyed by `<name>`. Names
+    /// without a route are accepted with the defaults.
+    #[serde(deserialize_with = "deserialize_webhook_routes")]
+    pub routes: BTreeMap<String, WebhookRoute>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookRoute {
+    /// Channel the message is published on (defaults to the route name).
+    pub channel: Option<String>,
+    /// Message with `{{field.path}}` placeholders filled in from the JSON payload. Without
+    /// one, the payload itself is the message.
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    /// Where the daemon serves the API. The API is off when unset.
+    pub listen: Option<ApiListen>,
+    /// Token required as `Authorization: Bearer <token>`; mandatory with a TCP address.
+    pub token: Option<String>,
+}
+
+/// `listen` of `[api]`: `unix` (`api.sock` in the state directory), `unix:<path>`, or a
+/// loopback address such as `127.0.0.1:8788`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum ApiListen {
+    Unix(Option<PathBuf>),
+    Tcp(SocketAddr),
+}
+
+impl TryFrom<String> for ApiListen {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, String> {
+        if raw == "unix" {
+            return Ok(Self::Unix(None));
+        }
+        if let Some(path) = raw.strip_prefix("unix:") {
+            return Ok(Self::Unix(Some(PathBuf::from(expand_home(Path::new(
+                path,
+            ))))));
+        }
+        let addr = match raw.strip_prefix("localhost:") {
+            Some(port) => port
+                .parse()
+                .map(|port| SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+                .ok(),
+            None => raw.parse::<SocketAddr>().ok(),
+        };
+        match addr {
+            Some(addr) if addr.ip().is_loopback() => Ok(Self::Tcp(addr)),
+            Some(_) => Err(format!(
+                "api listen address '{}' is not on localhost; use a Unix socket or 127.0.0.1",
+                raw
+            )),
+            None => Err(format!(
+                "invalid api listen address '{}' (expected unix, unix:<path> or 127.0.0.1:<port>)",
+                raw
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyTarget {
+    /// URL the events are POSTed to.
+    #[serde(deserialize_with = "deserialize_notify_url")]
+    pub url: String,
+    /// Events sent to this URL; every event when empty.
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    /// Key of the `X-Hub-Signature-256` HMAC sent with every payload.
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+impl NotifyTarget {
+    pub fn subscribes_to(&self, kind: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdapterConfig {
+    /// Set to `false` to keep a configured adapter from being started.
+    pub enabled: bool,
+    /// Environment variables for the adapter, e.g. `DISCORD_BOT_TOKEN`.
+    pub env: BTreeMap<String, String>,
+    /// Display name (defaults to the built-in label, or the section name).
+    pub label: Option<String>,
+    /// Program that runs the adapter (`acomm` unless set).
+    pub command: Option<String>,
+    /// Arguments passed to `command`. Extra adapters must set `command` or `args`.
+    pub args: Option<Vec<String>>,
+    /// Working directory of the adapter process (defaults to the bridge session workdir).
+    pub workdir: Option<PathBuf>,
+    /// When the supervisor restarts the adapter: `always` (default), `on-failure`, `never`.
+    pub restart: Option<RestartMode>,
+    /// Variables that must all be set for the adapter to be started.
+    pub required_env: Option<Vec<String>>,
+    /// Variables the adapter reads if they are set.
+    pub optional_env: Option<Vec<String>>,
+    /// Shell command that must succeed for a running adapter to count as connected.
+    pub health_command: Option<String>,
+    pub docs_url: Option<String>,
+}
+
+impl Default for AdapterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            env: BTreeMap::new(),
+            label: None,
+            command: None,
+            args: None,
+            workdir: None,
+            restart: None,
+            required_env: None,
+            optional_env: None,
+            health_command: None,
+            docs_url: None,
+        }
+    }
+}
+
+impl Config {
+    /// Parse `config.toml` contents. Errors carry the line and column of the offending value.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|e| e.to_string().trim_end().to_string())
+    }
+
+    /// Load `path`, treating a missing file as an empty configuration.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                Self::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    /// Environment variables this configuration provides, in the order they are applied.
+    ///
+    /// The top-level `[env]` table comes first, then adapter `env` tables, then the typed
+    /// path settings, so a typed setting wins over the same key spelled out in a table.
+    pub fn env_defaults(&self) -> Vec<(String, String)> {
+        let mut vars: Vec<(String, String)> = self
+            .env
+            .iter()
+            .chain(self.adapters.values().flat_map(|a| a.env.iter()))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        let paths = [
+            ("ACOMM_SOCKET_PATH", &self.socket_path),
+            ("YUICLAW_HOME", &self.workdir),
+            ("AMEM_ROOT", &self.amem_root),
+        ];
+        for (key, value) in paths {
+            if let Some(path) = value {
+                vars.retain(|(k, _)| k != key);
+                vars.push((key.to_string(), expand_home(path)));
+            }
+        }
+        vars
+    }
+
+    /// Whether the adapter `name` (case-insensitive) may be started. Adapters without a
+    /// section are enabled.
+    pub fn adapter_enabled(&self, name: &str) -> bool {
+        self.adapters
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .is_none_or(|(_, adapter)| adapter.enabled)
+    }
+
+    /// Provider to use when none is given on the command line.
+    pub fn default_provider(&self) -> &str {
+        self.provider.as_deref().unwrap_or(DEFAULT_PROVIDER)
+    }
+}
+
+/// Expand a leading `~/` so that paths in config.toml may be written relative to home.
+pub(crate) fn expand_home(path: &Path) -> String {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Store the loaded configuration and where its values came from. Called once by
+/// [`crate::profile::init`].
+pub fn install(config: Config, origins: Origins) -> &'static Config {
+    let _ = ORIGINS.set(origins);
+    CURRENT.get_or_init(|| config)
+}
+
+/// The active profile's configuration. Empty if none has been installed (unit tests).
+pub fn current() -> &'static Config {
+    CURRENT.get_or_init(Config::default)
+}
+
+/// `yuiclaw config show`: print every effective setting with its source, masking secrets.
+pub fn show() -> Result<(), Box<dyn std::error::Error>> {
+    let path = profile::current().config_path();
+    let settings = current_settings(&path);
+
+    println!("=== YuiClaw Config ===");
+    println!();
+    if !profile::current().is_default() {
+        println!("Profile: {}", profile::current().name);
+    }
+    println!(
+        "File: {}{}",
+        path.display(),
+        if path.exists() { "" } else { " (not found)" }
+    );
+    println!();
+
+    let key_width = settings.iter().map(|s| s.key.len()).max().unwrap_or(0);
+    let value_width = settings
+        .iter()
+        .map(|s| display_value(s).chars().count())
+        .max()
+        .unwrap_or(0);
+    for setting in &settings {
+        let value = display_value(setting);
+        let padding = value_width - value.chars().count();
+        println!(
+            "  {:key_width$}  {}{}  ({})",
+            setting.key,
+            value,
+            " ".repeat(padding),
+            setting.source.label()
+        );
+    }
+    Ok(())
+}
+
+/// `yuiclaw config get KEY`: print the effective value unmasked, for use in scripts.
+pub fn get(key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = profile::current().config_path();
+    let settings = current_settings(&path);
+    let wanted = canonical_key(key);
+
+    let value = match settings.iter().find(|s| s.key == wanted) {
+        Some(setting) => setting.value.clone(),
+        None if is_env_var_name(&wanted) => std::env::var(&wanted).ok(),
+        None => return Err(format!("Unknown setting '{}'.", key).into()),
+    };
+    match value {
+        Some(value) => {
+            println!("{}", value);
+            Ok(())
+        }
+        None => Err(format!("'{}' is not set.", key).into()),
+    }
+}
+
+/// `yuiclaw config set KEY VALUE`: write a value to config.toml, keeping comments and layout.
+pub fn set(key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = profile::current().config_path();
+    let contents = read_config_file(&path)?;
+    let updated = with_setting(&contents, key, value)?;
+    write_config_file(&path, &updated)?;
+
+    let wanted = canonical_key(key);
+    if is_secret(&wanted) {
+        println!("Set {} in {}", wanted, path.display());
+    } else {
+        println!("Set {} = {} in {}", wanted, value, path.display());
+    }
+    if let Some(var) = env_var_for(&wanted)
+        && origins().process_env.contains(var)
+    {
+        println!(
+            "Note: {} is set in the environment, which takes precedence over config.toml.",
+            var
+        );
+    }
+    Ok(())
+}
+
+/// Persist `adapters.<section>.enabled` in config.toml, keeping comments and layout.
+pub fn write_adapter_enabled(section: &str, enabled: bool) -> Result<(), String> {
+    let path = profile::current().config_path();
+    let contents = read_config_file(&path)?;
+    let key = format!("adapters.{}.enabled", section);
+    let updated = with_setting(&contents, &key, &enabled.to_string())?;
+    write_config_file(&path, &updated).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// `yuiclaw config unset KEY`: remove a value from config.toml.
+pub fn unset(key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = profile::current().config_path();
+    let contents = read_config_file(&path)?;
+    let updated = without_setting(&contents, key)?;
+    write_config_file(&path, &updated)?;
+    println!("Removed {} from {}", canonical_key(key), path.display());
+    Ok(())
+}
+
+/// `yuiclaw config path`
+pub fn print_path() -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", profile::current().config_path().display());
+    Ok(())
+}
+
+/// `yuiclaw config edit`: open config.toml in `$VISUAL` / `$EDITOR` (default `vi`) and
+/// validate it once the editor exits.
+pub fn edit() -> Result<(), Box<dyn std::error::Error>> {
+    let path = profile::current().config_path();
+    if !path.exists() {
+        write_config_file(&path, "")?;
+    }
+
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .find(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    // Run through sh so that editors configured with arguments (e.g. `code --wait`) work.
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(&path)
+        .status()
+        .map_err(|e| format!("Failed to launch editor '{}': {}", editor, e))?;
+    if !status.success() {
+        return Err(format!("Editor '{}' exited with {}", editor, status).into());
+    }
+
+    Config::load(&path)?;
+    println!("{} is valid.", path.display());
+    Ok(())
+}
+
+fn origins() -> &'static Origins {
+    ORIGINS.get_or_init(Origins::default)
+}
+
+/// Effective settings of the running process. A config.toml that fails to parse contributes
+/// nothing (the error has already been reported by [`crate::profile::init`]).
+fn current_settings(path: &Path) -> Vec<Setting> {
+    let file = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| contents.parse::<toml::Table>().ok())
+        .unwrap_or_default();
+    effective_settings(current(), &file, origins(), |key| std::env::var(key).ok())
+}
+
+/// Build the rows of `yuiclaw config show`.
+///
+/// `file` is the raw config.toml table, used to tell explicit values from defaults;
+/// `lookup` reads the merged environment.
+fn effective_settings(
+    config: &Config,
+    file: &toml::Table,
+    origins: &Origins,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Vec<Setting> {
+    let typed_source = |path: &[&str]| {
+        if file_contains(file, path) {
+            Source::ConfigFile
+        } else {
+            Source::Default
+        }
+    };
+    let config_env: HashSet<String> = config.env_defaults().into_iter().map(|(k, _)| k).collect();
+    let env_source = |var: &str, value: &Option<String>| {
+        if origins.process_env.contains(var) {
+            Source::Env
+        } else if config_env.contains(var) {
+            Source::ConfigFile
+        } else if origins.dotenv.contains(var) {
+            Source::DotEnv
+        } else if value.is_some() {
+            Source::Default
+        } else {
+            Source::Unset
+        }
+    };
+    let lookup = |var: &str| lookup(var).filter(|v| !v.trim().is_empty());
+
+    let mut settings = vec![
+        Setting {
+            key: "provider".to_string(),
+            value: Some(config.default_provider().to_string()),
+            source: typed_source(&["provider"]),
+        },
+        Setting {
+            key: "tui".to_string(),
+            value: Some(config.tui.as_str().to_string()),
+            source: typed_source(&["tui"]),
+        },
+        Setting {
+            key: "heartbeat.interval".to_string(),
+            value: Some(config.heartbeat.interval.clone()),
+            source: typed_source(&["heartbeat", "interval"]),
+        },
+    ];
+
+    for (key, var) in PATH_SETTINGS {
+        let value = lookup(var);
+        settings.push(Setting {
+            key: key.to_string(),
+            source: env_source(var, &value),
+            value,
+        });
+    }
+
+    let registry = adapters::registry(config);
+    for name in registry.iter().map(|adapter| &adapter.name) {
+        let source = config
+            .adapters
+            .keys()
+            .find(|key| key.eq_ignore_ascii_case(name))
+            .map(|key| typed_source(&["adapters", key, "enabled"]))
+            .unwrap_or(Source::Default);
+        settings.push(Setting {
+            value: Some(config.adapter_enabled(name).to_string()),
+            key: format!("adapters.{}.enabled", name),
+            source,
+        });
+    }
+
+    // Adapter credentials first, then every other variable yuiclaw provides.
+    let mut vars: Vec<String> = Vec::new();
+    for key in registry
+        .iter()
+        .flat_map(|adapter| adapter.required_env.iter().chain(&adapter.optional_env))
+    {
+        if !vars.contains(key) {
+            vars.push(key.clone());
+        }
+    }
+    let mut extra: Vec<String> = config_env
+        .iter()
+        .chain(origins.dotenv.iter())
+        .filter(|var| !vars.contains(var) && env_var_setting(var).is_none())
+        .cloned()
+        .collect();
+    extra.sort();
+    extra.dedup();
+    vars.extend(extra);
+    for var in vars {
+        let value = lookup(&var);
+        settings.push(Setting {
+            source: env_source(&var, &value),
+            key: var,
+            value,
+        });
+    }
+
+    settings
+}
+
+fn display_value(setting: &Setting) -> String {
+    match &setting.value {
+        Some(value) if is_secret(&setting.key) => mask(value),
+        Some(value) => value.clone(),
+        None => "(unset)".to_string(),
+    }
+}
+
+/// Whether a value should be masked: tokens, secrets, passwords and API keys.
+fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    ["TOKEN", "SECRET", "PASSWORD", "PASSWD"]
+        .iter()
+        .any(|word| key.contains(word))
+        || key.ends_with("_KEY")
+}
+
+/// Keep a short prefix of long secrets so that different tokens can still be told apart.
+fn mask(value: &str) -> String {
+    if value.chars().count() >= 12 {
+        format!("{}****", value.chars().take(4).collect::<String>())
+    } else {
+        "****".to_string()
+    }
+}
+
+/// Name used for `key` in `config show`: `ACOMM_SOCKET_PATH` → `socket_path`,
+/// `env.FOO` → `FOO`.
+fn canonical_key(key: &str) -> String {
+    let key = key.trim();
+    if let Some(typed) = env_var_setting(key) {
+        return typed.to_string();
+    }
+    key.strip_prefix("env.").unwrap_or(key).to_string()
+}
+
+/// Typed setting backed by the environment variable `var`, if any.
+fn env_var_setting(var: &str) -> Option<&'static str> {
+    PATH_SETTINGS
+        .iter()
+        .find(|(_, v)| *v == var)
+        .map(|(key, _)| *key)
+}
+
+/// Environment variable behind `key`: itself for variable names, or the variable a typed
+/// path setting is exported as.
+fn env_var_for(key: &str) -> Option<&str> {
+    if let Some((_, var)) = PATH_SETTINGS.iter().find(|(k, _)| *k == key) {
+        return Some(var);
+    }
+    is_env_var_name(key).then_some(key)
+}
+
+/// `DISCORD_BOT_TOKEN`-style names are environment variables; dotted lowercase keys are
+/// config.toml paths.
+fn is_env_var_name(key: &str) -> bool {
+    !key.is_empty()
+        && key.chars().any(|c| c.is_ascii_uppercase())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Location of `key` in config.toml. Environment variables live in the `[env]` table.
+fn document_path(key: &str) -> Vec<String> {
+    let key = canonical_key(key);
+    if is_env_var_name(&key) {
+        vec!["env".to_string(), key]
+    } else {
+        key.split('.').map(str::to_string).collect()
+    }
+}
+
+fn file_contains(file: &toml::Table, path: &[&str]) -> bool {
+    let Some((last, parents)) = path.split_last() else {
+        return false;
+    };
+    let mut table = file;
+    for part in parents {
+        match table.get(*part).and_then(toml::Value::as_table) {
+            Some(next) => table = next,
+            None => return false,
+        }
+    }
+    table.contains_key(*last)
+}
+
+/// Return `contents` with `key` set to `value`.
+///
+/// The value is written as a string when that is valid for the key, otherwise as a TOML
+/// literal (so `adapters.slack.enabled false` stores a boolean). The result must still be a
+/// valid configuration.
+fn with_setting(contents: &str, key: &str, value: &str) -> Result<String, String> {
+    let mut candidates = vec![toml_edit::value(value)];
+    if let Ok(literal) = value.parse::<toml_edit::Value>() {
+        candidates.push(Item::Value(literal));
+    }
+
+    let mut first_error = None;
+    for item in candidates {
+        let mut doc = parse_document(contents)?;
+        insert_path(&mut doc, &document_path(key), item)?;
+        let updated = doc.to_string();
+        match toml::from_str::<Config>(&updated) {
+            Ok(_) => return Ok(updated),
+            Err(e) => {
+                first_error.get_or_insert_with(|| e.message().to_string());
+            }
+        }
+    }
+    Err(format!(
+        "Invalid value for '{}': {}",
+        key,
+        first_error.unwrap_or_default()
+    ))
+}
+
+/// Return `contents` without `key`.
+fn without_setting(contents: &str, key: &str) -> Result<String, String> {
+    let mut doc = parse_document(contents)?;
+    let path = document_path(key);
+    let (last, parents) = path.split_last().ok_or("Empty key.")?;
+
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+    for part in parents {
+        table = table
+            .get_mut(part)
+            .and_then(Item::as_table_like_mut)
+            .ok_or_else(|| format!("'{}' is not set in config.toml.", key))?;
+    }
+    table
+        .remove(last)
+        .ok_or_else(|| format!("'{}' is not set in config.toml.", key))?;
+    Ok(doc.to_string())
+}
+
+fn parse_document(contents: &str) -> Result<DocumentMut, String> {
+    contents
+        .parse::<DocumentMut>()
+        .map_err(|e| format!("config.toml: {}", e.to_string().trim_end()))
+}
+
+fn insert_path(doc: &mut DocumentMut, path: &[String], value: Item) -> Result<(), String> {
+    let (last, parents) = path.split_last().ok_or("Empty key.")?;
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+    for part in parents {
+        let entry = table.entry(part).or_insert_with(|| {
+            let mut new_table = toml_edit::Table::new();
+            new_table.set_implicit(true);
+            Item::Table(new_table)
+        });
+        table = entry
+            .as_table_like_mut()
+            .ok_or_else(|| format!("'{}' is not a table in config.toml.", part))?;
+    }
+    match (table.get_mut(last), value) {
+        // Replace in place so that the key's comments and the value's trailing comment stay.
+        (Some(Item::Value(existing)), Item::Value(mut value)) => {
+            *value.decor_mut() = existing.decor().clone();
+            *existing = value;
+        }
+        (_, value) => {
+            table.insert(last, value);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn read_config_file(path: &Path) -> Result<String, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Ok(contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+pub(crate) fn write_config_file(path: &Path, contents: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("toml.tmp");
+    // config.toml may hold tokens, so keep it private to the user.
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)?;
+    file.write_all(contents.as_bytes())?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Canonical spelling of `name` if it is a known provider (case-insensitive).
+pub fn canonical_provider(name: &str) -> Option<&'static str> {
+    PROVIDERS
+        .iter()
+        .copied()
+        .find(|p| p.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Whether `value` is an abeat interval: a positive integer followed by `s`, `m`, `h` or `d`.
+pub fn is_valid_interval(value: &str) -> bool {
+    interval_seconds(value).is_some()
+}
+
+/// Seconds in an abeat interval such as `30m`. `None` when it is not a valid interval or
+/// is too long to schedule.
+pub fn interval_seconds(value: &str) -> Option<i64> {
+    let unit = value.chars().last()?;
+    let digits = &value[..value.len() - unit.len_utf8()];
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let unit_seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _ => return None,
+    };
+    let seconds = digits.parse::<i64>().ok()?.checked_mul(unit_seconds)?;
+    (seconds > 0 && chrono::TimeDelta::try_seconds(seconds).is_some()).then_some(seconds)
+}
+
+fn deserialize_provider<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    canonical_provider(&raw)
+        .map(|p| Some(p.to_string()))
+        .ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "unknown provider '{}', expected one of: {}",
+                raw,
+                PROVIDERS.join(", ")
+            ))
+        })
+}
+
+fn deserialize_adapters<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, AdapterConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let adapters = BTreeMap::<String, AdapterConfig>::deserialize(deserializer)?;
+    for (name, section) in &adapters {
+        adapters::validate(name, section).map_err(serde::de::Error::custom)?;
+    }
+    adapters::validate_unique(&adapters).map_err(serde::de::Error::custom)?;
+    Ok(adapters)
+}
+
+fn deserialize_webhook_routes<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, WebhookRoute>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let routes = BTreeMap::<String, WebhookRoute>::deserialize(deserializer)?;
+    if let Some(name) = routes
+        .keys()
+        .find(|name| !webhook::is_valid_route_name(name))
+    {
+        return Err(serde::de::Error::custom(format!(
+            "invalid webhook route name '{}' (use letters, digits, '-' and '_')",
+            name
+        )));
+    }
+    Ok(routes)
+}
+
+fn deserialize_api<'de, D>(deserializer: D) -> Result<ApiConfig, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let api = ApiConfig::deserialize(deserializer)?;
+    if matches!(api.listen, Some(ApiListen::Tcp(_))) && api.token.is_none() {
+        return Err(serde::de::Error::custom(
+            "api listens on a TCP address, so it needs a token",
+        ));
+    }
+    Ok(api)
+}
+
+fn deserialize_notify_url<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let url = String::deserialize(deserializer)?;
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(serde::de::Error::custom(format!(
+            "invalid notify URL '{}' (expected http:// or https://)",
+            url
+        )));
+    }
+    Ok(url)
+}
+
+fn deserialize_timezone<'de, D>(deserializer: D) -> Result<Option<chrono_tz::Tz>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    raw.parse().map(Some).map_err(|_| {
+        serde::de::Error::custom(format!(
+            "unknown time zone '{}', expected an IANA name such as Asia/Tokyo",
+            raw
+        ))
+    })
+}
+
+fn deserialize_weekdays<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    crate::cron::parse_weekdays(&raw)
+        .map(|_| Some(raw.clone()))
+        .map_err(|e| serde::de::Error::custom(format!("invalid days '{}': {}", raw, e)))
+}
+
+fn deserialize_clock_time<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&raw, "%H:%M").map_err(|_| {
+        serde::de::Error::custom(format!(
+            "invalid time '{}', expected HH:MM (e.g. 22:30)",
+            raw
+        ))
+    })
+}
+
+fn deserialize_interval<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    if is_valid_interval(&raw) {
+        Ok(raw)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "invalid interval '{}', expected a number followed by s, m, h or d (e.g. 30m)",
+            raw
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(config.default_provider(), "Gemini");
+        assert_eq!(config.heartbeat.interval, "30m");
+        assert_eq!(config.tui, TuiPreference::Auto);
+        assert!(config.env_defaults().is_empty());
+    }
+
+    #[test]
+    fn parses_full_config() {
+        let config = Config::parse(
+            r#"
+provider = "claude"
+socket_path = "/run/user/1000/acomm.sock"
+workdir = "/srv/yuiclaw"
+tui = "acomm"
+
+[heartbeat]
+interval = "1h"
+
+[adapters.discord]
+env = { DISCORD_BOT_TOKEN = "xyz" }
+
+[adapters.slack]
+enabled = false
+
+[env]
+GEMINI_API_KEY = "abc"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.default_provider(), "Claude");
+        assert_eq!(config.tui, TuiPreference::Acomm);
+        assert_eq!(config.heartbeat.interval, "1h");
+        assert!(config.adapter_enabled("Discord"));
+        assert!(!config.adapter_enabled("Slack"));
+        assert!(config.adapter_enabled("ntfy"));
+        assert_eq!(
+            config.env_defaults(),
+            vec![
+                ("GEMINI_API_KEY".to_string(), "abc".to_string()),
+                ("DISCORD_BOT_TOKEN".to_string(), "xyz".to_string()),
+                (
+                    "ACOMM_SOCKET_PATH".to_string(),
+                    "/run/user/1000/acomm.sock".to_string()
+                ),
+                ("YUICLAW_HOME".to_string(), "/srv/yuiclaw".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn paths_expand_leading_tilde() {
+        let home = dirs::home_dir().unwrap();
+        let config = Config::parse("workdir = \"~/yuiclaw\"\n").unwrap();
+        assert_eq!(
+            config.env_defaults(),
+            vec![(
+                "YUICLAW_HOME".to_string(),
+                home.join("yuiclaw").to_string_lossy().into_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn typed_settings_win_over_env_table() {
+        let config =
+            Config::parse("amem_root = \"/data/amem\"\n[env]\nAMEM_ROOT = \"/elsewhere\"\n")
+                .unwrap();
+        assert_eq!(
+            config.env_defaults(),
+            vec![("AMEM_ROOT".to_string(), "/data/amem".to_string())]
+        );
+    }
+
+    #[test]
+    fn syntax_errors_report_line_numbers() {
+        let err = Config::parse("provider = \"Gemini\"\nsocket_path = /tmp/x\n").unwrap_err();
+        assert!(err.contains("line 2"), "{}", err);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected_with_line_numbers() {
+        let err = Config::parse("\n\n[heartbeat]\nintervall = \"1h\"\n").unwrap_err();
+        assert!(err.contains("line 4"), "{}", err);
+        assert!(err.contains("intervall"), "{}", err);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected_with_line_numbers() {
+        let err = Config::parse("provider = \"gpt\"\n").unwrap_err();
+        assert!(err.contains("line 1"), "{}", err);
+        assert!(err.contains("unknown provider 'gpt'"), "{}", err);
+
+        let err = Config::parse("[heartbeat]\ninterval = \"soon\"\n").unwrap_err();
+        assert!(err.contains("line 2"), "{}", err);
+
+        let err = Config::parse("tui = \"vim\"\n").unwrap_err();
+        assert!(err.contains("line 1"), "{}", err);
+    }
+
+    #[test]
+    fn missing_file_is_an_empty_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::load(&dir.path().join("config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn load_errors_name_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "provider = \n").unwrap();
+        let err = Config::load(&path).unwrap_err();
+        assert!(err.starts_with(&path.display().to_string()), "{}", err);
+    }
+
+    fn origins(process_env: &[&str], dotenv: &[&str]) -> Origins {
+        Origins {
+            process_env: process_env.iter().map(|k| k.to_string()).collect(),
+            dotenv: dotenv.iter().map(|k| k.to_string()).collect(),
+        }
+    }
+
+    fn find<'a>(settings: &'a [Setting], key: &str) -> &'a Setting {
+        settings
+            .iter()
+            .find(|s| s.key == key)
+            .unwrap_or_else(|| panic!("{} should be listed", key))
+    }
+
+    #[test]
+    fn effective_settings_attribute_each_layer() {
+        let contents = "provider = \"codex\"\n[env]\nDISCORD_BOT_TOKEN = \"from-config\"\n";
+        let config = Config::parse(contents).unwrap();
+        let file: toml::Table = contents.parse().unwrap();
+        let env: HashMap<&str, &str> = [
+            ("DISCORD_BOT_TOKEN", "from-config"),
+            ("NTFY_TOPIC", "from-env"),
+            ("SLACK_BOT_TOKEN", "from-dotenv"),
+            ("ACOMM_SOCKET_PATH", "/tmp/acomm.sock"),
+        ]
+        .into_iter()
+        .collect();
+        let settings = effective_settings(
+            &config,
+            &file,
+            &origins(&["NTFY_TOPIC"], &["SLACK_BOT_TOKEN", "DISCORD_BOT_TOKEN"]),
+            |k| env.get(k).map(|v| v.to_string()),
+        );
+
+        assert_eq!(find(&settings, "provider").source, Source::ConfigFile);
+        assert_eq!(find(&settings, "provider").value.as_deref(), Some("Codex"));
+        assert_eq!(
+            find(&settings, "heartbeat.interval").source,
+            Source::Default
+        );
+        assert_eq!(find(&settings, "NTFY_TOPIC").source, Source::Env);
+        // config.toml wins over .env for the same key.
+        assert_eq!(
+            find(&settings, "DISCORD_BOT_TOKEN").source,
+            Source::ConfigFile
+        );
+        assert_eq!(find(&settings, "SLACK_BOT_TOKEN").source, Source::DotEnv);
+        assert_eq!(find(&settings, "SLACK_APP_TOKEN").source, Source::Unset);
+        assert_eq!(find(&settings, "socket_path").source, Source::Default);
+        assert_eq!(find(&settings, "workdir").value, None);
+        assert_eq!(
+            find(&settings, "adapters.slack.enabled").value.as_deref(),
+            Some("true")
+        );
+    }
+
+    #[test]
+    fn secrets_are_masked_in_show() {
+        let token = Setting {
+            key: "DISCORD_BOT_TOKEN".to_string(),
+            value: Some("MTIzNDU2Nzg5MDEyMzQ1Njc4".to_string()),
+            source: Source::Env,
+        };
+        assert_eq!(display_value(&token), "MTIz****");
+
+        let short = Setting {
+            key: "SLACK_APP_TOKEN".to_string(),
+            value: Some("xapp".to_string()),
+            source: Source::Env,
+        };
+        assert_eq!(display_value(&short), "****");
+
+        assert!(is_secret("GEMINI_API_KEY"));
+        assert!(!is_secret("NTFY_TOPIC"));
+        assert!(!is_secret("socket_path"));
+    }
+
+    #[test]
+    fn canonical_key_maps_aliases() {
+        assert_eq!(canonical_key("ACOMM_SOCKET_PATH"), "socket_path");
+        assert_eq!(canonical_key("env.GEMINI_API_KEY"), "GEMINI_API_KEY");
+        assert_eq!(canonical_key("heartbeat.interval"), "heartbeat.interval");
+    }
+
+    #[test]
+    fn parses_quiet_hours() {
+        let config = Config::parse(
+            "[quiet_hours]\ntimezone = \"Europe/Berlin\"\naction = \"drop\"\n\n\
+             [[quiet_hours.periods]]\ndays = \"mon-fri\"\nstart = \"22:30\"\nend = \"07:00\"\n\n\
+             [[quiet_hours.periods]]\nstart = \"12:00\"\nend = \"13:00\"\n",
+        )
+        .unwrap();
+        let quiet = &config.quiet_hours;
+        assert_eq!(quiet.timezone, Some(chrono_tz::Europe::Berlin));
+        assert_eq!(quiet.action, QuietAction::Drop);
+        assert_eq!(quiet.periods[0].weekdays(), 0b0111110);
+        assert_eq!(
+            quiet.periods[0].start,
+            NaiveTime::from_hms_opt(22, 30, 0).unwrap()
+        );
+        assert_eq!(quiet.periods[1].weekdays(), 0x7f);
+        assert_eq!(Config::default().quiet_hours.action, QuietAction::Queue);
+
+        for (toml, reason) in [
+            (
+                "[quiet_hours]\ntimezone = \"Mars/Olympus\"\n",
+                "unknown time zone",
+            ),
+            (
+                "[[quiet_hours.periods]]\nstart = \"25:00\"\nend = \"07:00\"\n",
+                "invalid time '25:00'",
+            ),
+            (
+                "[[quiet_hours.periods]]\ndays = \"weekdays\"\nstart = \"22:00\"\nend = \"07:00\"\n",
+                "invalid days 'weekdays'",
+            ),
+            (
+                "[[quiet_hours.periods]]\nstart = \"22:00\"\n",
+                "missing field `end`",
+            ),
+        ] {
+            let err = Config::parse(toml).unwrap_err();
+            assert!(err.contains(reason), "{}: {}", toml, err);
+        }
+    }
+
+    #[test]
+    fn parses_webhook_routes() {
+        let config = Config::parse(
+            "[webhook.routes.grafana]\nchannel = \"alerts\"\ntemplate = \"{{title}}\"\n\n\
+             [webhook.routes.ci]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.webhook.routes["grafana"],
+            WebhookRoute {
+                channel: Some("alerts".to_string()),
+                template: Some("{{title}}".to_string()),
+            }
+        );
+        assert_eq!(config.webhook.routes["ci"], WebhookRoute::default());
+
+        let err = Config::parse("[webhook.routes.\"a/b\"]\n").unwrap_err();
+        assert!(err.contains("invalid webhook route name 'a/b'"), "{}", err);
+    }
+
+    #[test]
+    fn parses_notify_targets() {
+        let config = Config::parse(
+            "[notify.ops]\nurl = \"https://hooks.example.org/yuiclaw\"\n\
+             events = [\"crashed\", \"gave_up\"]\nsecret = \"s3cret\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.notify["ops"],
+            NotifyTarget {
+                url: "https://hooks.example.org/yuiclaw".to_string(),
+                events: vec![EventKind::Crashed, EventKind::GaveUp],
+                secret: Some("s3cret".to_string()),
+            }
+        );
+
+        let err =
+            Config::parse("[notify.ops]\nurl = \"https://x\"\nevents = [\"crash\"]\n").unwrap_err();
+        assert!(err.contains("crash"), "{}", err);
+        let err = Config::parse("[notify.ops]\nurl = \"hooks.example.org\"\n").unwrap_err();
+        assert!(err.contains("invalid notify URL"), "{}", err);
+    }
+
+    #[test]
+    fn parses_api_listen_addresses() {
+        let listen = |raw: &str| ApiListen::try_from(raw.to_string());
+        assert_eq!(listen("unix"), Ok(ApiListen::Unix(None)));
+        assert_eq!(
+            listen("unix:/run/yuiclaw-api.sock"),
+            Ok(ApiListen::Unix(Some(PathBuf::from(
+                "/run/yuiclaw-api.sock"
+            ))))
+        );
+        assert_eq!(
+            listen("localhost:8788"),
+            Ok(ApiListen::Tcp("127.0.0.1:8788".parse().unwrap()))
+        );
+        assert!(listen("[::1]:8788").is_ok());
+        assert!(
+            listen("0.0.0.0:8788")
+                .unwrap_err()
+                .contains("not on localhost")
+        );
+        assert!(
+            listen("8788")
+                .unwrap_err()
+                .contains("invalid api listen address")
+        );
+
+        let config = Config::parse("[api]\nlisten = \"127.0.0.1:8788\"\ntoken = \"t\"\n").unwrap();
+        assert_eq!(config.api.token.as_deref(), Some("t"));
+        let err = Config::parse("[api]\nlisten = \"127.0.0.1:8788\"\n").unwrap_err();
+        assert!(err.contains("needs a token"), "{}", err);
+    }
+
+    #[test]
+    fn set_preserves_comments_and_types_values() {
+        let contents = "# my settings\nprovider = \"gemini\" # default\n";
+        let updated = with_setting(contents, "provider", "claude").unwrap();
+        assert!(updated.starts_with("# my settings\n"), "{}", updated);
+        assert!(updated.contains("provider = \"claude\""), "{}", updated);
+
+        let updated = with_setting(&updated, "adapters.slack.enabled", "false").unwrap();
+        assert!(
+            updated.contains("[adapters.slack]\nenabled = false"),
+            "{}",
+            updated
+        );
+        assert!(!updated.contains("[adapters]\n"), "{}", updated);
+
+        let updated = with_setting(&updated, "DISCORD_BOT_TOKEN", "123").unwrap();
+        let config = Config::parse(&updated).unwrap();
+        assert_eq!(
+            config.env.get("DISCORD_BOT_TOKEN").map(String::as_str),
+            Some("123")
+        );
+        assert!(!config.adapter_enabled("slack"));
+
+        let updated = with_setting(&updated, "AMEM_ROOT", "/data/amem").unwrap();
+        assert!(
+            updated.contains("amem_root = \"/data/amem\""),
+            "{}",
+            updated
+        );
+    }
+
+    #[test]
+    fn set_rejects_invalid_keys_and_values() {
+        let err = with_setting("", "provider", "gpt").unwrap_err();
+        assert!(err.contains("unknown provider"), "{}", err);
+
+        let err = with_setting("", "heartbeat.intervall", "1h").unwrap_err();
+        assert!(err.contains("intervall"), "{}", err);
+
+        let err = with_setting("", "adapters.slack.enabled", "maybe").unwrap_err();
+        assert!(err.contains("Invalid value"), "{}", err);
+    }
+
+    #[test]
+    fn unset_removes_keys() {
+        let contents = "provider = \"claude\"\n[env]\nGEMINI_API_KEY = \"x\"\n";
+        let updated = without_setting(contents, "GEMINI_API_KEY").unwrap();
+        assert!(!updated.contains("GEMINI_API_KEY"), "{}", updated);
+        let updated = without_setting(&updated, "provider").unwrap();
+        assert_eq!(Config::parse(&updated).unwrap(), Config::default());
+
+        assert!(without_setting("", "provider").is_err());
+        assert!(without_setting("", "heartbeat.interval").is_err());
+    }
+
+    #[test]
+    fn interval_validation() {
+        assert!(is_valid_interval("30m"));
+        assert!(is_valid_interval("2h"));
+        assert!(!is_valid_interval("0m"));
+        assert!(!is_valid_interval("m"));
+        assert!(!is_valid_interval("30"));
+        assert!(!is_valid_interval("1.5h"));
+        assert!(!is_valid_interval(""));
+        assert!(!is_valid_interval("999999999999999d"));
+        assert!(!is_valid_interval("99999999999999999999s"));
+        assert_eq!(interval_seconds("90m"), Some(5400));
+    }
+}
@@ -0,0 +1,252 @@
+//! Five-field cron expressions (`minute hour day-of-month month day-of-week`), evaluated in
+//! local time by yuiclaw so that scheduled jobs do not depend on cron support in abeat.
+//!
+//! Fields accept `*`, numbers, ranges (`1-5`), steps (`*/15`, `8-18/2`), lists (`1,15`) and
+//! English names for months and weekdays (`jan`, `mon-fri`). Day-of-week 0 and 7 are both
+//! Sunday. As in Vixie cron, a job whose day-of-month and day-of-week are both restricted
+//! runs when either matches. `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` are
+//! accepted as shorthands.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike};
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far ahead [`Schedule::next_after`] looks; enough for `0 0 29 2 *`.
+const SEARCH_YEARS: i64 = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether day-of-month / day-of-week were `*`, which changes how they combine.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expanded = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "invalid cron expression '{}': expected 5 fields (minute hour day month weekday)",
+                expr
+            ));
+        };
+        let field = |value: &str, name: &str, min: u32, max: u32, names: &[&str]| {
+            parse_field(value, min, max, names)
+                .map_err(|e| format!("invalid cron expression '{}': {} {}", expr, name, e))
+        };
+
+        let mut weekdays = field(weekday, "weekday", 0, 7, &WEEKDAYS)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: field(minute, "minute", 0, 59, &[])?,
+            hours: field(hour, "hour", 0, 23, &[])?,
+            days: field(day, "day", 1, 31, &[])?,
+            months: field(month, "month", 1, 12, &MONTHS)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    fn matches_day(&self, date: &NaiveDate) -> bool {
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// The first time after `t` at which the schedule fires. Minutes skipped by a DST
+    /// change are skipped by the schedule too.
+    pub fn next_after<Tz: TimeZone>(&self, t: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = t.timezone();
+        let local = t.naive_local();
+        let mut next = local.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let end = local + Duration::days(366 * SEARCH_YEARS);
+        while next < end {
+            let date = next.date();
+            if !has(self.months, date.month()) {
+                let (year, month) = if date.month() == 12 {
+                    (date.year() + 1, 1)
+                } else {
+                    (date.year(), date.month() + 1)
+                };
+                next = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(&date) {
+                next = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !has(self.hours, next.hour()) {
+                next = next.with_minute(0)? + Duration::hours(1);
+            } else if !has(self.minutes, next.minute()) {
+                next += Duration::minutes(1);
+            } else if let Some(fire) = tz.from_local_datetime(&next).earliest() {
+                return Some(fire);
+            } else {
+                next += Duration::minutes(1);
+            }
+        }
+        None
+    }
+}
+
+/// Parse a day-of-week field such as `mon-fri` or `sat,sun` into a set indexed by
+/// [`chrono::Weekday::num_days_from_sunday`].
+pub fn parse_weekdays(field: &str) -> Result<u8, String> {
+    let set = parse_field(field.trim(), 0, 7, &WEEKDAYS)?;
+    Ok(((set | (set >> 7)) & 0x7f) as u8)
+}
+
+fn has(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u32, String> {
+        let lower = s.to_ascii_lowercase();
+        let parsed = match names.iter().position(|n| *n == lower) {
+            // Month names start at 1, weekday names at 0.
+            Some(i) => i as u32 + min,
+            None => s
+                .parse()
+                .map_err(|_| format!("'{}' is not a number or name", s))?,
+        };
+        if (min..=max).contains(&parsed) {
+            Ok(parsed)
+        } else {
+            Err(format!("{} is out of range {}-{}", parsed, min, max))
+        }
+    };
+
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("step '{}' must be a positive number", step)),
+            },
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // `5/15` means from 5 to the end in steps of 15.
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let v = value(range)?;
+                    (v, v)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("range '{}' is reversed", range));
+        }
+        for v in (start..=end).step_by(step as usize) {
+            set |= 1 << v;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDateTime, Utc};
+
+    fn at(s: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_utc()
+    }
+
+    fn next(expr: &str, after: &str) -> String {
+        Schedule::parse(expr)
+            .unwrap()
+            .next_after(&at(after))
+            .unwrap()
+            .format("%Y-%m-%d %H:%M %a")
+            .to_string()
+    }
+
+    #[test]
+    fn finds_the_next_run() {
+        // 2026-10-16 is a Friday.
+        assert_eq!(
+            next("0 9 * * 1-5", "2026-10-16 08:59"),
+            "2026-10-16 09:00 Fri"
+        );
+        assert_eq!(
+            next("0 9 * * 1-5", "2026-10-16 09:00"),
+            "2026-10-19 09:00 Mon"
+        );
+        assert_eq!(
+            next("*/15 * * * *", "2026-10-16 09:07"),
+            "2026-10-16 09:15 Fri"
+        );
+        assert_eq!(
+            next("30 8-18/4 * * *", "2026-10-16 17:00"),
+            "2026-10-17 08:30 Sat"
+        );
+        assert_eq!(
+            next("0 0 1 jan *", "2026-10-16 00:00"),
+            "2027-01-01 00:00 Fri"
+        );
+        assert_eq!(next("@weekly", "2026-10-16 00:00"), "2026-10-18 00:00 Sun");
+        assert_eq!(
+            next("0 12 * * 7", "2026-10-16 00:00"),
+            "2026-10-18 12:00 Sun"
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2026-10-16 00:00"),
+            "2028-02-29 00:00 Tue"
+        );
+    }
+
+    #[test]
+    fn restricted_day_and_weekday_match_either() {
+        // The 1st of the month or any Monday.
+        assert_eq!(
+            next("0 0 1 * mon", "2026-10-16 00:00"),
+            "2026-10-19 00:00 Mon"
+        );
+        assert_eq!(
+            next("0 0 1 * mon", "2026-10-27 00:00"),
+            "2026-11-01 00:00 Sun"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for (expr, reason) in [
+            ("* * * *", "expected 5 fields"),
+            ("60 * * * *", "minute 60 is out of range 0-59"),
+            ("* * * * funday", "weekday 'funday' is not a number or name"),
+            ("*/0 * * * *", "step '0' must be a positive number"),
+            ("* 5-1 * * *", "hour range '5-1' is reversed"),
+        ] {
+            let err = Schedule::parse(expr).unwrap_err();
+            assert!(err.contains(reason), "{}: {}", expr, err);
+        }
+        assert!(Schedule::parse("0 9 * * MON-FRI").is_ok());
+    }
+}
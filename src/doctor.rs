@@ -0,0 +1,890 @@
+use crate::adapters::{self, Adapter};
+use crate::components::{self, BridgeHealth};
+use crate::config::{self, Config, TuiPreference};
+use crate::heartbeat;
+use crate::jobs;
+use crate::profile::{self, Profile};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// `<cli> --version` の応答を待つ時間 (node 製 CLI は起動が遅い)
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// bridge の ping 応答を待つ時間
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// プロバイダー名とその CLI コマンド (Dummy は外部 CLI を使わない)
+const PROVIDER_CLIS: [(&str, &str); 4] = [
+    ("Gemini", "gemini"),
+    ("Claude", "claude"),
+    ("Codex", "codex"),
+    ("OpenCode", "opencode"),
+];
+
+/// 診断結果の重大度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Pass => "✓",
+            Self::Warn => "⚠",
+            Self::Fail => "✗",
+        }
+    }
+}
+
+/// 1 項目分の診断結果。問題があれば `fix` に対処方法を入れる
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Check {
+    group: &'static str,
+    name: String,
+    status: CheckStatus,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn pass(group: &'static str, name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            group,
+            name: name.to_string(),
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(group: &'static str, name: &str, detail: impl Into<String>, fix: String) -> Self {
+        Self {
+            status: CheckStatus::Warn,
+            fix: Some(fix),
+            ..Self::pass(group, name, detail)
+        }
+    }
+
+    fn fail(group: &'static str, name: &str, detail: impl Into<String>, fix: String) -> Self {
+        Self {
+            status: CheckStatus::Fail,
+            fix: Some(fix),
+            ..Self::pass(group, name, detail)
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+struct Summary {
+    pass: usize,
+    warn: usize,
+    fail: usize,
+}
+
+impl Summary {
+    fn of(checks: &[Check]) -> Self {
+        let count = |status| checks.iter().filter(|c| c.status == status).count();
+        Self {
+            pass: count(CheckStatus::Pass),
+            warn: count(CheckStatus::Warn),
+            fail: count(CheckStatus::Fail),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    profile: String,
+    ok: bool,
+    summary: Summary,
+    checks: Vec<Check>,
+}
+
+/// 環境を診断して pass / warn / fail のレポートを表示する。fail があれば終了コード 1
+pub async fn run(json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let checks = collect_checks().await;
+    let summary = Summary::of(&checks);
+    let failed = summary.fail;
+
+    if json {
+        let report = Report {
+            profile: profile::current().name.clone(),
+            ok: failed == 0,
+            summary,
+            checks,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&checks, &summary);
+    }
+
+    match failed {
+        0 => Ok(()),
+        1 => Err("1 check failed.".into()),
+        n => Err(format!("{} checks failed.", n).into()),
+    }
+}
+
+async fn collect_checks() -> Vec<Check> {
+    let p = profile::current();
+    let config = config::current();
+
+    // コマンドのバージョン確認は並行して実行
+    let names = ["acomm", "acomm-tui", "amem", "abeat", "jq"]
+        .into_iter()
+        .chain(PROVIDER_CLIS.iter().map(|(_, cli)| *cli));
+    let probes: Vec<_> = names
+        .map(|name| (name, tokio::spawn(command_version(name))))
+        .collect();
+    let mut versions = Vec::new();
+    for (name, handle) in probes {
+        versions.push((name, handle.await.unwrap_or(None)));
+    }
+    let version = |name: &str| {
+        versions
+            .iter()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, v)| v.clone())
+    };
+
+    let mut checks = vec![config_check(&p.config_path())];
+
+    checks.push(match version("acomm") {
+        Some(v) => Check::pass("Components", "acomm", v),
+        None => Check::fail(
+            "Components",
+            "acomm",
+            "not found in PATH",
+            "install acomm (`make install` in the acomm repository)".to_string(),
+        ),
+    });
+    checks.push(tui_check(config.tui, version("acomm-tui")));
+    for (name, without) in [
+        ("amem", "memory disabled"),
+        ("abeat", "using the built-in scheduler"),
+    ] {
+        checks.push(match version(name) {
+            Some(v) => Check::pass("Components", name, v),
+            None => Check::warn(
+                "Components",
+                name,
+                format!("not found in PATH ({})", without),
+                format!(
+                    "install {} (`make install` in the {} repository)",
+                    name, name
+                ),
+            ),
+        });
+    }
+
+    let default_provider = config.default_provider();
+    for (provider, cli) in PROVIDER_CLIS {
+        checks.push(provider_check(
+            provider,
+            cli,
+            version(cli),
+            provider == default_provider,
+        ));
+    }
+
+    let present_env_keys = adapters::present_env_keys();
+    for adapter in adapters::registry(config) {
+        let enabled = config.adapter_enabled(&adapter.name);
+        let mut check = adapter_check(&adapter, enabled, &present_env_keys);
+        // 起動対象のアダプターはコマンドと作業ディレクトリも確認する
+        if enabled
+            && adapter.is_configured(&present_env_keys)
+            && !components::is_command_available(&adapter.command).await
+        {
+            check = Check::fail(
+                "Adapters",
+                &adapter.label,
+                format!("command `{}` not found", adapter.command),
+                format!(
+                    "install it, or fix `adapters.{}.command` in config.toml",
+                    adapter.name
+                ),
+            );
+        }
+        checks.push(check);
+    }
+
+    let socket = &p.socket_path;
+    checks.push(socket_dir_check(socket));
+    if let Some(check) = socket_file_check(socket) {
+        checks.push(check);
+    }
+    checks.push(bridge_check(
+        components::probe_bridge(socket, PROBE_TIMEOUT).await,
+        socket,
+    ));
+
+    checks.push(amem_root_check(&p.memory_root()));
+    let path = p.heartbeat_path();
+    checks.push(heartbeat_file_check(
+        &path,
+        &heartbeat::load(&path, &config::current().heartbeat.interval),
+    ));
+
+    if version("abeat").is_some() {
+        checks.extend(job_checks(p, version("jq").is_some()).await);
+    } else {
+        let path = p.jobs_path();
+        checks.push(jobs_file_check(&path, &jobs::load(&path)));
+        checks.push(Check::pass(
+            "Jobs",
+            "scheduler",
+            "built-in (runs due jobs in `yuiclaw daemon` and `yuiclaw tick`)",
+        ));
+    }
+
+    checks
+}
+
+fn config_check(path: &Path) -> Check {
+    if !path.exists() {
+        return Check::pass(
+            "Config",
+            "config.toml",
+            format!("{} not found (using defaults)", path.display()),
+        );
+    }
+    match Config::load(path) {
+        Ok(_) => Check::pass("Config", "config.toml", path.display().to_string()),
+        Err(e) => Check::fail(
+            "Config",
+            "config.toml",
+            e,
+            "fix the file with `yuiclaw config edit`".to_string(),
+        ),
+    }
+}
+
+fn tui_check(preference: TuiPreference, version: Option<String>) -> Check {
+    match (version, preference) {
+        (Some(v), _) => Check::pass("Components", "acomm-tui", v),
+        (None, TuiPreference::Acomm) => Check::pass(
+            "Components",
+            "acomm-tui",
+            "not installed (not used: tui = \"acomm\")",
+        ),
+        (None, TuiPreference::AcommTui) => Check::fail(
+            "Components",
+            "acomm-tui",
+            "not found in PATH, but tui = \"acomm-tui\"",
+            "run `make install` in the acomm repository, or `yuiclaw config set tui auto`"
+                .to_string(),
+        ),
+        (None, TuiPreference::Auto) => Check::warn(
+            "Components",
+            "acomm-tui",
+            "not found in PATH (falling back to the Rust TUI)",
+            "run `make install` in the acomm repository".to_string(),
+        ),
+    }
+}
+
+/// デフォルトプロバイダーの CLI がなければ fail、それ以外は warn
+fn provider_check(provider: &str, cli: &str, version: Option<String>, is_default: bool) -> Check {
+    match version {
+        Some(v) => Check::pass("Providers", cli, v),
+        None if is_default => Check::fail(
+            "Providers",
+            cli,
+            format!("not found in PATH ({} is the default provider)", provider),
+            format!(
+                "install the {} CLI, or choose another provider with `yuiclaw config set provider <name>`",
+                cli
+            ),
+        ),
+        None => Check::warn(
+            "Providers",
+            cli,
+            format!("not found in PATH ({} provider unavailable)", provider),
+            format!(
+                "install the {} CLI to use `yuiclaw {}`",
+                cli,
+                provider.to_lowercase()
+            ),
+        ),
+    }
+}
+
+/// 必要な環境変数が一部だけ設定されているアダプターは起動されないので warn
+fn adapter_check(adapter: &Adapter, enabled: bool, present_env_keys: &HashSet<String>) -> Check {
+    let name = &adapter.label;
+    if !enabled {
+        return Check::pass("Adapters", name, "disabled in config.toml");
+    }
+    let missing = adapter.missing_env(present_env_keys);
+    let launch = format!("{} {}", adapter.command, adapter.args.join(" "));
+    if missing.is_empty() {
+        if let Some(dir) = adapter.workdir.as_ref().filter(|dir| !dir.is_dir()) {
+            return Check::fail(
+                "Adapters",
+                name,
+                format!("workdir {} does not exist", dir.display()),
+                format!("create it with `mkdir -p {}`", dir.display()),
+            );
+        }
+        return Check::pass(
+            "Adapters",
+            name,
+            format!("configured ({})", launch.trim_end()),
+        );
+    }
+    if missing.len() == adapter.required_env.len() {
+        return Check::pass(
+            "Adapters",
+            name,
+            format!("not configured (set {} to enable)", missing.join(" and ")),
+        );
+    }
+    let docs = adapter
+        .docs_url
+        .as_ref()
+        .map(|url| format!(" (see {})", url))
+        .unwrap_or_default();
+    Check::warn(
+        "Adapters",
+        name,
+        format!(
+            "{} not set, so the adapter is not started",
+            missing.join(", ")
+        ),
+        format!(
+            "set it with `yuiclaw config set {} <value>`{}",
+            missing[0], docs
+        ),
+    )
+}
+
+/// ソケットを作成するディレクトリが書き込み可能か
+fn socket_dir_check(socket: &Path) -> Check {
+    let dir = match socket.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if !dir.is_dir() {
+        return Check::fail(
+            "Bridge",
+            "socket directory",
+            format!("{} does not exist", dir.display()),
+            format!("create it with `mkdir -p {}`", dir.display()),
+        );
+    }
+    if !is_writable(dir) {
+        return Check::fail(
+            "Bridge",
+            "socket directory",
+            format!("{} is not writable", dir.display()),
+            "set socket_path to a writable location with `yuiclaw config set socket_path <path>`"
+                .to_string(),
+        );
+    }
+    Check::pass("Bridge", "socket directory", dir.display().to_string())
+}
+
+/// 現在のユーザーがディレクトリにファイルを作成できるか (access(2) で確認)
+fn is_writable(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `path` is a valid NUL-terminated string that outlives the call.
+    unsafe { libc::access(path.as_ptr(), libc::W_OK | libc::X_OK) == 0 }
+}
+
+/// 既存のソケットファイルの種類・所有者・パーミッション。ファイルがなければ None
+fn socket_file_check(socket: &Path) -> Option<Check> {
+    let meta = std::fs::symlink_metadata(socket).ok()?;
+    // SAFETY: getuid(2) cannot fail and has no preconditions.
+    let uid = unsafe { libc::getuid() };
+    Some(socket_permissions_check(
+        socket,
+        meta.file_type().is_socket(),
+        meta.uid(),
+        meta.mode(),
+        uid,
+    ))
+}
+
+fn socket_permissions_check(
+    socket: &Path,
+    is_socket: bool,
+    owner: u32,
+    mode: u32,
+    uid: u32,
+) -> Check {
+    let name = "socket permissions";
+    if !is_socket {
+        return Check::fail(
+            "Bridge",
+            name,
+            format!("{} exists but is not a socket", socket.display()),
+            format!(
+                "move it away or set another socket_path: {}",
+                socket.display()
+            ),
+        );
+    }
+    if owner != uid {
+        return Check::fail(
+            "Bridge",
+            name,
+            format!("owned by uid {} (you are uid {})", owner, uid),
+            "stop the other user's bridge, or set socket_path to a path of your own".to_string(),
+        );
+    }
+    let mode = mode & 0o777;
+    if mode & 0o002 != 0 {
+        return Check::warn(
+            "Bridge",
+            name,
+            format!(
+                "mode {:04o}: any local user can send prompts to the agent",
+                mode
+            ),
+            format!("chmod 600 {}", socket.display()),
+        );
+    }
+    Check::pass("Bridge", name, format!("mode {:04o}", mode))
+}
+
+fn bridge_check(health: BridgeHealth, socket: &Path) -> Check {
+    let detail = health.describe();
+    let detail = detail.trim_start_matches(['✓', '✗', '⚠', ' ']);
+    match health {
+        BridgeHealth::Responding { .. } => Check::pass("Bridge", "bridge", detail),
+        BridgeHealth::NotRunning => Check::warn(
+            "Bridge",
+            "bridge",
+            detail,
+            "start it with `yuiclaw start` or `yuiclaw daemon start`".to_string(),
+        ),
+        BridgeHealth::SocketFilePresent => Check::fail(
+            "Bridge",
+            "bridge",
+            format!("{}: {}", detail, socket.display()),
+            "remove it with `yuiclaw stop` (or `yuiclaw start` cleans it up)".to_string(),
+        ),
+        BridgeHealth::Accepting => Check::warn(
+            "Bridge",
+            "bridge",
+            detail,
+            "restart it with `yuiclaw restart`".to_string(),
+        ),
+    }
+}
+
+/// プロファイルの AMEM_ROOT。未設定なら amem のデフォルト (~/.amem)
+fn amem_root_check(root: &Path) -> Check {
+    if !root.is_dir() {
+        return Check::warn(
+            "Memory",
+            "amem root",
+            format!("{} does not exist", root.display()),
+            "create it with `yuiclaw init`".to_string(),
+        );
+    }
+    // 実際にファイルを作成して書き込み可能か確かめる
+    let probe = root.join(format!(".yuiclaw-doctor-{}", std::process::id()));
+    match std::fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            Check::pass("Memory", "amem root", root.display().to_string())
+        }
+        Err(e) => Check::fail(
+            "Memory",
+            "amem root",
+            format!("{} is not writable: {}", root.display(), e),
+            format!("chmod u+w {}", root.display()),
+        ),
+    }
+}
+
+fn heartbeat_file_check(
+    path: &Path,
+    checks: &Result<Option<BTreeMap<String, jobs::JobDef>>, String>,
+) -> Check {
+    match checks {
+        Ok(Some(checks)) => Check::pass(
+            "Memory",
+            "HEARTBEAT.md",
+            format!("{} heartbeat check(s) in {}", checks.len(), path.display()),
+        ),
+        Ok(None) => Check::pass(
+            "Memory",
+            "HEARTBEAT.md",
+            "not found (built-in heartbeat prompt)",
+        ),
+        Err(e) => Check::fail(
+            "Memory",
+            "HEARTBEAT.md",
+            e,
+            "fix the file, then run `yuiclaw init`".to_string(),
+        ),
+    }
+}
+
+/// jobs.toml、jobs.toml と HEARTBEAT.md で宣言されたジョブの abeat への登録、旧バージョンの
+/// watchdog ジョブ (jq が必要) の確認
+async fn job_checks(p: &Profile, jq_available: bool) -> Vec<Check> {
+    let path = p.jobs_path();
+    let watchdog = p.job_id("daemon-watchdog");
+    let (rows, watchdog_exists) = tokio::join!(jobs::rows(), jobs::is_registered(&watchdog));
+
+    let mut checks = vec![jobs_file_check(&path, &jobs::load(&path))];
+    // 読み込めないときは jobs.toml / HEARTBEAT.md のチェックが失敗として報告する
+    if let Ok(rows) = rows {
+        checks.extend(rows.iter().map(job_row_check));
+    }
+    checks.push(legacy_watchdog_check(
+        &watchdog,
+        watchdog_exists,
+        jq_available,
+    ));
+    checks
+}
+
+fn job_row_check(row: &jobs::JobRow) -> Check {
+    match row.state.as_str() {
+        "active" => Check::pass("Jobs", &row.id, format!("registered ({})", row.schedule)),
+        "paused" => Check::pass("Jobs", &row.id, "paused (enabled = false)"),
+        "not registered" => Check::warn(
+            "Jobs",
+            &row.id,
+            format!("not registered ({} does not run)", row.schedule),
+            "register it with `yuiclaw init`".to_string(),
+        ),
+        state => Check::pass("Jobs", &row.id, state),
+    }
+}
+
+fn jobs_file_check(path: &Path, defs: &Result<BTreeMap<String, jobs::JobDef>, String>) -> Check {
+    match defs {
+        Ok(defs) if path.exists() => Check::pass(
+            "Jobs",
+            "jobs.toml",
+            format!("{} job(s) declared in {}", defs.len(), path.display()),
+        ),
+        Ok(_) => Check::pass("Jobs", "jobs.toml", "not found (heartbeat only)"),
+        Err(e) => Check::fail(
+            "Jobs",
+            "jobs.toml",
+            e,
+            "fix the file, then run `yuiclaw init`".to_string(),
+        ),
+    }
+}
+
+fn legacy_watchdog_check(job_id: &str, exists: bool, jq_available: bool) -> Check {
+    let fix = "remove it with `yuiclaw init` (the supervisor replaces it)".to_string();
+    match (exists, jq_available) {
+        (false, _) => Check::pass("Jobs", job_id, "not registered (jq not required)"),
+        (true, true) => Check::warn("Jobs", job_id, "legacy job still registered", fix),
+        (true, false) => Check::fail(
+            "Jobs",
+            job_id,
+            "legacy job registered, but jq is not installed so it always fails",
+            fix,
+        ),
+    }
+}
+
+/// PATH 上にあればバージョン文字列 (`--version` の 1 行目) を返す。なければ None
+async fn command_version(cmd: &'static str) -> Option<String> {
+    if !components::is_command_available(cmd).await {
+        return None;
+    }
+    let output = Command::new(cmd)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let version = match tokio::time::timeout(VERSION_TIMEOUT, output).await {
+        Ok(Ok(out)) if out.status.success() => {
+            first_line(&out.stdout).or_else(|| first_line(&out.stderr))
+        }
+        _ => None,
+    };
+    Some(version.unwrap_or_else(|| "installed (version unknown)".to_string()))
+}
+
+fn first_line(bytes: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .map(str::to_string)
+}
+
+fn print_report(checks: &[Check], summary: &Summary) {
+    println!("=== YuiClaw Doctor ===");
+    println!();
+    let p = profile::current();
+    if !p.is_default() {
+        println!("Profile: {}", p.name);
+        println!();
+    }
+
+    let width = checks
+        .iter()
+        .map(|c| c.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut group = "";
+    for check in checks {
+        if check.group != group {
+            if !group.is_empty() {
+                println!();
+            }
+            group = check.group;
+            println!("[{}]", group);
+        }
+        println!(
+            "  {} {:<width$}  {}",
+            check.status.symbol(),
+            check.name,
+            check.detail,
+            width = width
+        );
+        if let Some(fix) = &check.fix {
+            println!("    {:<width$}  fix: {}", "", fix, width = width);
+        }
+    }
+    println!();
+    println!(
+        "Summary: {} passed, {} warnings, {} failed",
+        summary.pass, summary.warn, summary.fail
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_default_provider_fails_and_others_warn() {
+        let check = provider_check("Claude", "claude", None, true);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.fix.unwrap().contains("yuiclaw config set provider"));
+
+        let check = provider_check("Codex", "codex", None, false);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert_eq!(
+            check.fix.as_deref(),
+            Some("install the codex CLI to use `yuiclaw codex`")
+        );
+
+        let check = provider_check("Gemini", "gemini", Some("0.9.0".into()), true);
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert_eq!(check.detail, "0.9.0");
+        assert_eq!(check.fix, None);
+    }
+
+    #[test]
+    fn partially_configured_adapter_warns_with_docs() {
+        let slack = adapters::registry(&Config::default()).remove(2);
+        let present: HashSet<String> = ["SLACK_APP_TOKEN".to_string()].into();
+        let check = adapter_check(&slack, true, &present);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert_eq!(
+            check.detail,
+            "SLACK_BOT_TOKEN not set, so the adapter is not started"
+        );
+        assert!(check.fix.unwrap().contains("https://api.slack.com/"));
+
+        assert_eq!(
+            adapter_check(&slack, true, &HashSet::new()).detail,
+            "not configured (set SLACK_APP_TOKEN and SLACK_BOT_TOKEN to enable)"
+        );
+        assert_eq!(
+            adapter_check(&slack, false, &present).detail,
+            "disabled in config.toml"
+        );
+    }
+
+    #[test]
+    fn custom_adapter_workdir_must_exist() {
+        let config = Config::parse(
+            "[adapters.relay]\ncommand = \"relay\"\nworkdir = \"/nonexistent/relay\"\n",
+        )
+        .unwrap();
+        let relay = adapters::registry(&config).pop().unwrap();
+        let check = adapter_check(&relay, true, &HashSet::new());
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert_eq!(check.detail, "workdir /nonexistent/relay does not exist");
+    }
+
+    #[test]
+    fn acomm_tui_requirement_follows_tui_preference() {
+        assert_eq!(
+            tui_check(TuiPreference::Auto, None).status,
+            CheckStatus::Warn
+        );
+        assert_eq!(
+            tui_check(TuiPreference::AcommTui, None).status,
+            CheckStatus::Fail
+        );
+        assert_eq!(
+            tui_check(TuiPreference::Acomm, None).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            tui_check(TuiPreference::AcommTui, Some("1.0.0".into())).status,
+            CheckStatus::Pass
+        );
+    }
+
+    #[test]
+    fn socket_permissions() {
+        let path = Path::new("/tmp/acomm.sock");
+        let check = socket_permissions_check(path, true, 1000, 0o140755, 1000);
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert_eq!(check.detail, "mode 0755");
+
+        let check = socket_permissions_check(path, true, 1000, 0o140777, 1000);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert_eq!(check.fix.as_deref(), Some("chmod 600 /tmp/acomm.sock"));
+
+        let check = socket_permissions_check(path, true, 0, 0o140755, 1000);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert_eq!(check.detail, "owned by uid 0 (you are uid 1000)");
+
+        let check = socket_permissions_check(path, false, 1000, 0o100644, 1000);
+        assert_eq!(check.status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn stale_socket_fails_with_cleanup_hint() {
+        let path = Path::new("/tmp/acomm.sock");
+        let check = bridge_check(BridgeHealth::SocketFilePresent, path);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert_eq!(
+            check.detail,
+            "stale socket file (connection refused): /tmp/acomm.sock"
+        );
+        assert!(check.fix.unwrap().contains("yuiclaw stop"));
+
+        let check = bridge_check(BridgeHealth::Responding { latency_ms: 1.25 }, path);
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert_eq!(check.detail, "responding (1.2 ms)");
+        assert_eq!(
+            bridge_check(BridgeHealth::NotRunning, path).status,
+            CheckStatus::Warn
+        );
+    }
+
+    #[test]
+    fn amem_root_must_exist_and_be_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let check = amem_root_check(dir.path());
+        assert_eq!(check.status, CheckStatus::Pass);
+        // 書き込み確認用のファイルは残さない
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let check = amem_root_check(&dir.path().join("missing"));
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert_eq!(check.fix.as_deref(), Some("create it with `yuiclaw init`"));
+    }
+
+    #[test]
+    fn invalid_heartbeat_file_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        let check = heartbeat_file_check(&path, &heartbeat::load(&path, "30m"));
+        assert_eq!(check.status, CheckStatus::Pass);
+
+        std::fs::write(&path, "## inbox\nx\n## inbox\ny\n").unwrap();
+        let check = heartbeat_file_check(&path, &heartbeat::load(&path, "30m"));
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.detail.contains("defined twice"), "{}", check.detail);
+    }
+
+    #[test]
+    fn each_declared_job_is_checked_by_its_state() {
+        let row = |name: &str, state: &str| jobs::JobRow {
+            name: name.to_string(),
+            id: format!("yuiclaw-{}", name),
+            schedule: "every 30m".to_string(),
+            target: "#heartbeat".to_string(),
+            state: state.to_string(),
+            last_run: None,
+            next_run: None,
+        };
+        let checks: Vec<(String, CheckStatus)> = [
+            row("heartbeat", "paused"),
+            row("inbox", "active"),
+            row("standup", "not registered"),
+        ]
+        .iter()
+        .map(job_row_check)
+        .map(|check| (check.name, check.status))
+        .collect();
+        assert_eq!(
+            checks,
+            [
+                ("yuiclaw-heartbeat".to_string(), CheckStatus::Pass),
+                ("yuiclaw-inbox".to_string(), CheckStatus::Pass),
+                ("yuiclaw-standup".to_string(), CheckStatus::Warn),
+            ]
+        );
+    }
+
+    #[test]
+    fn legacy_watchdog_job_needs_jq() {
+        let id = "yuiclaw-daemon-watchdog";
+        assert_eq!(
+            legacy_watchdog_check(id, false, false).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            legacy_watchdog_check(id, true, true).status,
+            CheckStatus::Warn
+        );
+        assert_eq!(
+            legacy_watchdog_check(id, true, false).status,
+            CheckStatus::Fail
+        );
+    }
+
+    #[test]
+    fn invalid_config_file_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        assert_eq!(config_check(&path).status, CheckStatus::Pass);
+
+        std::fs::write(&path, "provider = \"Gemini\"\nbogus = 1\n").unwrap();
+        let check = config_check(&path);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.detail.contains("line 2"), "{}", check.detail);
+    }
+
+    #[test]
+    fn summary_counts_each_status() {
+        let checks = vec![
+            Check::pass("A", "a", ""),
+            Check::warn("A", "b", "", String::new()),
+            Check::fail("B", "c", "", String::new()),
+            Check::pass("B", "d", ""),
+        ];
+        assert_eq!(
+            Summary::of(&checks),
+            Summary {
+                pass: 2,
+                warn: 1,
+                fail: 1
+            }
+        );
+    }
+}
@@ -1,54 +1,62 @@
-use std::path::PathBuf;
+use std::path::Path;
 
-/// Load `~/.config/yuiclaw/.env` if it exists.
+/// Load the active profile's `.env` (`~/.config/yuiclaw/.env` for the default profile)
+/// if it exists.
+///
+/// The `.env` file is the lowest-precedence configuration layer: process environment
+/// variables win over `config.toml`, which wins over `.env`.
 ///
 /// Rules:
 /// - Lines starting with `#` or blank lines are skipped.
-/// - Each non-empty line must be in `KEY=VALUE` form.
+/// - Each non-empty line must be in `KEY=VALUE` form; other lines are reported on stderr
+///   with their line number and skipped.
 /// - Leading/trailing whitespace around the key and value is trimmed.
 /// - Single- or double-quoted values have their quotes stripped.
 /// - Existing environment variables are **not** overridden (file provides defaults).
-pub fn load_config_dotenv() {
-    let env_path = match config_env_path() {
-        Some(p) => p,
-        None => return,
-    };
-
+///
+/// Returns the keys defined in the file, whether or not they were applied.
+pub fn load_config_dotenv(env_path: &Path) -> Vec<String> {
+    let mut keys = Vec::new();
     if !env_path.exists() {
-        return;
+        return keys;
     }
 
-    let contents = match std::fs::read_to_string(&env_path) {
+    let contents = match std::fs::read_to_string(env_path) {
         Ok(s) => s,
-        Err(_) => return,
+        Err(_) => return keys,
     };
 
-    for line in contents.lines() {
+    for (index, line) in contents.lines().enumerate() {
         let line = line.trim();
         if line.is_empty() || line.starts_with('#') {
             continue;
         }
-        let Some((key, value)) = line.split_once('=') else {
+        let key_value = line
+            .split_once('=')
+            .map(|(key, value)| (key.trim(), value))
+            .filter(|(key, _)| !key.is_empty());
+        let Some((key, value)) = key_value else {
+            eprintln!(
+                "Warning: {}:{}: expected KEY=VALUE, line ignored",
+                env_path.display(),
+                index + 1
+            );
             continue;
         };
-        let key = key.trim();
-        if key.is_empty() {
-            continue;
-        }
-        let value = strip_quotes(value.trim());
-        // Only set if the variable is not already present in the environment.
-        if std::env::var(key).is_err() {
-            // SAFETY: single-threaded at this point (early in main, before tokio runtime).
-            unsafe {
-                std::env::set_var(key, value);
-            }
-        }
+        set_default_var(key, strip_quotes(value.trim()));
+        keys.push(key.to_string());
     }
+    keys
 }
 
-/// Returns the path `~/.config/yuiclaw/.env`.
-fn config_env_path() -> Option<PathBuf> {
-    dirs::config_dir().map(|d| d.join("yuiclaw").join(".env"))
+/// Set `key` to `value` unless it is already present in the environment.
+pub fn set_default_var(key: &str, value: &str) {
+    if std::env::var_os(key).is_none() {
+        // SAFETY: single-threaded at this point (early in main, before other tasks run).
+        unsafe {
+            std::env::set_var(key, value);
+        }
+    }
 }
 
 /// Strip a single layer of surrounding single or double quotes from a value.
@@ -0,0 +1,359 @@
+//! Heartbeat checks defined in `HEARTBEAT.md` in the memory store root.
+//!
+//! Each `## name` section is a check: optional `every:`, `cron:`, `channel:`,
+//! `description:` and `enabled:` lines, then the prompt. A file without `##` sections is
+//! the prompt of the single `heartbeat` check. Checks without a schedule run every
+//! `[heartbeat] interval` and publish on `#heartbeat` unless they set a channel.
+//!
+//! Prompts are templates rendered by yuiclaw each time a check runs, so edits apply at the
+//! next tick. They may use `{{now}}`, `{{weekday}}`, `{{hostname}}`, `{{last_heartbeat}}`
+//! and `{{unread_channels}}` (channels that received prompts since the last heartbeat, as
+//! recorded by the daemon).
+
+use crate::bridge::{self, HEARTBEAT_CHANNEL};
+use crate::jobs::{self, JobDef};
+use crate::state;
+use crate::webhook;
+use chrono::{DateTime, Local, TimeZone};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Wait before following the bridge again after the connection was lost.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Channels that adapters and `yuiclaw ask` open for each message (`telegram-<chat>-<id>`,
+/// `matrix-<event>`, `email-<uid>`, `ask-<pid>-<n>`), recorded under their prefix.
+const PER_MESSAGE_CHANNELS: [&str; 4] = ["telegram", "matrix", "email", "ask"];
+
+/// Written by `yuiclaw init` when the memory store has no `HEARTBEAT.md` yet.
+const DEFAULT_TEMPLATE: &str = "# Heartbeat
+
+Checks that yuiclaw sends to the agent. Each `## name` section is a check: optional
+`every:` / `cron:` / `channel:` lines, then the prompt. Available variables: {{now}},
+{{weekday}}, {{hostname}}, {{last_heartbeat}}, {{unread_channels}}.
+
+## heartbeat
+
+Proactive heartbeat ({{weekday}} {{now}} on {{hostname}}): review recent amem activities and
+provide a brief status update. Channels with new messages since the last heartbeat
+({{last_heartbeat}}): {{unread_channels}}. Reply HEARTBEAT_OK if there is nothing to report.
+";
+
+/// Parse `HEARTBEAT.md` contents into jobs. Checks without a schedule run every `interval`.
+pub fn parse(contents: &str, interval: &str) -> Result<BTreeMap<String, JobDef>, String> {
+    let mut sections: Vec<(String, Vec<&str>)> = Vec::new();
+    for line in contents.lines() {
+        if let Some(name) = line.strip_prefix("## ") {
+            sections.push((name.trim().to_string(), Vec::new()));
+        } else if let Some((_, body)) = sections.last_mut() {
+            body.push(line);
+        }
+    }
+    if sections.is_empty() {
+        sections.push((jobs::HEARTBEAT_JOB.to_string(), contents.lines().collect()));
+    }
+
+    let mut checks = BTreeMap::new();
+    for (name, lines) in sections {
+        let check = parse_check(&name, &lines, interval)
+            .and_then(|check| jobs::validate(&name, &check).map(|_| check))
+            .map_err(|e| format!("## {}: {}", name, e))?;
+        if checks.insert(name.clone(), check).is_some() {
+            return Err(format!("## {}: defined twice", name));
+        }
+    }
+    Ok(checks)
+}
+
+/// Load `path`; `None` if it does not exist.
+pub fn load(path: &Path, interval: &str) -> Result<Option<BTreeMap<String, JobDef>>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => parse(&contents, interval)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Write the default `HEARTBEAT.md` to `path` unless it exists. Returns whether it did.
+pub fn scaffold(path: &Path) -> std::io::Result<bool> {
+    match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+    {
+        Ok(mut file) => {
+            std::io::Write::write_all(&mut file, DEFAULT_TEMPLATE.as_bytes())?;
+            Ok(true)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Fill in the variables of a check's prompt.
+pub fn render(template: &str) -> String {
+    let variables = variables(
+        &Local::now(),
+        state::counters().last_heartbeat,
+        &state::channel_activity(),
+        &hostname(),
+    );
+    webhook::render(template, &variables)
+}
+
+/// Record which channels receive prompts, for `{{unread_channels}}` (run by the daemon).
+pub async fn watch_activity(socket: PathBuf) {
+    loop {
+        // Connection errors are expected while the bridge (re)starts.
+        let _ = bridge::watch_prompts(&socket, |channel| {
+            if let Some(channel) = channel.filter(|c| c != HEARTBEAT_CHANNEL) {
+                state::record_channel_activity(channel_group(&channel));
+            }
+        })
+        .await;
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// The channel `channel` is reported as in `{{unread_channels}}`.
+fn channel_group(channel: &str) -> &str {
+    PER_MESSAGE_CHANNELS
+        .into_iter()
+        .find(|prefix| {
+            channel
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('-'))
+        })
+        .unwrap_or(channel)
+}
+
+fn parse_check(name: &str, lines: &[&str], interval: &str) -> Result<JobDef, String> {
+    let mut check = JobDef {
+        every: None,
+        cron: None,
+        prompt: None,
+        channel: None,
+        exec: None,
+        description: None,
+        enabled: true,
+        template: true,
+    };
+    // Settings are the `key: value` lines before the prompt.
+    let mut body = lines;
+    while let Some((line, rest)) = body.split_first() {
+        let line = line.trim();
+        if !line.is_empty() {
+            let Some((key, value)) = line.split_once(':') else {
+                break;
+            };
+            let value = value.trim().to_string();
+            match key.trim() {
+                "every" => check.every = Some(value),
+                "cron" => check.cron = Some(value),
+                "channel" => check.channel = Some(value),
+                "description" => check.description = Some(value),
+                "enabled" => {
+                    check.enabled = value
+                        .parse()
+                        .map_err(|_| format!("`enabled` is '{}', not true or false", value))?;
+                }
+                _ => break,
+            }
+        }
+        body = rest;
+    }
+
+    if check.every.is_none() && check.cron.is_none() {
+        check.every = Some(interval.to_string());
+    }
+    check
+        .channel
+        .get_or_insert_with(|| HEARTBEAT_CHANNEL.to_string());
+    check
+        .description
+        .get_or_insert_with(|| format!("YuiClaw heartbeat check {}", name));
+    check.prompt = Some(body.join("\n").trim().to_string());
+    Ok(check)
+}
+
+fn variables<Tz: TimeZone>(
+    now: &DateTime<Tz>,
+    last_heartbeat: Option<i64>,
+    activity: &BTreeMap<String, i64>,
+    hostname: &str,
+) -> Value
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let last = last_heartbeat
+        .and_then(|t| now.timezone().timestamp_opt(t, 0).single())
+        .map_or_else(
+            || "never".to_string(),
+            |t| t.format("%Y-%m-%d %H:%M").to_string(),
+        );
+    let unread: Vec<String> = activity
+        .iter()
+        .filter(|(_, at)| last_heartbeat.is_none_or(|last| **at > last))
+        .map(|(channel, _)| format!("#{}", channel))
+        .collect();
+    json!({
+        "now": now.format("%Y-%m-%d %H:%M").to_string(),
+        "weekday": now.format("%A").to_string(),
+        "hostname": hostname,
+        "last_heartbeat": last,
+        "unread_channels": if unread.is_empty() { "none".to_string() } else { unread.join(", ") },
+    })
+}
+
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    // SAFETY: gethostname(2) writes at most `buf.len()` bytes into a buffer we own.
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return "localhost".to_string();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDateTime, Utc};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn parses_named_checks_with_settings() {
+        let checks = parse(
+            "# My checks\n\nIgnored intro.\n\n## heartbeat\n\nHow are things?\n\n\
+             ## inbox\ncron: 0 9-18 * * mon-fri\nchannel: email\n\nAnything urgent?\nNote: be brief.\n\n\
+             ## weekly\nevery: 7d\nenabled: false\nWeekly review.\n",
+            "45m",
+        )
+        .unwrap();
+        assert_eq!(
+            checks.keys().collect::<Vec<_>>(),
+            ["heartbeat", "inbox", "weekly"]
+        );
+
+        let heartbeat = &checks["heartbeat"];
+        assert_eq!(heartbeat.every.as_deref(), Some("45m"));
+        assert_eq!(heartbeat.channel.as_deref(), Some("heartbeat"));
+        assert_eq!(heartbeat.prompt.as_deref(), Some("How are things?"));
+        assert!(heartbeat.template && heartbeat.enabled);
+
+        let inbox = &checks["inbox"];
+        assert_eq!(inbox.cron.as_deref(), Some("0 9-18 * * mon-fri"));
+        assert_eq!(inbox.every, None);
+        assert_eq!(inbox.channel.as_deref(), Some("email"));
+        // Settings end at the prompt; later `key: value` lines belong to it.
+        assert_eq!(
+            inbox.prompt.as_deref(),
+            Some("Anything urgent?\nNote: be brief.")
+        );
+
+        assert_eq!(checks["weekly"].every.as_deref(), Some("7d"));
+        assert!(!checks["weekly"].enabled);
+    }
+
+    #[test]
+    fn file_without_sections_is_the_heartbeat_prompt() {
+        let checks = parse("every: 2h\n\nStatus update, please ({{now}}).\n", "30m").unwrap();
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks["heartbeat"].every.as_deref(), Some("2h"));
+        assert_eq!(
+            checks["heartbeat"].prompt.as_deref(),
+            Some("Status update, please ({{now}}).")
+        );
+
+        let checks = parse(DEFAULT_TEMPLATE, "30m").unwrap();
+        assert_eq!(checks.keys().collect::<Vec<_>>(), ["heartbeat"]);
+    }
+
+    #[test]
+    fn rejects_invalid_checks() {
+        for (contents, reason) in [
+            ("## a\nx\n## a\ny\n", "## a: defined twice"),
+            (
+                "## a\ncron: 61 * * * *\nx\n",
+                "## a: invalid cron expression",
+            ),
+            (
+                "## a\nevery: 1h\ncron: @daily\nx\n",
+                "either `every` or `cron`",
+            ),
+            ("## a\nevery: 1h\n\n", "`prompt` is empty"),
+            ("## a b\nx\n", "job names"),
+            ("## a\nenabled: maybe\nx\n", "`enabled` is 'maybe'"),
+        ] {
+            let err = parse(contents, "30m").unwrap_err();
+            assert!(err.contains(reason), "{:?}: {}", contents, err);
+        }
+    }
+
+    #[test]
+    fn renders_variables() {
+        let at = |s: &str| {
+            NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+                .unwrap()
+                .and_utc()
+        };
+        let activity = BTreeMap::from([
+            ("discord".to_string(), at("2026-10-17 08:00").timestamp()),
+            ("email".to_string(), at("2026-10-17 10:00").timestamp()),
+            ("slack".to_string(), at("2026-10-17 11:00").timestamp()),
+        ]);
+        let template = "{{weekday}} {{now}} on {{ hostname }}; last {{last_heartbeat}}; \
+                        unread {{unread_channels}}{{unknown}}";
+
+        let vars = variables(
+            &at("2026-10-17 12:00"),
+            Some(at("2026-10-17 09:30").timestamp()),
+            &activity,
+            "nas",
+        );
+        assert_eq!(
+            webhook::render(template, &vars),
+            "Saturday 2026-10-17 12:00 on nas; last 2026-10-17 09:30; unread #email, #slack"
+        );
+
+        let vars = variables(&Utc::now(), None, &BTreeMap::new(), "nas");
+        assert!(
+            webhook::render(template, &vars).ends_with("last never; unread none"),
+            "{}",
+            webhook::render(template, &vars)
+        );
+    }
+
+    #[test]
+    fn per_message_channels_are_grouped_by_adapter() {
+        let channels = [
+            "telegram-42-1001",
+            "telegram-42-1002",
+            "matrix-abc:example.org",
+            "email-7",
+            "ask-123-1",
+            "discord",
+            "emails",
+        ];
+        let groups: BTreeSet<&str> = channels.iter().map(|c| channel_group(c)).collect();
+        assert_eq!(
+            groups,
+            BTreeSet::from(["ask", "discord", "email", "emails", "matrix", "telegram"])
+        );
+    }
+
+    #[test]
+    fn scaffold_keeps_an_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("HEARTBEAT.md");
+        assert!(scaffold(&path).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), DEFAULT_TEMPLATE);
+        std::fs::write(&path, "mine").unwrap();
+        assert!(!scaffold(&path).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "mine");
+    }
+}
@@ -0,0 +1,82 @@
+//! HTTP/1.1 plumbing shared by the servers in yuiclaw: `yuiclaw serve-webhook` and the
+//! control API. Every response is JSON, except for the Prometheus metrics.
+
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap};
+use hyper::service::service_fn;
+use hyper::{Request, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::{Value, json};
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+pub type Response = hyper::Response<Full<Bytes>>;
+
+/// Answers the requests of one server.
+pub trait Handler: Send + Sync + 'static {
+    fn handle(&self, request: Request<Incoming>) -> impl Future<Output = Response> + Send;
+}
+
+/// Serve the requests on `stream` in a task of its own until the client disconnects.
+pub fn spawn_connection<S, H>(stream: S, handler: Arc<H>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    H: Handler,
+{
+    tokio::spawn(async move {
+        let service = service_fn(move |request| {
+            let handler = Arc::clone(&handler);
+            async move { Ok::<_, Infallible>(handler.handle(request).await) }
+        });
+        let _ = hyper::server::conn::http1::Builder::new()
+            .serve_connection(TokioIo::new(stream), service)
+            .await;
+    });
+}
+
+/// Read a request body of at most `limit` bytes; larger ones are answered with 413.
+pub async fn read_body(body: Incoming, limit: usize) -> Result<Bytes, Response> {
+    match Limited::new(body, limit).collect().await {
+        Ok(body) => Ok(body.to_bytes()),
+        Err(_) => Err(error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            &format!("Payload exceeds {} KiB.", limit / 1024),
+        )),
+    }
+}
+
+pub fn reply(status: StatusCode, body: Value) -> Response {
+    hyper::Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .expect("static response parts are valid")
+}
+
+pub fn text(status: StatusCode, content_type: &str, body: String) -> Response {
+    hyper::Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, content_type)
+        .body(Full::new(Bytes::from(body)))
+        .expect("static response parts are valid")
+}
+
+pub fn error(status: StatusCode, reason: &str) -> Response {
+    reply(status, json!({ "ok": false, "error": reason }))
+}
+
+/// Whether the request carries `Authorization: Bearer <token>`, compared in constant time.
+pub fn has_bearer_token(headers: &HeaderMap, token: &str) -> bool {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(token.as_bytes(), given.trim().as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
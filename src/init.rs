@@ -1,4 +1,7 @@
 use crate::components;
+use crate::heartbeat;
+use crate::jobs;
+use crate::profile;
 use std::process::Stdio;
 use tokio::process::Command;
 
@@ -8,6 +11,11 @@ pub async fn initialize() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("=== YuiClaw Initialization ===");
     println!();
+    let p = profile::current();
+    if !p.is_default() {
+        println!("Profile: {} (socket: {})", p.name, p.socket_path.display());
+        println!();
+    }
 
     // 1. amem init
     if s.amem_available {
@@ -29,6 +37,7 @@ pub async fn initialize() -> Result<(), Box<dyn std::error::Error>> {
     } else {
         println!("[1/3] amem が見つかりません — スキップ");
     }
+    scaffold_heartbeat();
 
     // 2. abeat init
     if s.abeat_available {
@@ -48,13 +57,24 @@ pub async fn initialize() -> Result<(), Box<dyn std::error::Error>> {
             }
         );
 
-        // 3. デフォルトのハートビートジョブを設定
-        println!("[3/3] ハートビートジョブを設定しています...");
-        setup_heartbeat_job().await;
-        setup_daemon_watchdog_job().await;
+        // 3. jobs.toml のジョブを同期
+        println!(
+            "[3/3] ジョブを同期しています ({})...",
+            profile::current().jobs_path().display()
+        );
+        sync_jobs().await;
+        remove_legacy_daemon_watchdog_job().await;
     } else {
         println!("[2/3] abeat が見つかりません — スキップ");
-        println!("[3/3] abeat なしのためジョブ設定をスキップ");
+        // abeat がなければ jobs.toml のジョブは組み込みスケジューラーが実行する
+        println!("[3/3] abeat がないため組み込みスケジューラーでジョブを実行します");
+        match jobs::rows().await {
+            Ok(rows) => jobs::print_table(&rows, "  "),
+            Err(e) => println!("  ✗ {}", e),
+        }
+        println!(
+            "  (`yuiclaw daemon` の実行中、または `yuiclaw tick` のたびに期限の来たジョブを実行)"
+        );
     }
 
     println!();
@@ -62,84 +82,50 @@ pub async fn initialize() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-/// yuiclaw-heartbeat ジョブを abeat に登録する
-async fn setup_heartbeat_job() {
-    // ジョブが既に存在するか確認
-    let exists = Command::new("abeat")
-        .args(["get", "job", "yuiclaw-heartbeat"])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .await
-        .map(|s| s.success())
-        .unwrap_or(false);
-
-    if exists {
-        println!("  ✓ heartbeat job は既に存在します");
+/// amem のルートに HEARTBEAT.md のひな形を置く (既存のファイルはそのまま)
+fn scaffold_heartbeat() {
+    let p = profile::current();
+    if !p.memory_root().is_dir() {
         return;
     }
+    let path = p.heartbeat_path();
+    match heartbeat::scaffold(&path) {
+        Ok(true) => println!(
+            "  ✓ {} を作成しました (heartbeat のチェックを編集できます)",
+            path.display()
+        ),
+        Ok(false) => {}
+        Err(e) => println!("  ✗ {} を作成できません: {}", path.display(), e),
+    }
+}
 
-    // ホームディレクトリを workspace として使用
-    let home = dirs::home_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("/tmp"))
-        .to_string_lossy()
-        .to_string();
-
-    // abeat set jobs add で登録
-    // acomm が利用可能であれば結果を bridge に publish する
-    let exec_cmd = [
-        "if command -v acomm >/dev/null 2>&1 && test -S /tmp/acomm.sock; then",
-        "  acomm --publish 'Proactive heartbeat: review recent amem activities and provide a brief status update.' --channel heartbeat 2>/dev/null;",
-        "else",
-        "  echo HEARTBEAT_OK;",
-        "fi",
-    ]
-    .join(" ");
-
-    let ok = Command::new("abeat")
-        .args([
-            "set",
-            "jobs",
-            "add",
-            "--id",
-            "yuiclaw-heartbeat",
-            "--description",
-            "YuiClaw 30分ごとのプロアクティブチェック",
-            "--kind",
-            "heartbeat_check",
-            "--every",
-            "30m",
-            "--agent",
-            "shell",
-            "--workspace",
-            &home,
-            "--exec",
-            &exec_cmd,
-            "--no-op-token",
-            "HEARTBEAT_OK",
-        ])
-        .status()
-        .await
-        .map(|s| s.success())
-        .unwrap_or(false);
-
-    println!(
-        "  {}",
-        if ok {
-            "✓ yuiclaw-heartbeat job を登録しました"
-        } else {
-            "✗ heartbeat job の登録に失敗しました (abeat set jobs add が未実装の可能性あり)"
+/// jobs.toml と HEARTBEAT.md のチェック を abeat に同期し、差分を表示する
+async fn sync_jobs() {
+    let outcomes = match jobs::reconcile().await {
+        Ok(outcomes) => outcomes,
+        Err(e) => {
+            println!("  ✗ {} (ジョブは変更していません)", e);
+            return;
         }
-    );
+    };
+
+    let unchanged = jobs::print_diff(&outcomes);
+    if unchanged == outcomes.len() {
+        println!("  ✓ 変更はありません ({} 件のジョブ)", unchanged);
+    } else if unchanged > 0 {
+        println!("  ({} 件のジョブは変更なし)", unchanged);
+    }
 }
 
-/// yuiclaw-daemon-watchdog ジョブを abeat に登録する
-async fn setup_daemon_watchdog_job() {
-    let job_id = "yuiclaw-daemon-watchdog";
+/// 旧バージョンが登録した yuiclaw-daemon-watchdog ジョブを削除する
+///
+/// bridge / adapter の死活監視は `yuiclaw daemon run` (スーパーバイザー) が担うため、
+/// 5分ごとに jq でステータスを確認するシェルジョブは不要になった。
+async fn remove_legacy_daemon_watchdog_job() {
+    let job_id = profile::current().job_id("daemon-watchdog");
 
-    // ジョブが既に存在するか確認
     let exists = Command::new("abeat")
-        .args(["get", "job", job_id])
+        .args(["get", "job", &job_id])
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .status()
@@ -147,51 +133,13 @@ async fn setup_daemon_watchdog_job() {
         .map(|s| s.success())
         .unwrap_or(false);
 
-    if exists {
-        println!("  ✓ daemon watchdog job は既に存在します");
+    if !exists {
         return;
     }
 
-    let home = dirs::home_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("/tmp"))
-        .to_string_lossy()
-        .to_string();
-
-    // yuiclaw daemon status --json を実行し、異常があれば restart する
-    // bridge が停止しているか、いずれかのチャンネルが接続されていない場合に異常とみなす
-    let exec_cmd = [
-        "STATUS=$(yuiclaw daemon status --json 2>/dev/null);",
-        "if [ $? -ne 0 ]; then yuiclaw daemon restart; exit 0; fi;",
-        "BRIDGE_RUNNING=$(echo \"$STATUS\" | jq -r '.bridge_running');",
-        "if [ \"$BRIDGE_RUNNING\" != \"true\" ]; then yuiclaw daemon restart; exit 0; fi;",
-        "DISCONNECTED_CHANNELS=$(echo \"$STATUS\" | jq -r '.channels[] | select(.connected == false) | .label');",
-        "if [ -n \"$DISCONNECTED_CHANNELS\" ]; then yuiclaw daemon restart; exit 0; fi;",
-        "echo WATCHDOG_OK;",
-    ]
-    .join(" ");
-
     let ok = Command::new("abeat")
-        .args([
-            "set",
-            "jobs",
-            "add",
-            "--id",
-            job_id,
-            "--description",
-            "YuiClaw デーモンの死活監視 (5分ごと)",
-            "--kind",
-            "heartbeat_check",
-            "--every",
-            "5m",
-            "--agent",
-            "shell",
-            "--workspace",
-            &home,
-            "--exec",
-            &exec_cmd,
-            "--no-op-token",
-            "WATCHDOG_OK",
-        ])
+        .args(["set", "jobs", "remove", "--id", &job_id])
+        .stdout(Stdio::null())
         .status()
         .await
         .map(|s| s.success())
@@ -200,9 +148,9 @@ async fn setup_daemon_watchdog_job() {
     println!(
         "  {}",
         if ok {
-            "✓ yuiclaw-daemon-watchdog job を登録しました"
+            "✓ 旧 yuiclaw-daemon-watchdog job を削除しました (監視は `yuiclaw daemon run` が担当)"
         } else {
-            "✗ daemon watchdog job の登録に失敗しました"
+            "✗ 旧 yuiclaw-daemon-watchdog job の削除に失敗しました"
         }
     );
 }
@@ -0,0 +1,1383 @@
+//! Scheduled jobs managed by yuiclaw.
+//!
+//! Jobs are declared in `jobs.toml` next to config.toml, one table per job. A job either
+//! publishes a prompt to the bridge or runs a shell command, on an interval (`every`) or a
+//! cron schedule (`cron`, local time):
+//!
+//! ```toml
+//! [standup]
+//! cron = "0 9 * * 1-5"
+//! prompt = "Summarise my calendar for today."
+//! channel = "standup"
+//! ```
+//!
+//! The built-in `heartbeat` job runs every `[heartbeat] interval` unless `jobs.toml`
+//! declares a `[heartbeat]` table of its own. `yuiclaw init` and the `yuiclaw jobs`
+//! commands reconcile the declared jobs with abeat: missing jobs are created, changed jobs
+//! are re-registered and jobs that are no longer declared are removed. What was applied is
+//! recorded in `jobs.json` in the state directory, so only jobs created by yuiclaw are ever
+//! removed.
+//!
+//! Interval jobs are registered with abeat's `--every`. Cron jobs are registered to run
+//! every minute as `yuiclaw jobs run NAME --if-due`, which checks the schedule itself.
+
+use crate::components;
+use crate::config;
+use crate::cron::Schedule;
+use crate::heartbeat;
+use crate::process;
+use crate::profile::{self, Profile};
+use crate::quiet;
+use crate::state::{self, JobRun};
+use crate::supervisor;
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+use toml_edit::{DocumentMut, Item, Table, value};
+
+/// Name of the built-in job that sends the proactive heartbeat prompt.
+pub const HEARTBEAT_JOB: &str = "heartbeat";
+
+/// Prompt of the built-in heartbeat job.
+const HEARTBEAT_PROMPT: &str =
+    "Proactive heartbeat: review recent amem activities and provide a brief status update.";
+
+/// Printed by a job with nothing to report, so that abeat records the run as a no-op.
+pub const NO_OP_TOKEN: &str = "HEARTBEAT_OK";
+
+/// abeat interval of cron jobs, which decide for themselves whether they are due.
+const CRON_CHECK_INTERVAL: &str = "1m";
+
+/// One table of `jobs.toml`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobDef {
+    /// abeat interval such as `30m`, `1h` or `1d`.
+    #[serde(default)]
+    pub every: Option<String>,
+    /// Five-field cron expression, evaluated in local time.
+    #[serde(default)]
+    pub cron: Option<String>,
+    /// Prompt published to the bridge on each run.
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Channel the prompt is tagged with; defaults to the job name.
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// Shell command run instead of publishing a prompt. It should print `HEARTBEAT_OK`
+    /// when there is nothing to report.
+    #[serde(default)]
+    pub exec: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// `false` pauses the job: it is removed from abeat but keeps its definition.
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Whether the job is a `HEARTBEAT.md` check, whose prompt is re-read and rendered by
+    /// yuiclaw on every run.
+    #[serde(skip)]
+    pub template: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl JobDef {
+    /// A `jobs.toml` table with nothing but `enabled`, which pauses a heartbeat check.
+    fn is_override(&self) -> bool {
+        self.every.is_none()
+            && self.cron.is_none()
+            && self.prompt.is_none()
+            && self.channel.is_none()
+            && self.exec.is_none()
+            && self.description.is_none()
+    }
+
+    /// `every 30m` or `cron 0 9 * * 1-5`.
+    pub fn schedule(&self) -> String {
+        match (&self.every, &self.cron) {
+            (Some(every), _) => format!("every {}", every),
+            (None, Some(cron)) => format!("cron {}", cron),
+            (None, None) => "-".to_string(),
+        }
+    }
+
+    /// `#channel` for prompt jobs, `exec` for shell commands.
+    fn target(&self, name: &str) -> String {
+        match &self.exec {
+            Some(_) => "exec".to_string(),
+            None => format!("#{}", self.channel.as_deref().unwrap_or(name)),
+        }
+    }
+}
+
+/// A job as registered with `abeat set jobs add`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbeatJob {
+    pub id: String,
+    pub description: String,
+    pub every: String,
+    pub workspace: String,
+    pub exec: String,
+}
+
+/// What reconciling does to one job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Create(AbeatJob),
+    /// `previous` is `None` for a job registered before yuiclaw recorded what it applied.
+    Update {
+        previous: Option<AbeatJob>,
+        job: AbeatJob,
+    },
+    Remove(AbeatJob),
+    Unchanged(AbeatJob),
+}
+
+impl Change {
+    pub fn job(&self) -> &AbeatJob {
+        match self {
+            Change::Create(job)
+            | Change::Update { job, .. }
+            | Change::Remove(job)
+            | Change::Unchanged(job) => job,
+        }
+    }
+
+    /// Lines of a diff: `+ id`, `- id` or `~ id` followed by the changed fields.
+    pub fn diff(&self) -> Vec<String> {
+        match self {
+            Change::Create(job) => vec![format!("+ {} (every {})", job.id, job.every)],
+            Change::Remove(job) => vec![format!("- {}", job.id)],
+            Change::Unchanged(job) => vec![format!("  {} (every {})", job.id, job.every)],
+            Change::Update {
+                previous: None,
+                job,
+            } => vec![format!(
+                "~ {} (every {}; registered outside jobs.json, re-registering)",
+                job.id, job.every
+            )],
+            Change::Update {
+                previous: Some(previous),
+                job,
+            } => {
+                let mut lines = vec![format!("~ {}", job.id)];
+                for (field, old, new) in [
+                    ("description", &previous.description, &job.description),
+                    ("every", &previous.every, &job.every),
+                    ("workspace", &previous.workspace, &job.workspace),
+                    ("exec", &previous.exec, &job.exec),
+                ] {
+                    if old != new {
+                        lines.push(format!("    - {}: {}", field, old));
+                        lines.push(format!("    + {}: {}", field, new));
+                    }
+                }
+                lines
+            }
+        }
+    }
+}
+
+/// A [`Change`] and, if applying it failed, why.
+pub struct Outcome {
+    pub change: Change,
+    pub error: Option<String>,
+}
+
+/// One row of `yuiclaw jobs list`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JobRow {
+    pub name: String,
+    pub id: String,
+    pub schedule: String,
+    pub target: String,
+    /// `active`, `paused`, `not registered` or `built-in` (run by yuiclaw without abeat).
+    pub state: String,
+    pub last_run: Option<String>,
+    pub next_run: Option<String>,
+}
+
+/// Parse `jobs.toml` contents. Errors carry the line of the offending value or the job name.
+pub fn parse(contents: &str) -> Result<BTreeMap<String, JobDef>, String> {
+    let jobs: BTreeMap<String, JobDef> =
+        toml::from_str(contents).map_err(|e| e.to_string().trim_end().to_string())?;
+    for (name, job) in &jobs {
+        validate(name, job).map_err(|e| format!("[{}]: {}", name, e))?;
+    }
+    Ok(jobs)
+}
+
+/// Load `path`, treating a missing file as declaring no jobs.
+pub fn load(path: &Path) -> Result<BTreeMap<String, JobDef>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => parse(&contents).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+pub(crate) fn validate(name: &str, job: &JobDef) -> Result<(), String> {
+    let valid_name = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        return Err("job names use up to 64 ASCII letters, digits, '-' or '_'".to_string());
+    }
+    if job.is_override() && !job.template {
+        return Ok(());
+    }
+    match (&job.every, &job.cron) {
+        (Some(_), Some(_)) => return Err("set either `every` or `cron`, not both".to_string()),
+        (None, None) => return Err("set `every` or `cron`".to_string()),
+        (Some(every), None) if !config::is_valid_interval(every) => {
+            return Err(format!(
+                "invalid interval '{}', expected a number followed by s, m, h or d (e.g. 30m)",
+                every
+            ));
+        }
+        (None, Some(cron)) => {
+            Schedule::parse(cron)?;
+        }
+        _ => {}
+    }
+    match (&job.prompt, &job.exec) {
+        (Some(_), Some(_)) => Err("set either `prompt` or `exec`, not both".to_string()),
+        (None, None) => Err("set `prompt` or `exec`".to_string()),
+        (Some(prompt), None) if prompt.trim().is_empty() => Err("`prompt` is empty".to_string()),
+        (None, Some(_)) if job.channel.is_some() => {
+            Err("`channel` only applies to `prompt` jobs".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The jobs in `defs` plus the heartbeat `checks`. A job in `defs` replaces the check of
+/// the same name, except for a table that only sets `enabled`, which pauses or resumes it.
+pub fn declared(
+    defs: &BTreeMap<String, JobDef>,
+    checks: BTreeMap<String, JobDef>,
+) -> Result<BTreeMap<String, JobDef>, String> {
+    let mut jobs = checks;
+    for (name, def) in defs {
+        match jobs.get_mut(name) {
+            Some(check) if def.is_override() => check.enabled = def.enabled,
+            _ if def.is_override() => {
+                return Err(format!(
+                    "[{}]: set `every` or `cron` (only heartbeat checks can be paused with `enabled` alone)",
+                    name
+                ));
+            }
+            _ => {
+                jobs.insert(name.clone(), def.clone());
+            }
+        }
+    }
+    Ok(jobs)
+}
+
+/// The built-in heartbeat check, used when there is no `HEARTBEAT.md`.
+fn builtin_checks(interval: &str) -> BTreeMap<String, JobDef> {
+    let heartbeat = JobDef {
+        every: Some(interval.to_string()),
+        cron: None,
+        prompt: Some(HEARTBEAT_PROMPT.to_string()),
+        channel: None,
+        exec: None,
+        description: Some(format!("YuiClaw {}ごとのプロアクティブチェック", interval)),
+        enabled: true,
+        template: false,
+    };
+    BTreeMap::from([(HEARTBEAT_JOB.to_string(), heartbeat)])
+}
+
+/// Heartbeat checks of the active profile: the sections of `HEARTBEAT.md`, or the
+/// built-in heartbeat without one.
+fn heartbeat_checks() -> Result<BTreeMap<String, JobDef>, String> {
+    let interval = &config::current().heartbeat.interval;
+    let checks = heartbeat::load(&profile::current().heartbeat_path(), interval)?;
+    Ok(checks.unwrap_or_else(|| builtin_checks(interval)))
+}
+
+/// Jobs of the active profile: `jobs.toml` and the heartbeat checks.
+fn current_jobs() -> Result<BTreeMap<String, JobDef>, String> {
+    let defs = load(&profile::current().jobs_path())?;
+    declared(&defs, heartbeat_checks()?)
+}
+
+/// The abeat jobs that the enabled `jobs` need for profile `p`.
+pub fn desired(p: &Profile, jobs: &BTreeMap<String, JobDef>, workspace: &str) -> Vec<AbeatJob> {
+    jobs.iter()
+        .filter(|(_, def)| def.enabled)
+        .map(|(name, def)| AbeatJob {
+            id: p.job_id(name),
+            description: def
+                .description
+                .clone()
+                .unwrap_or_else(|| format!("YuiClaw job {}", name)),
+            every: def
+                .every
+                .clone()
+                .unwrap_or_else(|| CRON_CHECK_INTERVAL.to_string()),
+            workspace: workspace.to_string(),
+            exec: match (&def.cron, &def.exec, &def.prompt) {
+                (Some(_), _, _) => format!(
+                    "{} jobs run {} --if-due",
+                    p.yuiclaw_command(),
+                    shell_quote(name)
+                ),
+                (None, Some(exec), _) => exec.clone(),
+                // HEARTBEAT.md is read at run time, so that edits apply without `yuiclaw init`.
+                (None, None, _) if def.template => format!(
+                    "{} jobs run {} --scheduled",
+                    p.yuiclaw_command(),
+                    shell_quote(name)
+                ),
+                (None, None, prompt) => prompt_exec_command(
+                    p,
+                    prompt.as_deref().unwrap_or_default(),
+                    def.channel.as_deref().unwrap_or(name),
+                ),
+            },
+        })
+        .collect()
+}
+
+/// Work out the changes that turn `applied` (what yuiclaw registered last time) into
+/// `desired`, given the IDs abeat currently knows.
+pub fn plan(
+    desired: &[AbeatJob],
+    applied: &BTreeMap<String, AbeatJob>,
+    registered: &BTreeSet<String>,
+) -> Vec<Change> {
+    let mut changes: Vec<Change> = desired
+        .iter()
+        .map(|job| {
+            if !registered.contains(&job.id) {
+                Change::Create(job.clone())
+            } else {
+                match applied.get(&job.id) {
+                    Some(previous) if previous == job => Change::Unchanged(job.clone()),
+                    previous => Change::Update {
+                        previous: previous.cloned(),
+                        job: job.clone(),
+                    },
+                }
+            }
+        })
+        .collect();
+    let wanted: BTreeSet<&str> = desired.iter().map(|job| job.id.as_str()).collect();
+    changes.extend(
+        applied
+            .values()
+            .filter(|job| !wanted.contains(job.id.as_str()) && registered.contains(&job.id))
+            .map(|job| Change::Remove(job.clone())),
+    );
+    changes
+}
+
+/// Bring abeat in line with `jobs.toml` for the active profile.
+pub async fn reconcile() -> Result<Vec<Outcome>, String> {
+    let p = profile::current();
+    let jobs = current_jobs()?;
+    let workspace = dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .to_string_lossy()
+        .to_string();
+    let desired = desired(p, &jobs, &workspace);
+
+    let record = record_path();
+    let mut applied = read_record(&record);
+    let ids: BTreeSet<String> = desired
+        .iter()
+        .map(|job| job.id.clone())
+        .chain(applied.keys().cloned())
+        .collect();
+    let mut registered = BTreeSet::new();
+    for id in ids {
+        if is_registered(&id).await {
+            registered.insert(id);
+        }
+    }
+    applied.retain(|id, _| registered.contains(id));
+
+    let mut outcomes = Vec::new();
+    for change in plan(&desired, &applied, &registered) {
+        let result = match &change {
+            Change::Create(job) => abeat_add(job).await,
+            Change::Update { job, .. } => match abeat_remove(&job.id).await {
+                Ok(()) => abeat_add(job).await,
+                Err(e) => Err(e),
+            },
+            Change::Remove(job) => abeat_remove(&job.id).await,
+            Change::Unchanged(_) => Ok(()),
+        };
+        let job = change.job();
+        match (&change, &result) {
+            (Change::Remove(_), Ok(())) => {
+                applied.remove(&job.id);
+            }
+            (Change::Remove(_), Err(_)) => {}
+            (_, Ok(())) => {
+                applied.insert(job.id.clone(), job.clone());
+            }
+            // Left for the next run to create or re-register.
+            (_, Err(_)) => {
+                applied.remove(&job.id);
+            }
+        }
+        outcomes.push(Outcome {
+            change,
+            error: result.err(),
+        });
+    }
+
+    write_record(&record, &applied).map_err(|e| format!("{}: {}", record.display(), e))?;
+    Ok(outcomes)
+}
+
+/// Print the diff of `outcomes`, indented by two spaces, and return how many jobs were
+/// left unchanged.
+pub fn print_diff(outcomes: &[Outcome]) -> usize {
+    let mut unchanged = 0;
+    for outcome in outcomes {
+        if matches!(outcome.change, Change::Unchanged(_)) {
+            unchanged += 1;
+            continue;
+        }
+        for line in outcome.change.diff() {
+            println!("  {}", line);
+        }
+        if let Some(e) = &outcome.error {
+            println!("    ✗ {}", e);
+        }
+    }
+    unchanged
+}
+
+/// `yuiclaw jobs list`
+pub async fn list(json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let rows = rows().await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+    } else {
+        print_table(&rows, "");
+    }
+    Ok(())
+}
+
+/// The jobs of the active profile with their state in abeat, or in the built-in scheduler
+/// when abeat is not installed.
+pub async fn rows() -> Result<Vec<JobRow>, String> {
+    let p = profile::current();
+    let jobs = current_jobs()?;
+    let abeat = components::is_command_available("abeat").await;
+    let runs = state::job_runs();
+    let now = Local::now();
+
+    let mut rows = Vec::new();
+    for (name, def) in &jobs {
+        let id = p.job_id(name);
+        let info = match abeat && def.enabled {
+            true => abeat_job(&id).await,
+            false => None,
+        };
+        let state = match (def.enabled, abeat, &info) {
+            (false, _, _) => "paused",
+            (true, false, _) => "built-in",
+            (true, true, Some(_)) => "active",
+            (true, true, None) => "not registered",
+        };
+        let recorded_run = runs
+            .get(name)
+            .and_then(|run| run.last_run)
+            .and_then(|t| Local.timestamp_opt(t, 0).single())
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string());
+        let abeat_field = |keys: &[&str]| {
+            info.as_ref()
+                .and_then(|info| keys.iter().find_map(|key| info.get(*key).cloned()))
+        };
+        let (last_run, next_run) = match &def.cron {
+            Some(cron) => (
+                recorded_run,
+                Schedule::parse(cron)
+                    .ok()
+                    .filter(|_| def.enabled)
+                    .and_then(|schedule| schedule.next_after(&now))
+                    .map(|t| t.format("%Y-%m-%d %H:%M").to_string()),
+            ),
+            None if abeat => (
+                abeat_field(&["last_run", "last_run_at", "last_fired_at", "last"]).or(recorded_run),
+                abeat_field(&["next_run", "next_run_at", "next_due", "next_due_at", "next"]),
+            ),
+            // The built-in scheduler runs an interval job once its interval has passed.
+            None => {
+                let next = runs
+                    .get(name)
+                    .and_then(|run| run.last_run)
+                    .zip(def.every.as_deref().and_then(config::interval_seconds))
+                    .filter(|_| def.enabled)
+                    .and_then(|(last, interval)| last.checked_add(interval))
+                    .and_then(|due| Local.timestamp_opt(due, 0).single())
+                    .map(|t| t.max(now).format("%Y-%m-%d %H:%M").to_string());
+                (recorded_run, next)
+            }
+        };
+        rows.push(JobRow {
+            name: name.clone(),
+            id,
+            schedule: def.schedule(),
+            target: def.target(name),
+            state: state.to_string(),
+            last_run,
+            next_run,
+        });
+    }
+    Ok(rows)
+}
+
+/// Print `rows` as a table, each line prefixed with `indent`.
+pub fn print_table(rows: &[JobRow], indent: &str) {
+    let header = [
+        "NAME", "SCHEDULE", "TARGET", "STATE", "LAST RUN", "NEXT RUN",
+    ]
+    .map(str::to_string);
+    let cells: Vec<[String; 6]> = rows
+        .iter()
+        .map(|row| {
+            [
+                row.name.clone(),
+                row.schedule.clone(),
+                row.target.clone(),
+                row.state.clone(),
+                row.last_run.clone().unwrap_or_else(|| "-".to_string()),
+                row.next_run.clone().unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+    let widths: Vec<usize> = (0..5)
+        .map(|i| {
+            std::iter::once(&header)
+                .chain(&cells)
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in std::iter::once(&header).chain(&cells) {
+        let mut line = indent.to_string();
+        for (cell, width) in row.iter().zip(&widths) {
+            line.push_str(&format!("{:<w$}  ", cell, w = width));
+        }
+        line.push_str(&row[5]);
+        println!("{}", line);
+    }
+}
+
+/// `yuiclaw jobs add NAME --prompt TEXT (--every INTERVAL | --cron EXPR)`
+pub async fn add(
+    name: &str,
+    prompt: &str,
+    channel: Option<&str>,
+    every: Option<&str>,
+    cron: Option<&str>,
+    description: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = edit_jobs_file(|doc| {
+        if doc.contains_key(name) {
+            return Err(format!(
+                "Job '{}' already exists. Remove it first with `yuiclaw jobs remove {}`.",
+                name, name
+            ));
+        }
+        let mut table = Table::new();
+        for (key, setting) in [
+            ("every", every),
+            ("cron", cron),
+            ("prompt", Some(prompt)),
+            ("channel", channel),
+            ("description", description),
+        ] {
+            if let Some(setting) = setting {
+                table.insert(key, value(setting));
+            }
+        }
+        doc.insert(name, Item::Table(table));
+        Ok(())
+    })?;
+    println!("Added job '{}' to {}.", name, path.display());
+    apply().await;
+    Ok(())
+}
+
+/// `yuiclaw jobs remove NAME`
+pub async fn remove(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let checks = heartbeat_checks()?;
+    let path = edit_jobs_file(|doc| match doc.remove(name) {
+        Some(_) => Ok(()),
+        None if checks.contains_key(name) => Err(format!(
+            "'{}' is a heartbeat check; pause it with `yuiclaw jobs pause {}`{}.",
+            name,
+            name,
+            if checks[name].template {
+                " or delete its section from HEARTBEAT.md"
+            } else {
+                ""
+            }
+        )),
+        None => Err(unknown_job(name)),
+    })?;
+    println!("Removed job '{}' from {}.", name, path.display());
+    if checks.contains_key(name) {
+        println!("The heartbeat check '{}' applies again.", name);
+    }
+    apply().await;
+    Ok(())
+}
+
+/// `yuiclaw jobs pause NAME` / `yuiclaw jobs resume NAME`
+pub async fn set_enabled(name: &str, enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let checks = heartbeat_checks()?;
+    edit_jobs_file(|doc| {
+        if !doc.contains_key(name) {
+            if !checks.contains_key(name) {
+                return Err(unknown_job(name));
+            }
+            // A heartbeat check is paused with a table that only sets `enabled`.
+            doc.insert(name, Item::Table(Table::new()));
+        }
+        let table = doc
+            .get_mut(name)
+            .and_then(Item::as_table_like_mut)
+            .ok_or_else(|| format!("[{}] is not a table", name))?;
+        if enabled {
+            table.remove("enabled");
+            if table.is_empty() && checks.contains_key(name) {
+                doc.remove(name);
+            }
+        } else {
+            table.insert("enabled", value(false));
+        }
+        Ok(())
+    })?;
+    println!(
+        "{} job '{}'.",
+        if enabled { "Resumed" } else { "Paused" },
+        name
+    );
+    apply().await;
+    Ok(())
+}
+
+/// `yuiclaw jobs run NAME`: run a job now. With `scheduled` (how abeat runs heartbeat
+/// checks), the run counts as scheduled: a paused job or a prompt held back by quiet hours
+/// prints the no-op token instead. With `if_due` (how abeat runs cron jobs), the job also
+/// only runs when its schedule has come up since the last check.
+pub async fn run(
+    name: &str,
+    if_due: bool,
+    scheduled: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let jobs = current_jobs()?;
+    let def = jobs.get(name).ok_or_else(|| unknown_job(name))?;
+    let now = Local::now();
+    let scheduled = scheduled || if_due;
+    let ran = state::update_job_runs(|runs| {
+        let run = runs.entry(name.to_string()).or_default();
+        let due = match (if_due, scheduled) {
+            (true, _) => def.enabled && is_due(def, run, &now),
+            (false, true) => def.enabled,
+            (false, false) => true,
+        };
+        if if_due {
+            run.last_checked = Some(now.timestamp());
+        }
+        if due {
+            run.last_run = Some(now.timestamp());
+        }
+        due
+    })?;
+    if !ran {
+        println!("{}", NO_OP_TOKEN);
+        return Ok(());
+    }
+    match execute(name, def, scheduled).await? {
+        Some(channel) if !scheduled => println!("Published job '{}' on #{}.", name, channel),
+        None if scheduled => println!("{}", NO_OP_TOKEN),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Run every job that is due with the built-in scheduler, which stands in for abeat when it
+/// is not installed. Returns the names of the jobs that ran and how each went.
+pub async fn run_due() -> Result<Vec<(String, Result<(), String>)>, String> {
+    let jobs = current_jobs()?;
+    let now = Local::now();
+    let due = state::update_job_runs(|runs| take_due(&jobs, runs, &now))
+        .map_err(|e| format!("{}: {}", state::state_dir().display(), e))?;
+
+    let mut results = Vec::new();
+    for name in due {
+        let result = execute(&name, &jobs[&name], true)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        results.push((name, result));
+    }
+    Ok(results)
+}
+
+/// The enabled jobs that are due at `now`, marked as run. Every enabled job is marked as
+/// checked, so a cron minute is never run twice.
+fn take_due<Tz: TimeZone>(
+    jobs: &BTreeMap<String, JobDef>,
+    runs: &mut BTreeMap<String, JobRun>,
+    now: &DateTime<Tz>,
+) -> Vec<String> {
+    let mut due = Vec::new();
+    for (name, def) in jobs.iter().filter(|(_, def)| def.enabled) {
+        let run = runs.entry(name.clone()).or_default();
+        if is_due(def, run, now) {
+            run.last_run = Some(now.timestamp());
+            due.push(name.clone());
+        }
+        run.last_checked = Some(now.timestamp());
+    }
+    due
+}
+
+/// Run the built-in scheduler at the start of every minute until the process exits (the
+/// daemon does this when abeat is not installed).
+pub async fn run_scheduler() {
+    loop {
+        let seconds = 60 - Local::now().timestamp().rem_euclid(60) as u64;
+        tokio::time::sleep(std::time::Duration::from_secs(seconds)).await;
+        match run_due().await {
+            Ok(results) => {
+                for (name, result) in results {
+                    match result {
+                        Ok(()) => supervisor::log(format!("jobs: ran {}", name)),
+                        Err(e) => supervisor::log(format!("jobs: {} failed: {}", name, e)),
+                    }
+                }
+            }
+            Err(e) => supervisor::log(format!("jobs: {}", e)),
+        }
+    }
+}
+
+/// Run `def` now. Prompts are published to the bridge; returns the channel, or `None` when
+/// a `scheduled` prompt was skipped because the bridge is not running or held back by
+/// quiet hours.
+async fn execute(
+    name: &str,
+    def: &JobDef,
+    scheduled: bool,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    match (&def.exec, &def.prompt) {
+        (Some(exec), _) => {
+            let status = Command::new("sh").arg("-c").arg(exec).status().await?;
+            if !status.success() {
+                return Err(format!("Job '{}' failed ({}).", name, status).into());
+            }
+            Ok(None)
+        }
+        (None, prompt) => {
+            let channel = def.channel.as_deref().unwrap_or(name);
+            let prompt = prompt.as_deref().unwrap_or_default();
+            let rendered;
+            let prompt = if def.template {
+                rendered = heartbeat::render(prompt);
+                rendered.as_str()
+            } else {
+                prompt
+            };
+            if !scheduled {
+                process::publish(prompt, Some(channel)).await?;
+            } else if !profile::socket_path().exists()
+                || quiet::publish(prompt, Some(channel)).await? != quiet::Delivery::Published
+            {
+                return Ok(None);
+            }
+            Ok(Some(channel.to_string()))
+        }
+    }
+}
+
+/// Whether `def` should run at `now`, given when it was last checked and run. A cron job
+/// whose runs were missed (the machine was asleep) runs once.
+fn is_due<Tz: TimeZone>(def: &JobDef, run: &JobRun, now: &DateTime<Tz>) -> bool {
+    match (&def.cron, &def.every) {
+        (Some(cron), _) => {
+            let Ok(schedule) = Schedule::parse(cron) else {
+                return false;
+            };
+            // On the first check only the current minute counts.
+            let since = run
+                .last_checked
+                .and_then(|t| now.timezone().timestamp_opt(t, 0).single())
+                .unwrap_or_else(|| now.clone() - chrono::Duration::minutes(1));
+            schedule.next_after(&since).is_some_and(|next| next <= *now)
+        }
+        (None, Some(every)) => match (run.last_run, config::interval_seconds(every)) {
+            (Some(last), Some(interval)) => last
+                .checked_add(interval)
+                .is_some_and(|due| now.timestamp() >= due),
+            _ => true,
+        },
+        (None, None) => false,
+    }
+}
+
+fn unknown_job(name: &str) -> String {
+    format!("Unknown job '{}'. See `yuiclaw jobs list`.", name)
+}
+
+/// Edit `jobs.toml` of the active profile, keeping comments and layout. The result is
+/// validated before it is written.
+fn edit_jobs_file(
+    update: impl FnOnce(&mut DocumentMut) -> Result<(), String>,
+) -> Result<PathBuf, String> {
+    let path = profile::current().jobs_path();
+    let contents = config::read_config_file(&path)?;
+    let mut doc: DocumentMut = contents
+        .parse()
+        .map_err(|e: toml_edit::TomlError| format!("{}: {}", path.display(), e))?;
+    update(&mut doc)?;
+    let updated = doc.to_string();
+    parse(&updated).map_err(|e| format!("Not saved: {}", e))?;
+    config::write_config_file(&path, &updated).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Reconcile with abeat after `jobs.toml` changed and print what happened.
+async fn apply() {
+    if !components::is_command_available("abeat").await {
+        println!(
+            "abeat not found in PATH; the built-in scheduler runs the job during `yuiclaw tick` and in the daemon."
+        );
+        return;
+    }
+    match reconcile().await {
+        Ok(outcomes) => {
+            if print_diff(&outcomes) == outcomes.len() {
+                println!("abeat is already up to date.");
+            }
+        }
+        Err(e) => println!("Could not update abeat: {}", e),
+    }
+}
+
+/// Whether abeat knows a job with this ID.
+pub async fn is_registered(id: &str) -> bool {
+    Command::new("abeat")
+        .args(["get", "job", id])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Fields of `abeat get job ID`, or `None` if abeat does not know the job.
+async fn abeat_job(id: &str) -> Option<BTreeMap<String, String>> {
+    let output = Command::new("abeat")
+        .args(["get", "job", id])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| parse_abeat_fields(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Read the fields of an abeat job description, given either as a JSON object or as
+/// `key: value` / `key = value` lines. Keys are lowercased with `_` for spaces and dashes;
+/// nested and empty values are skipped.
+fn parse_abeat_fields(text: &str) -> BTreeMap<String, String> {
+    let key = |k: &str| k.trim().to_ascii_lowercase().replace([' ', '-'], "_");
+    if let Ok(serde_json::Value::Object(object)) = serde_json::from_str(text.trim()) {
+        return object
+            .iter()
+            .filter_map(|(k, v)| {
+                let v = match v {
+                    serde_json::Value::String(s) => s.clone(),
+                    serde_json::Value::Number(n) => n.to_string(),
+                    serde_json::Value::Bool(b) => b.to_string(),
+                    _ => return None,
+                };
+                (!v.is_empty()).then(|| (key(k), v))
+            })
+            .collect();
+    }
+    text.lines()
+        .filter_map(|line| {
+            let (k, v) = match (line.split_once(':'), line.split_once('=')) {
+                (Some(colon), Some(equals)) if equals.0.len() < colon.0.len() => equals,
+                (Some(colon), _) => colon,
+                (None, equals) => equals?,
+            };
+            let v = v.trim().trim_matches('"').trim();
+            (!v.is_empty() && !k.trim().is_empty()).then(|| (key(k), v.to_string()))
+        })
+        .collect()
+}
+
+async fn abeat_add(job: &AbeatJob) -> Result<(), String> {
+    run_abeat(&[
+        "set",
+        "jobs",
+        "add",
+        "--id",
+        &job.id,
+        "--description",
+        &job.description,
+        "--kind",
+        "heartbeat_check",
+        "--every",
+        &job.every,
+        "--agent",
+        "shell",
+        "--workspace",
+        &job.workspace,
+        "--exec",
+        &job.exec,
+        "--no-op-token",
+        NO_OP_TOKEN,
+    ])
+    .await
+}
+
+async fn abeat_remove(id: &str) -> Result<(), String> {
+    run_abeat(&["set", "jobs", "remove", "--id", id]).await
+}
+
+async fn run_abeat(args: &[&str]) -> Result<(), String> {
+    let output = Command::new("abeat")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("abeat: {}", e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(match stderr.trim() {
+        "" => format!("abeat {} failed ({})", args[..3].join(" "), output.status),
+        reason => reason.to_string(),
+    })
+}
+
+fn record_path() -> PathBuf {
+    state::state_dir().join("jobs.json")
+}
+
+fn read_record(path: &Path) -> BTreeMap<String, AbeatJob> {
+    std::fs::read(path)
+        .ok()
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn write_record(path: &Path, applied: &BTreeMap<String, AbeatJob>) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(applied)?)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Shell command that publishes `prompt` on `channel` with the profile's `yuiclaw pub`
+/// when its bridge is running, and reports a no-op otherwise.
+fn prompt_exec_command(p: &Profile, prompt: &str, channel: &str) -> String {
+    let yuiclaw = p.yuiclaw_command();
+    [
+        format!(
+            "if command -v yuiclaw >/dev/null 2>&1 && test -S {}; then",
+            shell_quote(&p.socket_path.to_string_lossy())
+        ),
+        format!(
+            "  {} pub {} --channel {} --proactive 2>/dev/null;",
+            yuiclaw,
+            shell_quote(prompt),
+            shell_quote(channel)
+        ),
+        "else".to_string(),
+        format!("  echo {};", NO_OP_TOKEN),
+        "fi".to_string(),
+    ]
+    .join(" ")
+}
+
+/// Quote `s` for the shell with single quotes.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDateTime, Utc};
+
+    fn profile(name: &str, socket: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            config_dir: "/cfg".into(),
+            state_dir: "/state".into(),
+            socket_path: socket.into(),
+            amem_root: None,
+            home: None,
+        }
+    }
+
+    fn job(id: &str, every: &str) -> AbeatJob {
+        AbeatJob {
+            id: id.to_string(),
+            description: format!("YuiClaw job {}", id),
+            every: every.to_string(),
+            workspace: "/home/me".to_string(),
+            exec: "echo HEARTBEAT_OK".to_string(),
+        }
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn prompt_exec_command_targets_default_socket() {
+        let cmd = prompt_exec_command(
+            &profile(profile::DEFAULT_PROFILE, "/tmp/acomm.sock"),
+            HEARTBEAT_PROMPT,
+            "heartbeat",
+        );
+        assert!(cmd.contains("test -S '/tmp/acomm.sock'"));
+        assert!(cmd.contains("  yuiclaw pub 'Proactive heartbeat"));
+        assert!(cmd.contains("--channel 'heartbeat' --proactive"));
+        assert!(cmd.contains("echo HEARTBEAT_OK"));
+    }
+
+    #[test]
+    fn prompt_exec_command_passes_named_profile() {
+        let cmd = prompt_exec_command(
+            &profile("work", "/run/user/1000/acomm-work.sock"),
+            "It's 9 o'clock",
+            "standup",
+        );
+        assert!(cmd.contains("test -S '/run/user/1000/acomm-work.sock'"));
+        assert!(cmd.contains("yuiclaw --profile work pub 'It'\\''s 9 o'\\''clock'"));
+    }
+
+    #[test]
+    fn shell_quote_escapes_single_quotes() {
+        assert_eq!(shell_quote("/tmp/a b"), "'/tmp/a b'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn parses_and_validates_jobs() {
+        let jobs = parse(
+            r#"
+[standup]
+cron = "0 9 * * mon-fri"
+prompt = "Summarise yesterday."
+channel = "standup"
+
+[disk]
+every = "6h"
+exec = "df -h / | tail -1"
+enabled = false
+"#,
+        )
+        .unwrap();
+        assert_eq!(jobs["standup"].schedule(), "cron 0 9 * * mon-fri");
+        assert_eq!(jobs["standup"].target("standup"), "#standup");
+        assert_eq!(jobs["disk"].target("disk"), "exec");
+        assert!(!jobs["disk"].enabled);
+
+        for (contents, reason) in [
+            (
+                "[a]\nevery = \"soon\"\nprompt = \"hi\"\n",
+                "[a]: invalid interval 'soon'",
+            ),
+            (
+                "[a]\nevery = \"999999999999999d\"\nprompt = \"hi\"\n",
+                "[a]: invalid interval '999999999999999d'",
+            ),
+            (
+                "[a]\ncron = \"0 25 * * *\"\nprompt = \"hi\"\n",
+                "hour 25 is out of range",
+            ),
+            ("[a]\nprompt = \"hi\"\n", "set `every` or `cron`"),
+            (
+                "[a]\nevery = \"1h\"\ncron = \"@daily\"\nprompt = \"hi\"\n",
+                "not both",
+            ),
+            ("[a]\nevery = \"1h\"\n", "set `prompt` or `exec`"),
+            (
+                "[a]\nevery = \"1h\"\nexec = \"true\"\nchannel = \"x\"\n",
+                "only applies to `prompt` jobs",
+            ),
+            ("[\"a b\"]\nevery = \"1h\"\nexec = \"true\"\n", "job names"),
+            ("[a]\nevery = \"1h\"\nexec = \"true\"\nevry = 1\n", "line 4"),
+        ] {
+            let err = parse(contents).unwrap_err();
+            assert!(err.contains(reason), "{}: {}", contents, err);
+        }
+    }
+
+    #[test]
+    fn heartbeat_is_built_in_unless_overridden_or_disabled() {
+        let p = profile("work", "/tmp/acomm-work.sock");
+        let jobs = desired(
+            &p,
+            &declared(&BTreeMap::new(), builtin_checks("45m")).unwrap(),
+            "/home/me",
+        );
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, "yuiclaw-work-heartbeat");
+        assert_eq!(jobs[0].every, "45m");
+        assert!(jobs[0].exec.contains("--channel 'heartbeat'"));
+
+        let defs = parse("[heartbeat]\nevery = \"2h\"\nprompt = \"Anything new?\"\n").unwrap();
+        let jobs = desired(
+            &p,
+            &declared(&defs, builtin_checks("45m")).unwrap(),
+            "/home/me",
+        );
+        assert_eq!(jobs[0].every, "2h");
+        assert!(jobs[0].exec.contains("pub 'Anything new?'"));
+
+        let defs = parse("[heartbeat]\nevery = \"2h\"\nprompt = \"x\"\nenabled = false\n").unwrap();
+        assert!(
+            desired(
+                &p,
+                &declared(&defs, builtin_checks("45m")).unwrap(),
+                "/home/me"
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn heartbeat_md_checks_render_at_run_time_and_pause_with_enabled() {
+        let p = profile("work", "/tmp/acomm-work.sock");
+        let checks = heartbeat::parse(
+            "## heartbeat\nAnything new since {{last_heartbeat}}?\n\n## inbox\nevery: 2h\nchannel: email\nCheck mail.\n",
+            "45m",
+        )
+        .unwrap();
+        let jobs = desired(
+            &p,
+            &declared(&BTreeMap::new(), checks.clone()).unwrap(),
+            "/home/me",
+        );
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].every, "45m");
+        assert_eq!(
+            jobs[0].exec,
+            "yuiclaw --profile work jobs run 'heartbeat' --scheduled"
+        );
+        assert_eq!(jobs[1].every, "2h");
+
+        let defs = parse("[inbox]\nenabled = false\n").unwrap();
+        let jobs = declared(&defs, checks.clone()).unwrap();
+        assert!(!jobs["inbox"].enabled);
+        assert!(jobs["inbox"].template);
+        assert_eq!(desired(&p, &jobs, "/home/me").len(), 1);
+
+        // A table with only `enabled` needs a heartbeat check to pause.
+        let defs = parse("[other]\nenabled = false\n").unwrap();
+        let err = declared(&defs, checks).unwrap_err();
+        assert!(err.contains("[other]: set `every` or `cron`"), "{}", err);
+    }
+
+    #[test]
+    fn cron_jobs_check_their_schedule_every_minute() {
+        let p = profile("work", "/tmp/acomm-work.sock");
+        let defs = parse("[standup]\ncron = \"0 9 * * 1-5\"\nprompt = \"Plan the day\"\n").unwrap();
+        let jobs = desired(&p, &defs, "/home/me");
+        assert_eq!(jobs[0].every, "1m");
+        assert_eq!(
+            jobs[0].exec,
+            "yuiclaw --profile work jobs run 'standup' --if-due"
+        );
+    }
+
+    #[test]
+    fn decides_whether_a_job_is_due() {
+        let defs = parse(
+            "[standup]\ncron = \"0 9 * * *\"\nprompt = \"x\"\n\n[hourly]\nevery = \"1h\"\nprompt = \"x\"\n",
+        )
+        .unwrap();
+        let (standup, hourly) = (&defs["standup"], &defs["hourly"]);
+        let checked = |t: &str| JobRun {
+            last_checked: Some(at(t).timestamp()),
+            last_run: None,
+        };
+
+        // First check: only the current minute counts.
+        assert!(is_due(
+            standup,
+            &JobRun::default(),
+            &at("2026-10-16 09:00:20")
+        ));
+        assert!(!is_due(
+            standup,
+            &JobRun::default(),
+            &at("2026-10-16 09:01:20")
+        ));
+        // The check at 08:58 covers 08:59..=now.
+        assert!(is_due(
+            standup,
+            &checked("2026-10-16 08:58:00"),
+            &at("2026-10-16 09:03:00")
+        ));
+        assert!(!is_due(
+            standup,
+            &checked("2026-10-16 09:00:10"),
+            &at("2026-10-16 09:05:00")
+        ));
+        // Missed runs while asleep fire once.
+        assert!(is_due(
+            standup,
+            &checked("2026-10-13 22:00:00"),
+            &at("2026-10-16 07:00:00")
+        ));
+
+        let ran = |t: &str| JobRun {
+            last_checked: None,
+            last_run: Some(at(t).timestamp()),
+        };
+        assert!(is_due(
+            hourly,
+            &JobRun::default(),
+            &at("2026-10-16 09:00:00")
+        ));
+        assert!(!is_due(
+            hourly,
+            &ran("2026-10-16 08:30:00"),
+            &at("2026-10-16 09:00:00")
+        ));
+        assert!(is_due(
+            hourly,
+            &ran("2026-10-16 08:00:00"),
+            &at("2026-10-16 09:00:00")
+        ));
+    }
+
+    #[test]
+    fn built_in_scheduler_runs_each_due_job_once() {
+        let defs = parse(
+            "[standup]\ncron = \"0 9 * * *\"\nprompt = \"x\"\n\n[hourly]\nevery = \"1h\"\nprompt = \"x\"\n\n[paused]\nevery = \"1m\"\nprompt = \"x\"\nenabled = false\n",
+        )
+        .unwrap();
+        let mut runs = BTreeMap::new();
+
+        let due = take_due(&defs, &mut runs, &at("2026-10-16 08:59:00"));
+        assert_eq!(due, ["hourly"]);
+        assert!(!runs.contains_key("paused"));
+        let due = take_due(&defs, &mut runs, &at("2026-10-16 09:00:00"));
+        assert_eq!(due, ["standup"]);
+        assert!(take_due(&defs, &mut runs, &at("2026-10-16 09:00:30")).is_empty());
+        let due = take_due(&defs, &mut runs, &at("2026-10-16 10:00:00"));
+        assert_eq!(due, ["hourly"]);
+        assert_eq!(
+            runs["standup"].last_checked,
+            Some(at("2026-10-16 10:00:00").timestamp())
+        );
+    }
+
+    #[test]
+    fn parses_abeat_job_descriptions() {
+        let fields = parse_abeat_fields(
+            r#"{"id": "yuiclaw-heartbeat", "every": "30m", "next_run": "2026-10-16T09:30:00+09:00", "last_status": null, "runs": 3}"#,
+        );
+        assert_eq!(fields["next_run"], "2026-10-16T09:30:00+09:00");
+        assert_eq!(fields["runs"], "3");
+        assert!(!fields.contains_key("last_status"));
+
+        let fields = parse_abeat_fields(
+            "ID: yuiclaw-heartbeat\nLast run: 2026-10-16 09:00:00\nNext-Run = \"2026-10-16 09:30:00\"\nNotes:\n",
+        );
+        assert_eq!(fields["id"], "yuiclaw-heartbeat");
+        assert_eq!(fields["last_run"], "2026-10-16 09:00:00");
+        assert_eq!(fields["next_run"], "2026-10-16 09:30:00");
+        assert!(!fields.contains_key("notes"));
+    }
+
+    #[test]
+    fn plan_creates_updates_and_removes() {
+        let desired = vec![
+            job("yuiclaw-heartbeat", "1h"),
+            job("yuiclaw-standup", "1d"),
+            job("yuiclaw-new", "5m"),
+            job("yuiclaw-legacy", "30m"),
+        ];
+        let applied: BTreeMap<String, AbeatJob> = [
+            job("yuiclaw-heartbeat", "30m"),
+            job("yuiclaw-standup", "1d"),
+            job("yuiclaw-old", "1h"),
+            job("yuiclaw-gone", "1h"),
+        ]
+        .into_iter()
+        .map(|job| (job.id.clone(), job))
+        .collect();
+        let registered: BTreeSet<String> = [
+            "yuiclaw-heartbeat",
+            "yuiclaw-standup",
+            "yuiclaw-legacy",
+            "yuiclaw-old",
+            "someone-elses-job",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+        let changes = plan(&desired, &applied, &registered);
+        assert_eq!(
+            changes,
+            vec![
+                Change::Update {
+                    previous: Some(job("yuiclaw-heartbeat", "30m")),
+                    job: job("yuiclaw-heartbeat", "1h"),
+                },
+                Change::Unchanged(job("yuiclaw-standup", "1d")),
+                Change::Create(job("yuiclaw-new", "5m")),
+                Change::Update {
+                    previous: None,
+                    job: job("yuiclaw-legacy", "30m"),
+                },
+                Change::Remove(job("yuiclaw-old", "1h")),
+            ]
+        );
+        assert_eq!(
+            changes[0].diff(),
+            vec!["~ yuiclaw-heartbeat", "    - every: 30m", "    + every: 1h"]
+        );
+
+        // Applying the plan leaves nothing to do.
+        let applied = desired.iter().map(|j| (j.id.clone(), j.clone())).collect();
+        let registered = desired.iter().map(|j| j.id.clone()).collect();
+        assert!(
+            plan(&desired, &applied, &registered)
+                .iter()
+                .all(|c| matches!(c, Change::Unchanged(_)))
+        );
+    }
+}
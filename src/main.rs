@@ -1,34 +1,122 @@
+mod adapters;
+mod api;
+mod bridge;
 mod cli;
 mod components;
+mod config;
+mod cron;
+mod doctor;
+mod email;
 mod env;
+#[cfg(test)]
+mod fake_http;
+mod heartbeat;
+mod http;
 mod init;
+mod jobs;
+mod matrix;
+mod message;
+mod metrics;
+mod notify;
 mod process;
+mod profile;
+mod quiet;
+mod state;
 mod status;
+mod supervisor;
+mod telegram;
+mod webhook;
 
 use clap::Parser;
-use cli::{Cli, Commands, DaemonCommands};
+use cli::{
+    AdapterCommands, Cli, Commands, ConfigCommands, DaemonCommands, DndCommands, JobsCommands,
+};
 
 #[tokio::main]
 async fn main() {
-    // Load ~/.config/yuiclaw/.env before anything else so that adapter tokens
-    // and other settings are available for all subcommands.
-    env::load_config_dotenv();
-
     let cli = Cli::parse();
 
+    // Resolve the profile and load its config.toml and .env (~/.config/yuiclaw/ by default)
+    // before anything else so that adapter tokens, the socket path and other settings are
+    // available for all subcommands.
+    // `yuiclaw config` must keep working when config.toml is broken, so that it can be fixed,
+    // and `yuiclaw doctor` reports the error as one of its checks.
+    let strict = !matches!(
+        cli.command,
+        Some(Commands::Config { .. } | Commands::Doctor { .. })
+    );
+    if let Err(e) = profile::init(cli.profile.as_deref(), strict) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
     // デフォルト（引数なし）は start と同等
-    let command = cli.command.unwrap_or(Commands::Start {
-        provider: "Gemini".to_string(),
-    });
+    let command = cli.command.unwrap_or(Commands::Start { provider: None });
 
     let result = match command {
         Commands::Daemon { action } => match action {
             DaemonCommands::Start => process::daemon_start().await,
+            DaemonCommands::Run => process::daemon_run().await,
             DaemonCommands::Status { json } => status::show_daemon_status(json).await,
             DaemonCommands::Stop => process::daemon_stop().await,
             DaemonCommands::Restart => process::daemon_restart().await,
         },
-        Commands::Start { provider } => process::start_stack(&provider).await,
+        Commands::Adapter { action } => match action {
+            AdapterCommands::List => adapters::list(),
+            AdapterCommands::Start { name } => adapters::start(&name).await,
+            AdapterCommands::Stop { name } => adapters::stop(&name).await,
+            AdapterCommands::Restart { name } => adapters::restart(&name).await,
+            AdapterCommands::Enable { name } => adapters::enable(&name).await,
+            AdapterCommands::Disable { name } => adapters::disable(&name).await,
+            AdapterCommands::Run { name } => adapters::run(&name).await,
+        },
+        Commands::Jobs { action } => match action {
+            JobsCommands::List { json } => jobs::list(json).await,
+            JobsCommands::Add {
+                name,
+                prompt,
+                channel,
+                every,
+                cron,
+                description,
+            } => {
+                jobs::add(
+                    &name,
+                    &prompt,
+                    channel.as_deref(),
+                    every.as_deref(),
+                    cron.as_deref(),
+                    description.as_deref(),
+                )
+                .await
+            }
+            JobsCommands::Remove { name } => jobs::remove(&name).await,
+            JobsCommands::Run {
+                name,
+                if_due,
+                scheduled,
+            } => jobs::run(&name, if_due, scheduled).await,
+            JobsCommands::Pause { name } => jobs::set_enabled(&name, false).await,
+            JobsCommands::Resume { name } => jobs::set_enabled(&name, true).await,
+        },
+        Commands::Dnd { action } => match action {
+            DndCommands::On { duration } => quiet::dnd_on(duration).await,
+            DndCommands::Off => quiet::dnd_off().await,
+            DndCommands::Status => quiet::dnd_status().await,
+        },
+        Commands::Config { action } => match action {
+            ConfigCommands::Show => config::show(),
+            ConfigCommands::Get { key } => config::get(&key),
+            ConfigCommands::Set { key, value } => config::set(&key, &value),
+            ConfigCommands::Unset { key } => config::unset(&key),
+            ConfigCommands::Path => config::print_path(),
+            ConfigCommands::Edit => config::edit(),
+        },
+        Commands::Start { provider } => {
+            let provider =
+                provider.unwrap_or_else(|| config::current().default_provider().to_string());
+            process::start_stack(&provider).await
+        }
         // Provider shorthand subcommands — map to start_stack_with_opts
         Commands::Gemini { new } => process::start_stack_with_opts("Gemini", new).await,
         Commands::Claude { new } => process::start_stack_with_opts("Claude", new).await,
@@ -38,10 +126,29 @@ async fn main() {
         Commands::Stop => process::stop_bridge().await,
         Commands::Restart => process::restart_stack().await,
         Commands::Status => status::show_status().await,
+        Commands::Doctor { json } => doctor::run(json).await,
         Commands::Init => init::initialize().await,
         Commands::Tick => process::run_tick().await,
-        Commands::Pub { message, channel } => process::publish(&message, channel.as_deref()).await,
+        Commands::Pub {
+            message,
+            file,
+            attach,
+            channel,
+            proactive,
+        } => match message::compose(&message::Body::from_args(message, file), &attach) {
+            Ok(message) if proactive => quiet::publish_command(&message, channel.as_deref()).await,
+            Ok(message) => process::publish(&message, channel.as_deref()).await,
+            Err(e) => Err(e.into()),
+        },
+        Commands::Ask {
+            question,
+            provider,
+            timeout,
+            json,
+        } => process::ask(&question, provider.as_deref(), timeout, json).await,
         Commands::Reset => process::reset_session().await,
+        Commands::ServeWebhook { listen } => webhook::serve(listen.as_deref()).await,
+        Commands::Metrics { textfile } => metrics::run(textfile.as_deref()).await,
     };
 
     if let Err(e) = result {
@@ -0,0 +1,497 @@
+//! Matrix channel adapter, run by the supervisor as `yuiclaw adapter run matrix`.
+//!
+//! It logs in to `MATRIX_HOMESERVER_URL` with `MATRIX_ACCESS_TOKEN`, joins the rooms listed
+//! in `MATRIX_ROOM_IDS`, and long-polls `/sync` for new messages in them. Text messages
+//! from anyone but the bot itself are forwarded to the bridge and the agent's reply is
+//! posted back to the room. Messages sent before the adapter started are not answered.
+
+use crate::adapters::{self, required_env};
+use crate::components::socket_path;
+use reqwest::Method;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long one `/sync` call waits for new events.
+const LONG_POLL_MS: u64 = 30_000;
+
+/// Pause after a failed sync before trying again.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let homeserver = Homeserver::new(
+        &required_env("MATRIX_HOMESERVER_URL")?,
+        &required_env("MATRIX_ACCESS_TOKEN")?,
+    );
+    let rooms = parse_room_ids(&required_env("MATRIX_ROOM_IDS")?)?;
+
+    // Fail fast on a bad token; the supervisor restarts the adapter with backoff.
+    let whoami: WhoAmI = homeserver
+        .request(Method::GET, "/account/whoami", &[], None)
+        .await?;
+    for room in &rooms {
+        if let Err(e) = homeserver.join(room).await {
+            eprintln!("matrix: cannot join {}: {}", room, e);
+        }
+    }
+    eprintln!(
+        "matrix: connected as {} (rooms: {})",
+        whoami.user_id,
+        rooms.len()
+    );
+
+    let mut relay = Relay::new(
+        homeserver,
+        rooms,
+        whoami.user_id,
+        socket_path().to_path_buf(),
+    );
+    loop {
+        if let Err(e) = relay.sync_once(LONG_POLL_MS).await {
+            eprintln!("matrix: {}", e);
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+    }
+}
+
+/// Parse a list of room IDs (`!abc123:example.org`) separated by commas and/or whitespace.
+fn parse_room_ids(raw: &str) -> Result<HashSet<String>, String> {
+    let rooms = adapters::split_list(raw)
+        .map(|room| {
+            if room.starts_with('!') && room.contains(':') {
+                Ok(room.to_string())
+            } else {
+                Err(format!(
+                    "Invalid room ID '{}' in MATRIX_ROOM_IDS (expected an ID such as !abc123:example.org, not an alias).",
+                    room
+                ))
+            }
+        })
+        .collect::<Result<HashSet<_>, _>>()?;
+    if rooms.is_empty() {
+        return Err("MATRIX_ROOM_IDS does not contain any room ID.".to_string());
+    }
+    Ok(rooms)
+}
+
+/// Minimal client for the Matrix client-server API.
+struct Homeserver {
+    http: reqwest::Client,
+    /// `<homeserver>/_matrix/client/v3`
+    base: String,
+    token: String,
+}
+
+/// Error body returned by the homeserver.
+#[derive(Debug, Deserialize)]
+struct MatrixError {
+    #[serde(default)]
+    errcode: String,
+    #[serde(default)]
+    error: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WhoAmI {
+    user_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncResponse {
+    next_batch: String,
+    #[serde(default)]
+    rooms: SyncRooms,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SyncRooms {
+    #[serde(default)]
+    join: HashMap<String, JoinedRoom>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JoinedRoom {
+    #[serde(default)]
+    timeline: Timeline,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Timeline {
+    #[serde(default)]
+    events: Vec<RoomEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RoomEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    event_id: String,
+    #[serde(default)]
+    sender: String,
+    #[serde(default)]
+    content: Value,
+}
+
+impl Homeserver {
+    fn new(url: &str, token: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base: format!("{}/_matrix/client/v3", url.trim_end_matches('/')),
+            token: token.to_string(),
+        }
+    }
+
+    /// Call the endpoint at `path` (relative to `/_matrix/client/v3`).
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, String)],
+        body: Option<Value>,
+    ) -> Result<T, String> {
+        // Name errors after the endpoint (`whoami`, `sync`, `join`, `send`).
+        let endpoint = path
+            .rsplit('/')
+            .find(|s| !s.is_empty() && s.chars().all(|c| c.is_ascii_lowercase()))
+            .unwrap_or(path);
+        let failed = |e: reqwest::Error| format!("{} failed: {}", endpoint, e.without_url());
+        let mut request = self
+            .http
+            .request(method, format!("{}{}", self.base, path))
+            .bearer_auth(&self.token)
+            .query(query)
+            // Leave room for the server-side long poll.
+            .timeout(Duration::from_millis(LONG_POLL_MS) + Duration::from_secs(30));
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await.map_err(failed)?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error: MatrixError = response.json().await.unwrap_or(MatrixError {
+                errcode: String::new(),
+                error: status.to_string(),
+            });
+            return Err(if error.errcode.is_empty() {
+                format!("{} failed: {}", endpoint, error.error)
+            } else {
+                format!("{} failed: {} ({})", endpoint, error.error, error.errcode)
+            });
+        }
+        response.json().await.map_err(failed)
+    }
+
+    async fn join(&self, room: &str) -> Result<(), String> {
+        let path = format!("/rooms/{}/join", encode_path_segment(room));
+        let _: Value = self
+            .request(Method::POST, &path, &[], Some(json!({})))
+            .await?;
+        Ok(())
+    }
+
+    /// Sync the timelines of `rooms` (message events only) since `since`.
+    async fn sync(
+        &self,
+        rooms: &HashSet<String>,
+        since: Option<&str>,
+        timeout_ms: u64,
+    ) -> Result<SyncResponse, String> {
+        let filter = json!({
+            "presence": { "types": [] },
+            "account_data": { "types": [] },
+            "room": {
+                "rooms": rooms,
+                "timeline": { "types": ["m.room.message"] },
+                "state": { "types": [] },
+                "ephemeral": { "types": [] },
+                "account_data": { "types": [] },
+            },
+        });
+        let mut query = vec![
+            ("filter", filter.to_string()),
+            ("timeout", timeout_ms.to_string()),
+        ];
+        if let Some(since) = since {
+            query.push(("since", since.to_string()));
+        }
+        self.request(Method::GET, "/sync", &query, None).await
+    }
+
+    async fn send_text(&self, room: &str, txn_id: &str, text: &str) -> Result<(), String> {
+        let path = format!(
+            "/rooms/{}/send/m.room.message/{}",
+            encode_path_segment(room),
+            encode_path_segment(txn_id)
+        );
+        let _: Value = self
+            .request(
+                Method::PUT,
+                &path,
+                &[],
+                Some(json!({ "msgtype": "m.text", "body": text })),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Percent-encode everything but unreserved characters, so that room IDs (`!a:b`) can be
+/// used as a path segment.
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Relays messages between the homeserver and the bridge.
+struct Relay {
+    homeserver: Homeserver,
+    rooms: HashSet<String>,
+    /// The bot's own user ID, whose messages (including its replies) are not relayed.
+    user_id: String,
+    /// Bridge socket the messages are forwarded to.
+    socket: PathBuf,
+    /// `next_batch` token of the last sync; `None` until the first sync.
+    since: Option<String>,
+    /// Counter for transaction IDs, which must be unique for the access token.
+    sent: u64,
+}
+
+impl Relay {
+    fn new(
+        homeserver: Homeserver,
+        rooms: HashSet<String>,
+        user_id: String,
+        socket: PathBuf,
+    ) -> Self {
+        Self {
+            homeserver,
+            rooms,
+            user_id,
+            socket,
+            since: None,
+            sent: 0,
+        }
+    }
+
+    /// Sync once (waiting up to `timeout_ms` for new events) and handle the new messages.
+    /// The first sync only catches up, so that old messages are not answered again.
+    async fn sync_once(&mut self, timeout_ms: u64) -> Result<(), String> {
+        let initial = self.since.is_none();
+        let response = self
+            .homeserver
+            .sync(
+                &self.rooms,
+                self.since.as_deref(),
+                if initial { 0 } else { timeout_ms },
+            )
+            .await?;
+        self.since = Some(response.next_batch);
+        if initial {
+            return Ok(());
+        }
+
+        let mut rooms: Vec<_> = response
+            .rooms
+            .join
+            .into_iter()
+            .filter(|(room, _)| self.rooms.contains(room))
+            .collect();
+        rooms.sort_by(|a, b| a.0.cmp(&b.0));
+        for (room, joined) in rooms {
+            for event in joined.timeline.events {
+                self.handle(&room, event).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle(&mut self, room: &str, event: RoomEvent) {
+        if event.kind != "m.room.message"
+            || event.sender == self.user_id
+            || event.content["msgtype"] != "m.text"
+        {
+            return;
+        }
+        let Some(text) = event.content["body"]
+            .as_str()
+            .filter(|t| !t.trim().is_empty())
+        else {
+            return;
+        };
+
+        // A channel per message keeps replies apart when several rooms are active.
+        let channel = format!("matrix-{}", event.event_id.trim_start_matches('$'));
+        let reply = match adapters::relay_to_bridge(&self.socket, text, &channel).await {
+            Ok(reply) if reply.trim().is_empty() => return,
+            Ok(reply) => reply,
+            Err(e) => {
+                eprintln!("matrix: {}", e);
+                format!("⚠️ {}", e)
+            }
+        };
+        self.sent += 1;
+        let txn_id = format!(
+            "yuiclaw-{}-{}",
+            chrono::Utc::now().timestamp_millis(),
+            self.sent
+        );
+        if let Err(e) = self.homeserver.send_text(room, &txn_id, &reply).await {
+            eprintln!("matrix: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::Request;
+    use crate::bridge::fake::FakeBridge;
+    use crate::fake_http::FakeHttpServer;
+    use tempfile::tempdir;
+
+    const ROOM: &str = "!room:example.org";
+
+    fn message(event_id: &str, sender: &str, body: &str) -> Value {
+        json!({
+            "type": "m.room.message",
+            "event_id": event_id,
+            "sender": sender,
+            "content": { "msgtype": "m.text", "body": body },
+        })
+    }
+
+    /// Local stand-in for a homeserver. The initial sync returns an old message; the next
+    /// one a message from Alice, the bot's own message and a message in another room.
+    async fn stub_homeserver() -> FakeHttpServer {
+        FakeHttpServer::start(|request| {
+            if request.header("authorization") != Some("Bearer secret") {
+                return (
+                    401,
+                    json!({ "errcode": "M_UNKNOWN_TOKEN", "error": "Invalid access token" }),
+                );
+            }
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/_matrix/client/v3/account/whoami") => {
+                    (200, json!({ "user_id": "@yui:example.org" }))
+                }
+                ("GET", "/_matrix/client/v3/sync") if !request.query.contains("since=") => {
+                    let events = [message("$old", "@alice:example.org", "yesterday")];
+                    (
+                        200,
+                        json!({
+                            "next_batch": "s1",
+                            "rooms": { "join": { ROOM: { "timeline": { "events": events } } } },
+                        }),
+                    )
+                }
+                ("GET", "/_matrix/client/v3/sync") => {
+                    let events = [
+                        message("$hello", "@alice:example.org", "hello"),
+                        message("$reply", "@yui:example.org", "Hi there"),
+                    ];
+                    let other = [message("$other", "@bob:example.org", "psst")];
+                    (
+                        200,
+                        json!({
+                            "next_batch": "s2",
+                            "rooms": { "join": {
+                                ROOM: { "timeline": { "events": events } },
+                                "!other:example.org": { "timeline": { "events": other } },
+                            } },
+                        }),
+                    )
+                }
+                ("PUT", _) => (200, json!({ "event_id": "$sent" })),
+                _ => (
+                    404,
+                    json!({ "errcode": "M_UNRECOGNIZED", "error": "Unrecognized request" }),
+                ),
+            }
+        })
+        .await
+    }
+
+    #[test]
+    fn room_ids_must_not_be_aliases() {
+        assert_eq!(
+            parse_room_ids("!a:example.org, !b:example.org").unwrap(),
+            HashSet::from(["!a:example.org".to_string(), "!b:example.org".to_string()])
+        );
+        let err = parse_room_ids("#general:example.org").unwrap_err();
+        assert!(err.contains("not an alias"), "{}", err);
+        assert!(parse_room_ids("").is_err());
+    }
+
+    #[test]
+    fn room_ids_are_percent_encoded_in_paths() {
+        assert_eq!(encode_path_segment(ROOM), "%21room%3Aexample.org");
+    }
+
+    #[tokio::test]
+    async fn relays_new_messages_from_others_and_replies_in_the_room() {
+        let dir = tempdir().unwrap();
+        let bridge = FakeBridge::start(&dir.path().join("acomm.sock"), &["Hi ", "there"]);
+        let homeserver = stub_homeserver().await;
+
+        let mut relay = Relay::new(
+            Homeserver::new(&homeserver.url, "secret"),
+            HashSet::from([ROOM.to_string()]),
+            "@yui:example.org".to_string(),
+            bridge.path.clone(),
+        );
+        relay.sync_once(0).await.unwrap();
+        relay.sync_once(0).await.unwrap();
+
+        let prompts: Vec<Request> = bridge
+            .requests()
+            .into_iter()
+            .filter(|r| matches!(r, Request::Prompt { .. }))
+            .collect();
+        assert_eq!(
+            prompts,
+            vec![Request::Prompt {
+                text: "hello".to_string(),
+                channel: Some("matrix-hello".to_string()),
+                provider: None,
+            }]
+        );
+
+        let requests = homeserver.requests();
+        assert!(requests[1].query.contains("since=s1"), "{:?}", requests[1]);
+        let sent: Vec<_> = requests.iter().filter(|r| r.method == "PUT").collect();
+        assert_eq!(sent.len(), 1);
+        assert!(
+            sent[0]
+                .path
+                .starts_with("/_matrix/client/v3/rooms/%21room%3Aexample.org/send/m.room.message/"),
+            "{}",
+            sent[0].path
+        );
+        assert_eq!(
+            sent[0].body,
+            json!({ "msgtype": "m.text", "body": "Hi there" })
+        );
+        assert_eq!(relay.since.as_deref(), Some("s2"));
+    }
+
+    #[tokio::test]
+    async fn homeserver_errors_include_the_matrix_error_code() {
+        let homeserver = stub_homeserver().await;
+        let err = Homeserver::new(&homeserver.url, "wrong")
+            .request::<WhoAmI>(Method::GET, "/account/whoami", &[], None)
+            .await
+            .unwrap_err();
+        assert_eq!(err, "whoami failed: Invalid access token (M_UNKNOWN_TOKEN)");
+    }
+}
@@ -0,0 +1,185 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Largest message `yuiclaw pub` sends, including attachments.
+pub const MAX_MESSAGE_BYTES: usize = 256 * 1024;
+
+/// Where the body of a `yuiclaw pub` message comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Body {
+    Inline(String),
+    Stdin,
+    File(PathBuf),
+}
+
+impl Body {
+    /// `-` reads stdin; `--file` wins over an inline message (clap rejects both together).
+    pub fn from_args(message: Option<String>, file: Option<PathBuf>) -> Self {
+        match (message, file) {
+            (_, Some(path)) => Self::File(path),
+            (Some(m), None) if m == "-" => Self::Stdin,
+            (Some(m), None) => Self::Inline(m),
+            (None, None) => Self::Inline(String::new()),
+        }
+    }
+}
+
+/// Build the message for `yuiclaw pub`: the body followed by one context block per
+/// attachment.
+pub fn compose(body: &Body, attachments: &[PathBuf]) -> Result<String, String> {
+    compose_with(
+        body,
+        attachments,
+        std::io::stdin().lock(),
+        MAX_MESSAGE_BYTES,
+    )
+}
+
+fn compose_with(
+    body: &Body,
+    attachments: &[PathBuf],
+    stdin: impl Read,
+    limit: usize,
+) -> Result<String, String> {
+    let mut message = match body {
+        Body::Inline(text) => text.clone(),
+        Body::Stdin => read_limited(stdin, "stdin", limit)?,
+        Body::File(path) => read_file(path, limit)?,
+    };
+    if message.trim().is_empty() {
+        return Err("Message is empty.".to_string());
+    }
+
+    for path in attachments {
+        let contents = read_file(path, limit)?;
+        message.push_str(&attachment_block(path, &contents));
+    }
+
+    if message.len() > limit {
+        return Err(too_large("Message", limit));
+    }
+    Ok(message)
+}
+
+fn attachment_block(path: &Path, contents: &str) -> String {
+    let name = path.display().to_string().replace('"', "'");
+    let newline = if contents.ends_with('\n') { "" } else { "\n" };
+    format!(
+        "\n\n<attachment path=\"{}\">\n{}{}</attachment>",
+        name, contents, newline
+    )
+}
+
+fn read_file(path: &Path, limit: usize) -> Result<String, String> {
+    let file =
+        std::fs::File::open(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    read_limited(file, &path.display().to_string(), limit)
+}
+
+/// Read at most `limit` bytes of UTF-8 text from `reader`, labelled `label` in errors.
+fn read_limited(reader: impl Read, label: &str, limit: usize) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    // Read one byte past the limit so that oversized input is detected without buffering
+    // all of it.
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Cannot read {}: {}", label, e))?;
+    if bytes.len() > limit {
+        return Err(too_large(label, limit));
+    }
+    String::from_utf8(bytes).map_err(|e| {
+        format!(
+            "{} is not valid UTF-8 (invalid byte at offset {}).",
+            label,
+            e.utf8_error().valid_up_to()
+        )
+    })
+}
+
+fn too_large(label: &str, limit: usize) -> String {
+    format!("{} exceeds the {} KiB size limit.", label, limit / 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn compose_stdin(input: &[u8], limit: usize) -> Result<String, String> {
+        compose_with(&Body::Stdin, &[], input, limit)
+    }
+
+    #[test]
+    fn dash_reads_stdin() {
+        assert_eq!(Body::from_args(Some("-".into()), None), Body::Stdin);
+        assert_eq!(
+            compose_stdin(b"line 1\nline 2\n", 1024).unwrap(),
+            "line 1\nline 2\n"
+        );
+    }
+
+    #[test]
+    fn file_body_and_attachments() {
+        let dir = tempdir().unwrap();
+        let body = dir.path().join("report.md");
+        let log = dir.path().join("app.log");
+        std::fs::write(&body, "Daily report").unwrap();
+        std::fs::write(&log, "error: disk full").unwrap();
+
+        let message = compose_with(
+            &Body::from_args(None, Some(body)),
+            std::slice::from_ref(&log),
+            &b""[..],
+            1024,
+        )
+        .unwrap();
+        assert_eq!(
+            message,
+            format!(
+                "Daily report\n\n<attachment path=\"{}\">\nerror: disk full\n</attachment>",
+                log.display()
+            )
+        );
+    }
+
+    #[test]
+    fn oversized_input_is_rejected() {
+        let err = compose_stdin(&[b'a'; 2049], 2048).unwrap_err();
+        assert_eq!(err, "stdin exceeds the 2 KiB size limit.");
+
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("big.log");
+        std::fs::write(&log, [b'b'; 1500]).unwrap();
+        let err =
+            compose_with(&Body::Inline("x".repeat(1000)), &[log], &b""[..], 2048).unwrap_err();
+        assert!(err.starts_with("Message exceeds"), "{}", err);
+    }
+
+    #[test]
+    fn invalid_utf8_is_reported_with_offset() {
+        let err = compose_stdin(b"ok\xff\xfe", 1024).unwrap_err();
+        assert_eq!(err, "stdin is not valid UTF-8 (invalid byte at offset 2).");
+    }
+
+    #[test]
+    fn empty_messages_and_missing_files_are_errors() {
+        assert_eq!(
+            compose_stdin(b"  \n", 1024).unwrap_err(),
+            "Message is empty."
+        );
+
+        let err = compose_with(
+            &Body::File(PathBuf::from("/nonexistent/yuiclaw.txt")),
+            &[],
+            &b""[..],
+            1024,
+        )
+        .unwrap_err();
+        assert!(
+            err.starts_with("Cannot read /nonexistent/yuiclaw.txt"),
+            "{}",
+            err
+        );
+    }
+}
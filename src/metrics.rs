@@ -0,0 +1,301 @@
+//! Prometheus metrics: bridge and adapter health from the status probe, plus the counters
+//! persisted in the state directory (restarts, published messages, ticks).
+//!
+//! Exposed by `yuiclaw metrics` (stdout, or a node_exporter textfile with `--textfile`)
+//! and by `GET /metrics` on the control API.
+
+use crate::state::{self, Counters};
+use crate::status::{self, DaemonStatus};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Content type of the Prometheus text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Print the metrics, or write them atomically to `textfile`.
+pub async fn run(textfile: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let text = collect().await;
+    match textfile {
+        Some(path) => write_textfile(path, &text)?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Probe the daemon and render every metric.
+pub async fn collect() -> String {
+    render(&status::daemon_status().await, &state::counters())
+}
+
+/// Count a message published to the bridge by `source` (`cli`, `api`, `webhook`).
+pub fn record_publish(source: &str, channel: Option<&str>) {
+    record_publish_in(&state::counters_path(), source, channel);
+}
+
+/// [`record_publish`] into the counters file at `path`.
+pub fn record_publish_in(path: &Path, source: &str, channel: Option<&str>) {
+    let heartbeat = channel == Some(crate::bridge::HEARTBEAT_CHANNEL);
+    // Recording a metric must never fail a publish.
+    let _ = state::update_counters_in(path, |c| {
+        *c.published.entry(source.to_string()).or_default() += 1;
+        if heartbeat {
+            c.last_heartbeat = Some(chrono::Utc::now().timestamp());
+        }
+    });
+}
+
+/// Count a restart of `process` by the supervisor.
+pub fn record_restart(process: &str) {
+    state::update_counters(|c| *c.restarts.entry(process.to_string()).or_default() += 1);
+}
+
+/// Count a `yuiclaw tick` run.
+pub fn record_tick(success: bool) {
+    state::update_counters(|c| {
+        if success {
+            c.ticks_succeeded += 1;
+        } else {
+            c.ticks_failed += 1;
+        }
+    });
+}
+
+/// node_exporter may read the file at any time, so it is replaced rather than rewritten.
+fn write_textfile(path: &Path, text: &str) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::other(format!("Not a file path: {}", path.display())))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp = path.with_file_name(tmp_name);
+    std::fs::write(&tmp, text)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+fn render(status: &DaemonStatus, counters: &Counters) -> String {
+    let profile = format!("profile=\"{}\"", escape(&status.profile));
+    let mut out = Metrics::default();
+
+    out.family(
+        "yuiclaw_bridge_up",
+        "gauge",
+        "Whether the bridge accepts connections on its socket.",
+    );
+    out.sample("yuiclaw_bridge_up", &profile, status.bridge_running as u8);
+    if let crate::components::BridgeHealth::Responding { latency_ms } = status.bridge {
+        out.family(
+            "yuiclaw_bridge_probe_latency_seconds",
+            "gauge",
+            "Round trip of the status ping to the bridge.",
+        );
+        out.sample(
+            "yuiclaw_bridge_probe_latency_seconds",
+            &profile,
+            latency_ms / 1000.0,
+        );
+    }
+
+    out.family(
+        "yuiclaw_supervisor_up",
+        "gauge",
+        "Whether the `yuiclaw daemon run` supervisor is alive.",
+    );
+    out.sample(
+        "yuiclaw_supervisor_up",
+        &profile,
+        status.supervisor_pid.is_some() as u8,
+    );
+
+    if !status.channels.is_empty() {
+        out.family(
+            "yuiclaw_adapter_enabled",
+            "gauge",
+            "Whether the adapter is enabled and not stopped.",
+        );
+        for channel in &status.channels {
+            let labels = format!("{},adapter=\"{}\"", profile, escape(&channel.label));
+            out.sample(
+                "yuiclaw_adapter_enabled",
+                &labels,
+                (channel.enabled && !channel.stopped) as u8,
+            );
+        }
+        out.family(
+            "yuiclaw_adapter_connected",
+            "gauge",
+            "Whether the adapter is connected to the bridge.",
+        );
+        for channel in &status.channels {
+            let labels = format!("{},adapter=\"{}\"", profile, escape(&channel.label));
+            out.sample(
+                "yuiclaw_adapter_connected",
+                &labels,
+                channel.connected as u8,
+            );
+        }
+    }
+
+    out.family(
+        "yuiclaw_restarts_total",
+        "counter",
+        "Restarts of a crashed process by the supervisor.",
+    );
+    for (process, count) in &counters.restarts {
+        let labels = format!("{},process=\"{}\"", profile, escape(process));
+        out.sample("yuiclaw_restarts_total", &labels, count);
+    }
+
+    out.family(
+        "yuiclaw_published_messages_total",
+        "counter",
+        "Messages published to the bridge, by source.",
+    );
+    for (source, count) in &counters.published {
+        let labels = format!("{},source=\"{}\"", profile, escape(source));
+        out.sample("yuiclaw_published_messages_total", &labels, count);
+    }
+
+    out.family(
+        "yuiclaw_abeat_ticks_total",
+        "counter",
+        "`yuiclaw tick` runs, by result (abeat or the built-in scheduler).",
+    );
+    for (result, count) in [
+        ("success", counters.ticks_succeeded),
+        ("failure", counters.ticks_failed),
+    ] {
+        let labels = format!("{},result=\"{}\"", profile, result);
+        out.sample("yuiclaw_abeat_ticks_total", &labels, count);
+    }
+
+    if let Some(last) = counters.last_heartbeat {
+        out.family(
+            "yuiclaw_last_heartbeat_timestamp_seconds",
+            "gauge",
+            "Unix time of the last heartbeat prompt.",
+        );
+        out.sample("yuiclaw_last_heartbeat_timestamp_seconds", &profile, last);
+    }
+
+    out.0
+}
+
+#[derive(Default)]
+struct Metrics(String);
+
+impl Metrics {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.0, "# HELP {} {}", name, help);
+        let _ = writeln!(self.0, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &str, value: impl std::fmt::Display) {
+        let _ = writeln!(self.0, "{}{{{}}} {}", name, labels, value);
+    }
+}
+
+/// Escape a label value: backslash, double quote and line feed.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::BridgeHealth;
+    use crate::status::ChannelStatus;
+
+    fn status(bridge: BridgeHealth) -> DaemonStatus {
+        DaemonStatus {
+            profile: "work".to_string(),
+            bridge_running: bridge.is_running(),
+            bridge,
+            socket_path: "/tmp/acomm.sock".to_string(),
+            supervisor_pid: Some(42),
+            channels: vec![
+                ChannelStatus {
+                    label: "Slack".to_string(),
+                    connected: true,
+                    enabled: true,
+                    stopped: false,
+                },
+                ChannelStatus {
+                    label: "Discord".to_string(),
+                    connected: false,
+                    enabled: true,
+                    stopped: true,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn renders_status_and_counters() {
+        let mut counters = Counters::default();
+        counters.restarts.insert("bridge".to_string(), 3);
+        counters.published.insert("cli".to_string(), 5);
+        counters.published.insert("webhook".to_string(), 2);
+        counters.ticks_succeeded = 7;
+        counters.ticks_failed = 1;
+        counters.last_heartbeat = Some(1_700_000_000);
+
+        let text = render(
+            &status(BridgeHealth::Responding { latency_ms: 12.5 }),
+            &counters,
+        );
+        for line in [
+            "# TYPE yuiclaw_bridge_up gauge",
+            "yuiclaw_bridge_up{profile=\"work\"} 1",
+            "yuiclaw_bridge_probe_latency_seconds{profile=\"work\"} 0.0125",
+            "yuiclaw_supervisor_up{profile=\"work\"} 1",
+            "yuiclaw_adapter_enabled{profile=\"work\",adapter=\"Discord\"} 0",
+            "yuiclaw_adapter_connected{profile=\"work\",adapter=\"Slack\"} 1",
+            "# TYPE yuiclaw_restarts_total counter",
+            "yuiclaw_restarts_total{profile=\"work\",process=\"bridge\"} 3",
+            "yuiclaw_published_messages_total{profile=\"work\",source=\"webhook\"} 2",
+            "yuiclaw_abeat_ticks_total{profile=\"work\",result=\"success\"} 7",
+            "yuiclaw_abeat_ticks_total{profile=\"work\",result=\"failure\"} 1",
+            "yuiclaw_last_heartbeat_timestamp_seconds{profile=\"work\"} 1700000000",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing {:?} in\n{}",
+                line,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn omits_latency_and_heartbeat_when_unknown() {
+        let text = render(&status(BridgeHealth::NotRunning), &Counters::default());
+        assert!(text.contains("yuiclaw_bridge_up{profile=\"work\"} 0\n"));
+        assert!(!text.contains("yuiclaw_bridge_probe_latency_seconds"));
+        assert!(!text.contains("yuiclaw_last_heartbeat_timestamp_seconds"));
+        // Tick counters are always present so that rate() works from the first scrape.
+        assert!(
+            text.contains("yuiclaw_abeat_ticks_total{profile=\"work\",result=\"failure\"} 0\n")
+        );
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
+    #[test]
+    fn writes_the_textfile_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("yuiclaw.prom");
+        write_textfile(&path, "old\n").unwrap();
+        write_textfile(&path, "new\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,298 @@
+//! Outbound webhooks: a JSON payload is POSTed to the URLs in the `[notify.<name>]` sections
+//! of config.toml when the daemon starts or stops, a supervised process crashes, is
+//! restarted or crash-loops, the bridge is stopped, or a heartbeat reports something.
+//!
+//! Deliveries that fail with a network error, 408, 429 or 5xx are retried with exponential
+//! backoff. A target with a `secret` gets an `X-Hub-Signature-256` HMAC of every payload,
+//! the same header that `yuiclaw serve-webhook` checks.
+
+use crate::bridge;
+use crate::config::{self, NotifyTarget};
+use crate::profile;
+use crate::webhook;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Attempts per delivery, including the first one.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry; doubled after every failed attempt.
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest time one attempt may take.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause before reconnecting to the bridge to watch heartbeats.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// What happened. Also the names accepted by `events` in config.toml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// The supervisor (`yuiclaw daemon run`) started.
+    DaemonStarted,
+    /// The supervisor stopped, on request or because the bridge crash-looped.
+    DaemonStopped,
+    /// `yuiclaw stop` stopped a bridge that was not owned by a running supervisor.
+    BridgeStopped,
+    /// A supervised process exited with a failure.
+    Crashed,
+    /// A supervised process was started again after it exited.
+    Restarted,
+    /// A supervised process crash-looped and is no longer restarted.
+    GaveUp,
+    /// A heartbeat answered with something other than `HEARTBEAT_OK`.
+    Heartbeat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Event {
+    pub event: EventKind,
+    /// Supervised process the event is about (`bridge` or an adapter name).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+    /// Human-readable description, e.g. the supervisor's log line.
+    pub message: String,
+}
+
+impl Event {
+    pub fn new(event: EventKind, message: impl Into<String>) -> Self {
+        Self {
+            event,
+            component: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn component(mut self, name: &str) -> Self {
+        self.component = Some(name.to_string());
+        self
+    }
+}
+
+/// JSON body of every delivery.
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a Event,
+    profile: &'a str,
+    /// RFC 3339 local time.
+    timestamp: String,
+}
+
+/// Whether any configured target subscribes to `kind`.
+pub fn wants(kind: EventKind) -> bool {
+    config::current()
+        .notify
+        .values()
+        .any(|target| target.subscribes_to(kind))
+}
+
+/// Deliver `event` to every target that subscribes to it. Returns once every delivery has
+/// succeeded or given up; failures are logged.
+pub async fn send(event: Event) {
+    deliver_all(
+        &config::current().notify,
+        &event,
+        &profile::current().name,
+        FIRST_RETRY_DELAY,
+    )
+    .await;
+}
+
+/// Like [`send`], without waiting for the deliveries.
+pub fn spawn(event: Event) {
+    if wants(event.event) {
+        tokio::spawn(send(event));
+    }
+}
+
+/// Follow the bridge at `socket` and send a [`EventKind::Heartbeat`] event for every
+/// heartbeat that reports something, reconnecting whenever the bridge goes away.
+pub async fn watch_heartbeats(socket: PathBuf) {
+    loop {
+        // Connection errors are expected while the bridge (re)starts and are reported
+        // through the supervisor's own events.
+        let _ = bridge::watch_heartbeats(&socket, |text| {
+            spawn(Event::new(EventKind::Heartbeat, text));
+        })
+        .await;
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn deliver_all(
+    targets: &BTreeMap<String, NotifyTarget>,
+    event: &Event,
+    profile: &str,
+    first_retry_delay: Duration,
+) {
+    let targets: Vec<_> = targets
+        .iter()
+        .filter(|(_, target)| target.subscribes_to(event.event))
+        .collect();
+    if targets.is_empty() {
+        return;
+    }
+    let body = serde_json::to_vec(&Payload {
+        event,
+        profile,
+        timestamp: chrono::Local::now().to_rfc3339(),
+    })
+    .expect("payload serializes");
+    let http = reqwest::Client::new();
+    let deliveries = targets.into_iter().map(|(name, target)| {
+        let (http, body) = (&http, &body);
+        async move {
+            if let Err(e) = deliver(http, target, body, first_retry_delay).await {
+                eprintln!("notify: {}: {}", name, e);
+            }
+        }
+    });
+    futures::future::join_all(deliveries).await;
+}
+
+/// POST `body` to `target`, retrying transient failures.
+async fn deliver(
+    http: &reqwest::Client,
+    target: &NotifyTarget,
+    body: &[u8],
+    first_retry_delay: Duration,
+) -> Result<(), String> {
+    let mut delay = first_retry_delay;
+    let mut attempt = 1;
+    loop {
+        let mut request = http
+            .post(&target.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .timeout(REQUEST_TIMEOUT)
+            .body(body.to_vec());
+        if let Some(secret) = &target.secret {
+            request = request.header("X-Hub-Signature-256", webhook::signature(secret, body));
+        }
+        // Errors leave out the URL, which often contains a token.
+        let failure = match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                let transient = status.is_server_error()
+                    || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                    || status == reqwest::StatusCode::REQUEST_TIMEOUT;
+                if !transient {
+                    return Err(format!("rejected with {}", status));
+                }
+                status.to_string()
+            }
+            Err(e) => e.without_url().to_string(),
+        };
+        if attempt == MAX_ATTEMPTS {
+            return Err(format!(
+                "giving up after {} attempts (last error: {})",
+                attempt, failure
+            ));
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_http::FakeHttpServer;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn target(url: &str, events: &[EventKind]) -> NotifyTarget {
+        NotifyTarget {
+            url: url.to_string(),
+            events: events.to_vec(),
+            secret: None,
+        }
+    }
+
+    #[test]
+    fn targets_without_events_subscribe_to_everything() {
+        assert!(target("http://x", &[]).subscribes_to(EventKind::Heartbeat));
+        let crashes = target("http://x", &[EventKind::Crashed, EventKind::GaveUp]);
+        assert!(crashes.subscribes_to(EventKind::GaveUp));
+        assert!(!crashes.subscribes_to(EventKind::DaemonStarted));
+    }
+
+    #[tokio::test]
+    async fn delivers_the_event_to_subscribed_targets() {
+        let server = FakeHttpServer::start(|_| (200, json!(null))).await;
+        let mut crash_target = target(&format!("{}/crashes", server.url), &[EventKind::Crashed]);
+        crash_target.secret = Some("s3cret".to_string());
+        let targets = BTreeMap::from([
+            (
+                "all".to_string(),
+                target(&format!("{}/all", server.url), &[]),
+            ),
+            ("crashes".to_string(), crash_target),
+            (
+                "heartbeats".to_string(),
+                target(
+                    &format!("{}/heartbeats", server.url),
+                    &[EventKind::Heartbeat],
+                ),
+            ),
+        ]);
+
+        let event =
+            Event::new(EventKind::Crashed, "slack: exited (exit status: 1)").component("slack");
+        deliver_all(&targets, &event, "work", Duration::ZERO).await;
+
+        let mut requests = server.requests();
+        requests.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            requests.iter().map(|r| r.path.as_str()).collect::<Vec<_>>(),
+            ["/all", "/crashes"]
+        );
+        let body = &requests[0].body;
+        assert_eq!(body["event"], "crashed");
+        assert_eq!(body["component"], "slack");
+        assert_eq!(body["message"], "slack: exited (exit status: 1)");
+        assert_eq!(body["profile"], "work");
+        assert!(body["timestamp"].is_string());
+        assert_eq!(requests[0].header("x-hub-signature-256"), None);
+        assert!(
+            requests[1]
+                .header("x-hub-signature-256")
+                .is_some_and(|s| s.starts_with("sha256="))
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures_but_not_rejections() {
+        let calls = AtomicUsize::new(0);
+        let server = FakeHttpServer::start(move |request| match request.path.as_str() {
+            "/flaky" if calls.fetch_add(1, Ordering::SeqCst) < 2 => (503, json!(null)),
+            "/flaky" => (200, json!(null)),
+            _ => (404, json!(null)),
+        })
+        .await;
+        let http = reqwest::Client::new();
+
+        let flaky = target(&format!("{}/flaky", server.url), &[]);
+        deliver(&http, &flaky, b"{}", Duration::ZERO).await.unwrap();
+        assert_eq!(server.requests().len(), 3);
+
+        let missing = target(&format!("{}/missing", server.url), &[]);
+        let err = deliver(&http, &missing, b"{}", Duration::ZERO)
+            .await
+            .unwrap_err();
+        assert_eq!(err, "rejected with 404 Not Found");
+        assert_eq!(server.requests().len(), 4);
+
+        let down = target("http://127.0.0.1:1/hook?token=abc", &[]);
+        let err = deliver(&http, &down, b"{}", Duration::ZERO)
+            .await
+            .unwrap_err();
+        assert!(err.starts_with("giving up after 5 attempts"), "{}", err);
+        assert!(!err.contains("token=abc"), "{}", err);
+    }
+}
@@ -1,43 +1,46 @@
-use crate::components::{self, SOCKET_PATH};
-use std::collections::HashSet;
+use crate::adapters::{self, Adapter};
+use crate::api;
+use crate::bridge::{BridgeClient, BridgeError, Event, Request};
+use crate::components::{self, socket_path};
+use crate::config::{self, Config, TuiPreference};
+use crate::heartbeat;
+use crate::jobs;
+use crate::metrics;
+use crate::notify::{self, EventKind};
+use crate::profile;
+use crate::quiet;
+use crate::state::{self, ProcessRecord};
+use crate::supervisor::{self, ChildSpec, RestartMode, RestartPolicy, SUPERVISOR_RECORD};
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Stdio;
-use tokio::net::UnixStream;
+use std::time::{Duration, Instant};
 use tokio::process::Command;
 
-#[derive(Debug, Clone, Copy)]
-struct ChannelAdapterSpec {
-    label: &'static str,
-    env_keys: &'static [&'static str],
-    adapter_flag: &'static str,
-}
-
-const CHANNEL_ADAPTER_SPECS: [ChannelAdapterSpec; 3] = [
-    ChannelAdapterSpec {
-        label: "ntfy",
-        env_keys: &["NTFY_TOPIC"],
-        adapter_flag: "--ntfy",
-    },
-    ChannelAdapterSpec {
-        label: "Discord",
-        env_keys: &["DISCORD_BOT_TOKEN"],
-        adapter_flag: "--discord",
-    },
-    ChannelAdapterSpec {
-        label: "Slack",
-        env_keys: &["SLACK_APP_TOKEN", "SLACK_BOT_TOKEN"],
-        adapter_flag: "--slack",
-    },
-];
+/// Ownership record name of the supervised `acomm --bridge` process.
+const BRIDGE_RECORD: &str = "bridge";
+
+/// How long `yuiclaw stop` waits for the `bridge_stopped` notification, so that an
+/// unreachable target does not hold it up while notify retries.
+const STOP_NOTIFY_TIMEOUT: Duration = Duration::from_secs(2);
+
+const ACOMM_NOT_FOUND: &str = "acomm not found in PATH. \
+     See https://github.com/yuiseki/acomm for installation instructions.";
 
 /// Launch the full stack:
-///   1. If daemon (bridge) is not running: silently initialise amem / abeat and start adapters
+///   1. If daemon (bridge) is not running: start the background supervisor, which initialises
+///      amem / abeat and owns the bridge and configured adapters
 ///   2. exec(2) into the TypeScript TUI (acomm-tui), replacing this process
 pub async fn start_stack(provider: &str) -> Result<(), Box<dyn std::error::Error>> {
-    if !is_bridge_running() {
-        initialize_runtime_components().await?;
-        auto_start_configured_adapters().await;
+    if !bridge_socket_accepts_connection().await {
+        start_supervisor_in_background().await?;
+        if !wait_for_bridge_socket().await {
+            eprintln!(
+                "Warning: bridge did not become ready; see {}",
+                supervisor::log_path().display()
+            );
+        }
     }
 
     let amem_root = std::env::var("AMEM_ROOT")
@@ -46,37 +49,31 @@ pub async fn start_stack(provider: &str) -> Result<(), Box<dyn std::error::Error
         .map(|v| format!("{} (AMEM_ROOT)", v))
         .unwrap_or_else(|| "~/.amem (default)".to_string());
     eprintln!("Starting YuiClaw...");
+    if !profile::current().is_default() {
+        eprintln!("Profile: {}", profile::current().name);
+    }
     eprintln!("Memory: {}", amem_root);
     eprintln!("Launching acomm-tui... (press q to quit)");
 
     // exec(2) into acomm-tui — this process is replaced by the TypeScript TUI.
     // acomm-tui is the bin entry from repos/acomm/tui/package.json installed via `make install`.
-    // Falls back to `acomm` (Rust TUI) if acomm-tui is not in PATH.
+    // Falls back to `acomm` (Rust TUI) if acomm-tui is not in PATH, unless `tui` in
+    // config.toml pins one of them.
     #[cfg(unix)]
     {
         use std::os::unix::process::CommandExt;
 
-        // Prefer the TypeScript TUI; fall back to Rust TUI for backwards compatibility.
-        let tui_cmd = if is_command_in_path("acomm-tui") {
-            "acomm-tui"
-        } else {
-            "acomm"
-        };
-
+        let tui_cmd = tui_command();
         let err = std::process::Command::new(tui_cmd)
             .arg("--provider")
             .arg(provider)
             .exec();
-        return Err(format!("Failed to exec {}: {}", tui_cmd, err).into());
+        Err(format!("Failed to exec {}: {}", tui_cmd, err).into())
     }
 
     #[cfg(not(unix))]
     {
-        let tui_cmd = if is_command_in_path("acomm-tui") {
-            "acomm-tui"
-        } else {
-            "acomm"
-        };
+        let tui_cmd = tui_command();
         let status = Command::new(tui_cmd)
             .arg("--provider")
             .arg(provider)
@@ -94,37 +91,117 @@ pub async fn start_stack(provider: &str) -> Result<(), Box<dyn std::error::Error
 /// Equivalent to `yuiclaw stop` followed by the non-interactive startup portion of `yuiclaw start`.
 pub async fn restart_stack() -> Result<(), Box<dyn std::error::Error>> {
     stop_bridge().await?;
-    initialize_runtime_components().await?;
+    start_supervisor_in_background().await?;
 
-    if !ensure_bridge_running_for_adapters().await {
-        return Err("Failed to start acomm bridge.".into());
+    if !wait_for_bridge_socket().await {
+        return Err(bridge_start_failed_message().into());
     }
-    auto_start_configured_adapters().await;
 
     println!("Bridge restarted. (TUI not started)");
     Ok(())
 }
 
 /// Start the daemon (bridge + configured adapters) in the background without launching the TUI.
+///
+/// The work is delegated to a detached `yuiclaw daemon run` supervisor that owns every child.
 pub async fn daemon_start() -> Result<(), Box<dyn std::error::Error>> {
-    if is_bridge_running() {
-        println!("Daemon is already running.");
+    if let Some(record) = state::live_record(SUPERVISOR_RECORD) {
+        println!("Daemon is already running. (supervisor pid {})", record.pid);
+        return Ok(());
+    }
+    if bridge_socket_accepts_connection().await {
+        println!(
+            "Bridge is already running outside the supervisor. \
+             Run `yuiclaw daemon restart` to hand it over."
+        );
         return Ok(());
     }
-    initialize_runtime_components().await?;
 
-    if !ensure_bridge_running_for_adapters().await {
-        return Err("Failed to start acomm bridge.".into());
+    start_supervisor_in_background().await?;
+    if !wait_for_bridge_socket().await {
+        return Err(bridge_start_failed_message().into());
     }
-    auto_start_configured_adapters().await;
 
-    println!("Daemon started. (bridge + adapters running in background)");
+    println!("Daemon started. (supervisor + bridge + adapters running in background)");
     Ok(())
 }
 
-/// Stop all adapter processes and the acomm bridge.
+/// Run the process supervisor in the foreground (`yuiclaw daemon run`).
+///
+/// Owns `acomm --bridge` and every configured adapter, restarting them with exponential
+/// backoff until SIGTERM/SIGINT. Suitable as a systemd `ExecStart`.
+pub async fn daemon_run() -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(record) = state::live_record(SUPERVISOR_RECORD) {
+        return Err(format!("Daemon is already running. (supervisor pid {})", record.pid).into());
+    }
+    initialize_runtime_components().await?;
+
+    // Children left behind by a supervisor that was killed without cleaning up.
+    for record in state::live_records() {
+        eprintln!(
+            "Stopping orphaned {} process (pid {})",
+            record.name, record.pid
+        );
+        terminate_owned_process(&record).await;
+    }
+
+    if bridge_socket_accepts_connection().await {
+        return Err("Bridge is already running outside the supervisor. \
+             Stop it with `yuiclaw stop` first."
+            .into());
+    }
+    // Stale socket file from a previous crash can block bridge/adapters.
+    remove_socket_file_if_exists(socket_path())?;
+
+    let workdir = ensure_daemon_session_workdir()?;
+    let bridge = acomm_child_spec(BRIDGE_RECORD, &["--bridge".to_string()], workdir.as_deref());
+
+    // Adapters stopped with `yuiclaw adapter stop` only stay stopped until the next start.
+    state::clear_stopped_adapters();
+    let adapters = move || desired_adapter_specs(workdir.as_deref());
+
+    if notify::wants(EventKind::Heartbeat) {
+        tokio::spawn(notify::watch_heartbeats(socket_path().to_path_buf()));
+    }
+    tokio::spawn(api::serve(config::current().api.clone()));
+    tokio::spawn(quiet::watch());
+    tokio::spawn(heartbeat::watch_activity(socket_path().to_path_buf()));
+    if !components::is_command_available("abeat").await {
+        supervisor::log(
+            "abeat not found in PATH; running scheduled jobs with the built-in scheduler",
+        );
+        tokio::spawn(jobs::run_scheduler());
+    }
+    supervisor::run(bridge, adapters, socket_path(), RestartPolicy::default()).await
+}
+
+/// Adapters the supervisor should be running: configured, enabled and not stopped with
+/// `yuiclaw adapter stop`.
+///
+/// config.toml is read again on every call so that `yuiclaw adapter enable/disable` takes
+/// effect when the supervisor reloads on SIGHUP.
+fn desired_adapter_specs(workdir: Option<&Path>) -> Vec<ChildSpec> {
+    let config = Config::load(&profile::current().config_path()).unwrap_or_else(|e| {
+        eprintln!("Warning: {}; using the settings loaded at startup", e);
+        config::current().clone()
+    });
+    let stopped = state::stopped_adapters();
+    adapters::to_autostart(&config, &adapters::present_env_keys())
+        .into_iter()
+        .filter(|adapter| !stopped.contains(&adapter.name))
+        .map(|adapter| adapter_child_spec(&adapter, workdir))
+        .collect()
+}
+
+/// Stop the supervisor (if any), then any remaining adapter processes and the acomm bridge.
 pub async fn daemon_stop() -> Result<(), Box<dyn std::error::Error>> {
+    let supervised = stop_supervisor().await?;
     stop_all_adapters().await;
+    if supervised {
+        remove_socket_file_if_exists(socket_path())?;
+        println!("Daemon stopped.");
+        return Ok(());
+    }
     stop_bridge().await
 }
 
@@ -134,7 +211,7 @@ pub async fn daemon_restart() -> Result<(), Box<dyn std::error::Error>> {
     // Be extra defensive on restart: remove any leftover socket before startup.
     // This covers cases where the bridge process has exited but the stale socket
     // file remains and can confuse daemon-start checks.
-    let _ = remove_socket_file_if_exists(SOCKET_PATH)?;
+    let _ = remove_socket_file_if_exists(socket_path())?;
     daemon_start().await
 }
 
@@ -147,73 +224,115 @@ pub async fn start_stack_with_opts(
     provider: &str,
     new_session: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if new_session && is_bridge_running() {
+    if new_session && bridge_socket_accepts_connection().await {
         // Ask the bridge to discard the current session before we attach
-        let _ = Command::new("acomm")
-            .arg("--publish")
-            .arg("/clear")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .await;
-        // Brief pause so the bridge has time to process /clear before we exec
+        if let Ok(mut client) = BridgeClient::connect(socket_path()).await {
+            let _ = client.control("clear").await;
+        }
+        // Brief pause so the bridge has time to process the reset before we exec
         tokio::time::sleep(std::time::Duration::from_millis(200)).await;
     }
     start_stack(provider).await
 }
 
 /// Stop the acomm bridge process and remove the socket file.
+///
+/// When the bridge is owned by the supervisor, the supervisor is stopped instead so that it
+/// does not restart the bridge; its adapters are stopped along with it.
 pub async fn stop_bridge() -> Result<(), Box<dyn std::error::Error>> {
-    if !is_bridge_running() {
-        println!("Bridge is not running.");
+    if stop_supervisor().await? {
+        remove_socket_file_if_exists(socket_path())?;
+        println!("Bridge stopped.");
         return Ok(());
     }
 
-    // Send SIGTERM to any process matching "acomm.*--bridge"
-    let killed = Command::new("pkill")
-        .arg("-f")
-        .arg("acomm.*--bridge")
-        .status()
-        .await
-        .map(|s| s.success())
-        .unwrap_or(false);
+    if let Some(record) = state::live_record(BRIDGE_RECORD) {
+        // Bridge left behind by a supervisor that did not shut down cleanly.
+        terminate_owned_process(&record).await;
+        remove_socket_file_if_exists(socket_path())?;
+        notify_bridge_stopped(format!(
+            "bridge (pid {}) stopped by `yuiclaw stop`",
+            record.pid
+        ))
+        .await;
+        println!("Bridge stopped.");
+        return Ok(());
+    }
 
-    if !killed {
-        println!("Bridge process not found; cleaning up socket...");
+    if !socket_path().exists() {
+        println!("Bridge is not running.");
+        return Ok(());
+    }
+
+    if bridge_socket_accepts_connection().await {
+        println!(
+            "Bridge at {} was not started by yuiclaw; leaving it running.",
+            socket_path().display()
+        );
+        return Ok(());
     }
 
-    // Remove the Unix socket file
-    remove_socket_file_if_exists(SOCKET_PATH)?;
+    // Nobody is listening: the socket file is left over from a crash.
+    println!("Bridge process not found; cleaning up socket...");
+    remove_socket_file_if_exists(socket_path())?;
+    notify_bridge_stopped("bridge was no longer running; `yuiclaw stop` removed its socket")
+        .await;
 
     println!("Bridge stopped.");
     Ok(())
 }
 
-/// Stop all configured channel adapter processes (ntfy, Discord, Slack).
+async fn notify_bridge_stopped(message: impl Into<String>) {
+    let event = notify::Event::new(EventKind::BridgeStopped, message).component(BRIDGE_RECORD);
+    let _ = tokio::time::timeout(STOP_NOTIFY_TIMEOUT, notify::send(event)).await;
+}
+
+/// Stop every adapter process owned by yuiclaw.
+///
+/// Adapters normally exit with the supervisor; this only finds processes left behind when
+/// the supervisor was killed without cleaning up.
 async fn stop_all_adapters() {
-    for spec in &CHANNEL_ADAPTER_SPECS {
-        let pattern = format!("acomm.*{}", spec.adapter_flag);
-        let _ = Command::new("pkill")
-            .arg("-f")
-            .arg(&pattern)
-            .status()
-            .await;
+    for record in state::live_records() {
+        if record.name == SUPERVISOR_RECORD || record.name == BRIDGE_RECORD {
+            continue;
+        }
+        terminate_owned_process(&record).await;
     }
 }
 
-/// Run abeat's due jobs (heartbeat tick).
+/// Run abeat's due jobs (heartbeat tick), or the built-in scheduler's when abeat is not
+/// installed.
 pub async fn run_tick() -> Result<(), Box<dyn std::error::Error>> {
+    // Prompts held back during quiet hours go out first, once they are over.
+    if let Err(e) = quiet::flush().await {
+        eprintln!("Warning: {}", e);
+    }
     if !components::is_command_available("abeat").await {
-        return Err("abeat not found in PATH.".into());
+        let results = jobs::run_due().await;
+        let failed = match &results {
+            Ok(results) => results.iter().filter(|(_, r)| r.is_err()).count(),
+            Err(_) => 1,
+        };
+        metrics::record_tick(failed == 0);
+        for (name, result) in results? {
+            if let Err(e) = result {
+                eprintln!("Job '{}' failed: {}", name, e);
+            }
+        }
+        if failed > 0 {
+            return Err(format!("{} job(s) failed.", failed).into());
+        }
+        return Ok(());
     }
 
     let status = Command::new("abeat")
         .arg("tick")
         .arg("--due")
         .status()
-        .await?;
+        .await;
+    metrics::record_tick(status.as_ref().is_ok_and(|s| s.success()));
 
-    if !status.success() {
+    if !status?.success() {
         return Err("abeat tick failed.".into());
     }
 
@@ -225,47 +344,196 @@ pub async fn publish(
     message: &str,
     channel: Option<&str>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if !is_bridge_running() {
-        return Err("Bridge is not running. Start yuiclaw with `yuiclaw start`.".into());
-    }
+    let mut client = connect_bridge().await?;
+    client.prompt(message, channel).await?;
+    metrics::record_publish("cli", channel);
+    Ok(())
+}
 
-    let mut cmd = Command::new("acomm");
-    cmd.arg("--publish").arg(message);
-    if let Some(ch) = channel {
-        cmd.arg("--channel").arg(ch);
-    }
+/// Send `question` to the running bridge and stream the agent's reply to stdout.
+///
+/// The prompt is tagged with a channel unique to this invocation so that only the reply to
+/// it is printed, even when other clients are talking to the bridge at the same time.
+/// Fails on timeout, on an agent/provider error, and when the bridge goes away mid-reply.
+pub async fn ask(
+    question: &str,
+    provider: Option<&str>,
+    timeout: Duration,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let provider = match provider {
+        Some(p) => Some(config::canonical_provider(p).ok_or_else(|| {
+            format!(
+                "Unknown provider '{}'. Expected one of: {}",
+                p,
+                config::PROVIDERS.join(", ")
+            )
+        })?),
+        None => None,
+    };
+    let channel = format!(
+        "ask-{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_millis()
+    );
+
+    let mut client = connect_bridge().await?;
+    let started = Instant::now();
+    let mut printed_any = false;
+    let result = ask_bridge(
+        &mut client,
+        question,
+        provider,
+        &channel,
+        timeout,
+        |chunk| {
+            if !json {
+                print!("{}", chunk);
+                let _ = std::io::stdout().flush();
+                printed_any = true;
+            }
+        },
+    )
+    .await;
+
+    let reply = match result {
+        Ok(reply) => reply,
+        Err(e) => {
+            if printed_any {
+                println!();
+            }
+            return Err(e.into());
+        }
+    };
 
-    let status = cmd.status().await?;
-    if !status.success() {
-        return Err("Failed to publish message to bridge.".into());
+    if json {
+        let output = serde_json::json!({
+            "reply": reply,
+            "provider": provider,
+            "channel": channel,
+            "elapsed_ms": started.elapsed().as_millis() as u64,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if !reply.ends_with('\n') {
+        println!();
     }
-
     Ok(())
 }
 
-/// Reset the active session by sending /clear to the running bridge.
+/// Send a prompt on `channel` and collect the reply, passing each chunk to `on_chunk` as it
+/// arrives. Events for other channels are ignored.
+pub(crate) async fn ask_bridge(
+    client: &mut BridgeClient,
+    question: &str,
+    provider: Option<&str>,
+    channel: &str,
+    timeout: Duration,
+    mut on_chunk: impl FnMut(&str),
+) -> Result<String, String> {
+    client
+        .send(&Request::Prompt {
+            text: question.to_string(),
+            channel: Some(channel.to_string()),
+            provider: provider.map(str::to_string),
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let is_ours = |c: &Option<String>| c.as_deref() == Some(channel);
+    let collect = async {
+        let mut reply = String::new();
+        loop {
+            match client.next_event().await.map_err(|e| e.to_string())? {
+                None => {
+                    return Err(
+                        "Bridge closed the connection before the agent finished replying."
+                            .to_string(),
+                    );
+                }
+                Some(Event::AgentChunk { chunk, channel }) if is_ours(&channel) => {
+                    on_chunk(&chunk);
+                    reply.push_str(&chunk);
+                }
+                Some(Event::AgentDone { channel }) if is_ours(&channel) => return Ok(reply),
+                Some(Event::Error { message, channel })
+                    if channel.is_none() || is_ours(&channel) =>
+                {
+                    return Err(format!("Agent error: {}", message));
+                }
+                Some(_) => {}
+            }
+        }
+    };
+
+    tokio::time::timeout(timeout, collect)
+        .await
+        .unwrap_or_else(|_| {
+            Err(format!(
+                "Timed out after {}s waiting for the agent's reply.",
+                timeout.as_secs()
+            ))
+        })
+}
+
+/// Connect to the active profile's bridge, with a hint on how to start it.
+async fn connect_bridge() -> Result<BridgeClient, Box<dyn std::error::Error>> {
+    BridgeClient::connect(socket_path())
+        .await
+        .map_err(|e| match e {
+            BridgeError::Connect { .. } => {
+                "Bridge is not running. Start yuiclaw with `yuiclaw start`.".into()
+            }
+            e => e.into(),
+        })
+}
+
+/// Reset the active session by sending the `clear` command to the running bridge.
 /// Clears the in-memory event backlog and the agent session manager.
 /// The TUI (if connected) will display the bridge's "Cleared." acknowledgement.
 pub async fn reset_session() -> Result<(), Box<dyn std::error::Error>> {
-    if !is_bridge_running() {
+    if !clear_session(socket_path()).await? {
         println!("No active session (bridge is not running).");
         return Ok(());
     }
-
-    let mut cmd = Command::new("acomm");
-    cmd.arg("--publish").arg("/clear");
-
-    let status = cmd.status().await?;
-    if !status.success() {
-        return Err("Failed to send reset command to bridge.".into());
-    }
-
     println!("Session reset.");
     Ok(())
 }
 
-fn is_bridge_running() -> bool {
-    components::is_bridge_running()
+/// Send `clear` to the bridge at `socket`. Returns `false` if no bridge is running.
+pub(crate) async fn clear_session(socket: &Path) -> Result<bool, String> {
+    let Ok(mut client) = BridgeClient::connect(socket).await else {
+        return Ok(false);
+    };
+    client.control("clear").await.map_err(|e| e.to_string())?;
+
+    // Wait briefly for the acknowledgement so that a rejected reset is reported.
+    let ack = tokio::time::timeout(std::time::Duration::from_secs(2), async {
+        loop {
+            match client.next_event().await {
+                Ok(Some(Event::SystemMessage { .. })) | Ok(None) => return Ok(()),
+                Ok(Some(Event::Error { message, .. })) => return Err(message),
+                Ok(Some(_)) => continue,
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    })
+    .await;
+    if let Ok(Err(message)) = ack {
+        return Err(format!("Bridge rejected the reset: {}", message));
+    }
+    Ok(true)
+}
+
+/// TUI binary selected by `tui` in config.toml.
+///
+/// `auto` prefers the TypeScript TUI and falls back to the Rust TUI for backwards compatibility.
+fn tui_command() -> &'static str {
+    match config::current().tui {
+        TuiPreference::AcommTui => "acomm-tui",
+        TuiPreference::Acomm => "acomm",
+        TuiPreference::Auto if is_command_in_path("acomm-tui") => "acomm-tui",
+        TuiPreference::Auto => "acomm",
+    }
 }
 
 /// Check whether a command name resolves to an executable in PATH.
@@ -283,9 +551,7 @@ async fn initialize_runtime_components() -> Result<(), Box<dyn std::error::Error
     let s = components::detect().await;
 
     if !s.acomm_available {
-        return Err("acomm not found in PATH. \
-             See https://github.com/yuiseki/acomm for installation instructions."
-            .into());
+        return Err(ACOMM_NOT_FOUND.into());
     }
 
     // Silently initialise amem / abeat (idempotent — safe to run even if already initialised)
@@ -309,156 +575,141 @@ async fn initialize_runtime_components() -> Result<(), Box<dyn std::error::Error
     Ok(())
 }
 
-async fn auto_start_configured_adapters() {
-    let present_env_keys = present_nonempty_env_keys();
-    let daemon_workdir = daemon_session_workdir();
-    let mut process_list = read_process_list().await.unwrap_or_default();
-
-    let adapters_to_start = adapters_to_autostart_from_inputs(&present_env_keys, &process_list);
-    if adapters_to_start.is_empty() {
-        return;
+/// Spawn `yuiclaw daemon run` detached from the terminal, logging to [`supervisor::log_path`].
+async fn start_supervisor_in_background() -> Result<(), Box<dyn std::error::Error>> {
+    if !components::is_command_available("acomm").await {
+        return Err(ACOMM_NOT_FOUND.into());
     }
 
-    let bridge_ready = ensure_bridge_running_for_adapters().await;
-    if !bridge_ready {
-        eprintln!(
-            "Warning: bridge was not ready; skipping auto-start for configured channel adapters."
-        );
-        return;
+    let log_path = supervisor::log_path();
+    if let Some(dir) = log_path.parent() {
+        std::fs::create_dir_all(dir)?;
     }
-    process_list = read_process_list().await.unwrap_or_default();
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)?;
 
-    for spec in adapters_to_start {
-        // Re-check against the latest process list so we don't duplicate after a bridge refresh.
-        if process_list_has_acomm_flag(&process_list, spec.adapter_flag) {
-            continue;
-        }
-
-        let mut cmd = std::process::Command::new("acomm");
-        cmd.arg(spec.adapter_flag)
-            .stdout(Stdio::null())
-            .stderr(Stdio::inherit());
-        // Background adapters inherit the daemon session workdir so all bridge-mediated
-        // sessions run under YUICLAW_HOME when configured.
-        apply_spawn_workdir_if_configured(&mut cmd, daemon_workdir.as_deref());
+    let mut cmd = std::process::Command::new(std::env::current_exe()?);
+    cmd.args(["daemon", "run"])
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    apply_spawn_workdir_if_configured(&mut cmd, ensure_daemon_session_workdir()?.as_deref());
 
-        match cmd.spawn() {
-            Ok(_) => {
-                eprintln!("Auto-started acomm adapter: {}", spec.label);
-            }
-            Err(err) => {
-                eprintln!(
-                    "Warning: failed to auto-start acomm adapter {} ({}): {}",
-                    spec.label, spec.adapter_flag, err
-                );
-            }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Start a new session so the supervisor survives the terminal that launched it.
+        // SAFETY: setsid(2) is async-signal-safe and touches no Rust state.
+        unsafe {
+            cmd.pre_exec(|| {
+                libc::setsid();
+                Ok(())
+            });
         }
     }
-}
 
-async fn ensure_bridge_running_for_adapters() -> bool {
-    let daemon_workdir = daemon_session_workdir();
-    for _ in 0..20 {
-        let process_list = read_process_list().await.unwrap_or_default();
-        let bridge_process_running = process_list_has_acomm_flag(&process_list, "--bridge");
+    cmd.spawn()?;
+    Ok(())
+}
 
-        if bridge_process_running && bridge_socket_accepts_connection().await {
+/// Wait up to 10 seconds for the bridge socket to accept connections.
+async fn wait_for_bridge_socket() -> bool {
+    for _ in 0..100 {
+        if bridge_socket_accepts_connection().await {
             return true;
         }
-
-        if bridge_process_running {
-            // Bridge process exists but socket is not accepting yet (startup race). Wait.
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-            continue;
-        }
-
-        if components::is_bridge_running() {
-            // Stale socket file from a previous crash can block bridge/adapters.
-            let _ = std::fs::remove_file(SOCKET_PATH);
-        }
-
-        let mut cmd = std::process::Command::new("acomm");
-        cmd.arg("--bridge")
-            .stdout(Stdio::null())
-            .stderr(Stdio::inherit());
-        apply_spawn_workdir_if_configured(&mut cmd, daemon_workdir.as_deref());
-
-        if cmd.spawn().is_err() {
-            return false;
-        }
-
         tokio::time::sleep(std::time::Duration::from_millis(100)).await;
     }
-
-    bridge_socket_accepts_connection().await
+    false
 }
 
-fn present_nonempty_env_keys() -> HashSet<String> {
-    std::env::vars()
-        .filter_map(|(k, v)| if v.trim().is_empty() { None } else { Some(k) })
-        .collect()
+fn bridge_start_failed_message() -> String {
+    format!(
+        "Failed to start acomm bridge. See {} for details.",
+        supervisor::log_path().display()
+    )
 }
 
-async fn read_process_list() -> Option<String> {
-    let out = Command::new("ps")
-        .args(["-eo", "comm=,args="])
-        .output()
-        .await
-        .ok()?;
+/// Send SIGTERM to the running `yuiclaw daemon run` and wait for it to exit.
+///
+/// Returns `true` if a supervisor owned by yuiclaw was running.
+async fn stop_supervisor() -> Result<bool, Box<dyn std::error::Error>> {
+    let Some(record) = state::live_record(SUPERVISOR_RECORD) else {
+        return Ok(false);
+    };
 
-    if !out.status.success() {
-        return None;
+    state::signal(&record, libc::SIGTERM);
+    // The supervisor terminates its children before exiting, which may take a few seconds.
+    if wait_for_exit(&record).await {
+        state::remove_record(SUPERVISOR_RECORD);
+        return Ok(true);
     }
-
-    Some(String::from_utf8_lossy(&out.stdout).into_owned())
-}
-
-fn adapters_to_autostart_from_inputs(
-    present_env_keys: &HashSet<String>,
-    process_list: &str,
-) -> Vec<&'static ChannelAdapterSpec> {
-    CHANNEL_ADAPTER_SPECS
-        .iter()
-        .filter(|spec| is_adapter_configured(spec, present_env_keys))
-        .filter(|spec| !process_list_has_acomm_flag(process_list, spec.adapter_flag))
-        .collect()
-}
-
-fn is_adapter_configured(spec: &ChannelAdapterSpec, present_env_keys: &HashSet<String>) -> bool {
-    spec.env_keys.iter().all(|k| present_env_keys.contains(*k))
+    Err(format!(
+        "Supervisor (pid {}) did not exit within 10 seconds.",
+        record.pid
+    )
+    .into())
 }
 
-async fn bridge_socket_accepts_connection() -> bool {
-    UnixStream::connect(SOCKET_PATH).await.is_ok()
+/// SIGTERM an owned process, escalating to SIGKILL if it is still alive after 10 seconds.
+async fn terminate_owned_process(record: &ProcessRecord) {
+    state::signal(record, libc::SIGTERM);
+    if !wait_for_exit(record).await {
+        state::signal(record, libc::SIGKILL);
+    }
+    state::remove_record(&record.name);
 }
 
-fn process_list_has_acomm_flag(process_list: &str, flag: &str) -> bool {
-    process_list
-        .lines()
-        .any(|line| process_line_matches_acomm_flag(line, flag))
+async fn wait_for_exit(record: &ProcessRecord) -> bool {
+    for _ in 0..100 {
+        if !state::is_alive(record) {
+            return true;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    false
 }
 
-fn process_line_matches_acomm_flag(line: &str, flag: &str) -> bool {
-    let trimmed = line.trim_start();
-    if trimmed.is_empty() {
-        return false;
+fn acomm_child_spec(name: &str, args: &[String], workdir: Option<&Path>) -> ChildSpec {
+    ChildSpec {
+        name: name.to_string(),
+        program: "acomm".to_string(),
+        args: args.to_vec(),
+        // Background adapters inherit the daemon session workdir so all bridge-mediated
+        // sessions run under YUICLAW_HOME when configured.
+        workdir: workdir.map(Path::to_path_buf),
+        restart: RestartMode::Always,
     }
+}
 
-    let Some((comm, args)) = trimmed.split_once(char::is_whitespace) else {
-        return false;
-    };
-
-    if comm != "acomm" {
-        return false;
+/// Child spec of an adapter from the registry. Adapters without their own `workdir` run in
+/// the daemon session workdir, like the bridge.
+pub(crate) fn adapter_child_spec(
+    adapter: &Adapter,
+    session_workdir: Option<&Path>,
+) -> ChildSpec {
+    ChildSpec {
+        name: adapter.name.clone(),
+        program: adapter.command.clone(),
+        args: adapter.args.clone(),
+        workdir: adapter
+            .workdir
+            .clone()
+            .or_else(|| session_workdir.map(Path::to_path_buf)),
+        restart: adapter.restart,
     }
+}
 
-    args.split_whitespace().any(|token| token == flag)
+async fn bridge_socket_accepts_connection() -> bool {
+    components::is_bridge_running().await
 }
 
-fn remove_socket_file_if_exists(path: &str) -> Result<bool, std::io::Error> {
-    if Path::new(path).exists() {
+fn remove_socket_file_if_exists(path: &Path) -> Result<bool, std::io::Error> {
+    if path.exists() {
         std::fs::remove_file(path)?;
-        println!("Removed socket: {}", path);
+        println!("Removed socket: {}", path.display());
         return Ok(true);
     }
     Ok(false)
@@ -477,6 +728,16 @@ fn daemon_session_workdir() -> Option<PathBuf> {
     resolve_daemon_session_workdir_from_env_value(std::env::var("YUICLAW_HOME").ok())
 }
 
+/// Like [`daemon_session_workdir`], but creates the directory so that spawning into it
+/// cannot fail (named profiles get a fresh default `YUICLAW_HOME`).
+fn ensure_daemon_session_workdir() -> Result<Option<PathBuf>, std::io::Error> {
+    let workdir = daemon_session_workdir();
+    if let Some(dir) = &workdir {
+        std::fs::create_dir_all(dir)?;
+    }
+    Ok(workdir)
+}
+
 fn apply_spawn_workdir_if_configured(
     cmd: &mut std::process::Command,
     workdir: Option<&Path>,
@@ -489,47 +750,119 @@ fn apply_spawn_workdir_if_configured(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bridge::fake::FakeBridge;
     use tempfile::tempdir;
 
-    fn env_keys(keys: &[&str]) -> HashSet<String> {
-        keys.iter().map(|k| (*k).to_string()).collect()
-    }
+    #[tokio::test]
+    async fn ask_bridge_streams_only_its_own_reply() {
+        let dir = tempdir().unwrap();
+        let bridge = FakeBridge::start(&dir.path().join("acomm.sock"), &["Hel", "lo\n"]);
+        let mut other = BridgeClient::connect(&bridge.path).await.unwrap();
+        let mut client = BridgeClient::connect(&bridge.path).await.unwrap();
+        // Make sure both clients are registered before traffic starts.
+        client.ping().await.unwrap();
+        other.prompt("someone else", Some("discord")).await.unwrap();
+
+        let mut chunks = Vec::new();
+        let reply = ask_bridge(
+            &mut client,
+            "hi",
+            Some("Claude"),
+            "ask-1",
+            Duration::from_secs(5),
+            |chunk| chunks.push(chunk.to_string()),
+        )
+        .await
+        .unwrap();
 
-    #[test]
-    fn adapters_to_autostart_uses_only_configured_services() {
-        let rows = adapters_to_autostart_from_inputs(&env_keys(&[]), "");
-        assert!(rows.is_empty());
+        assert_eq!(reply, "Hello\n");
+        assert_eq!(chunks, vec!["Hel", "lo\n"]);
+        assert!(bridge.requests().contains(&Request::Prompt {
+            text: "hi".to_string(),
+            channel: Some("ask-1".to_string()),
+            provider: Some("Claude".to_string()),
+        }));
+    }
 
-        let discord = adapters_to_autostart_from_inputs(&env_keys(&["DISCORD_BOT_TOKEN"]), "");
-        assert_eq!(discord.len(), 1);
-        assert_eq!(discord[0].label, "Discord");
+    #[tokio::test]
+    async fn ask_bridge_fails_on_provider_error() {
+        let dir = tempdir().unwrap();
+        let bridge = FakeBridge::start(&dir.path().join("acomm.sock"), &["never"]);
+        let mut client = BridgeClient::connect(&bridge.path).await.unwrap();
+
+        let err = ask_bridge(
+            &mut client,
+            "hi",
+            Some("Broken"),
+            "ask-2",
+            Duration::from_secs(5),
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+        assert!(err.contains("Broken CLI exited"), "{}", err);
+    }
 
-        let slack_missing_one =
-            adapters_to_autostart_from_inputs(&env_keys(&["SLACK_APP_TOKEN"]), "");
-        assert!(slack_missing_one.is_empty());
+    #[tokio::test]
+    async fn ask_bridge_times_out_without_reply() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("acomm.sock");
+        // Accepts the connection but never answers.
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        let server = tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+        let mut client = BridgeClient::connect(&path).await.unwrap();
+
+        let err = ask_bridge(
+            &mut client,
+            "hi",
+            None,
+            "ask-3",
+            Duration::from_millis(100),
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+        assert!(err.starts_with("Timed out"), "{}", err);
+        server.abort();
     }
 
     #[test]
-    fn adapters_to_autostart_skips_already_running_adapter_processes() {
-        let ps_output = "acomm           acomm --discord\nacomm           acomm --bridge\n";
-        let rows = adapters_to_autostart_from_inputs(&env_keys(&["DISCORD_BOT_TOKEN"]), ps_output);
-        assert!(rows.is_empty());
+    fn acomm_child_spec_carries_flag_and_workdir() {
+        let spec = acomm_child_spec(
+            "Discord",
+            &["--discord".to_string()],
+            Some(Path::new("/tmp/home")),
+        );
+        assert_eq!(spec.program, "acomm");
+        assert_eq!(spec.args, vec!["--discord".to_string()]);
+        assert_eq!(spec.workdir, Some(PathBuf::from("/tmp/home")));
     }
 
     #[test]
-    fn process_match_requires_acomm_binary_and_exact_flag() {
-        assert!(process_line_matches_acomm_flag(
-            "acomm           /home/user/.cargo/bin/acomm --discord",
-            "--discord"
-        ));
-        assert!(!process_line_matches_acomm_flag(
-            "cargo           cargo run -p acomm -- --discord",
-            "--discord"
-        ));
-        assert!(!process_line_matches_acomm_flag(
-            "acomm           acomm --discordx",
-            "--discord"
-        ));
+    fn custom_adapter_child_spec_uses_its_command_workdir_and_restart_mode() {
+        let config = crate::config::Config::parse(
+            "[adapters.relay]\ncommand = \"relay\"\nargs = [\"--listen\", \":9000\"]\n\
+             workdir = \"/srv/relay\"\nrestart = \"on-failure\"\n",
+        )
+        .unwrap();
+        let registry = adapters::registry(&config);
+        let relay = registry.iter().find(|a| a.name == "relay").unwrap();
+
+        let spec = adapter_child_spec(relay, Some(Path::new("/tmp/home")));
+        assert_eq!(spec.name, "relay");
+        assert_eq!(spec.program, "relay");
+        assert_eq!(spec.args, vec!["--listen", ":9000"]);
+        assert_eq!(spec.workdir, Some(PathBuf::from("/srv/relay")));
+        assert_eq!(spec.restart, RestartMode::OnFailure);
+
+        let discord = registry.iter().find(|a| a.name == "discord").unwrap();
+        let spec = adapter_child_spec(discord, Some(Path::new("/tmp/home")));
+        assert_eq!(spec.program, "acomm");
+        assert_eq!(spec.workdir, Some(PathBuf::from("/tmp/home")));
+        assert_eq!(spec.restart, RestartMode::Always);
     }
 
     #[test]
@@ -539,7 +872,7 @@ mod tests {
         std::fs::write(&sock_path, b"stale").unwrap();
         assert!(sock_path.exists());
 
-        let removed = remove_socket_file_if_exists(sock_path.to_str().unwrap()).unwrap();
+        let removed = remove_socket_file_if_exists(&sock_path).unwrap();
 
         assert!(removed);
         assert!(!sock_path.exists());
@@ -550,7 +883,7 @@ mod tests {
         let dir = tempdir().unwrap();
         let sock_path = dir.path().join("missing.sock");
 
-        let removed = remove_socket_file_if_exists(sock_path.to_str().unwrap()).unwrap();
+        let removed = remove_socket_file_if_exists(&sock_path).unwrap();
 
         assert!(!removed);
         assert!(!sock_path.exists());
@@ -0,0 +1,342 @@
+use crate::config::{self, Config, Origins};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Name of the profile used when neither `--profile` nor `YUICLAW_PROFILE` is given.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Socket path of the default profile (matches acomm's built-in default).
+const DEFAULT_SOCKET_PATH: &str = "/tmp/acomm.sock";
+
+static CURRENT: OnceLock<Profile> = OnceLock::new();
+
+/// An isolated yuiclaw instance: its own bridge socket, memory root, workdir,
+/// abeat job namespace, `.env` and runtime state.
+///
+/// The default profile keeps the historical layout (`/tmp/acomm.sock`,
+/// `~/.config/yuiclaw/.env`, `~/.local/state/yuiclaw/`) so existing setups are unaffected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    /// Directory holding the profile's `config.toml` and `.env`.
+    pub config_dir: PathBuf,
+    /// Directory holding the profile's runtime state (PID records, daemon log).
+    pub state_dir: PathBuf,
+    pub socket_path: PathBuf,
+    /// `AMEM_ROOT` for this profile; `None` leaves amem's own default (`~/.amem`).
+    pub amem_root: Option<PathBuf>,
+    /// `YUICLAW_HOME` for this profile; `None` runs bridge sessions in the current directory.
+    pub home: Option<PathBuf>,
+}
+
+/// Base directories the profile layout is derived from.
+struct BaseDirs {
+    home: PathBuf,
+    config: PathBuf,
+    state: PathBuf,
+    data: PathBuf,
+    runtime: Option<PathBuf>,
+    uid: u32,
+}
+
+impl BaseDirs {
+    fn detect() -> Self {
+        let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        Self {
+            config: dirs::config_dir().unwrap_or_else(|| home.join(".config")),
+            state: dirs::state_dir().unwrap_or_else(|| home.join(".local").join("state")),
+            data: dirs::data_dir().unwrap_or_else(|| home.join(".local").join("share")),
+            runtime: dirs::runtime_dir(),
+            // SAFETY: getuid(2) cannot fail and has no preconditions.
+            uid: unsafe { libc::getuid() },
+            home,
+        }
+    }
+}
+
+impl Profile {
+    /// Default layout of the profile `name`, before environment overrides are applied.
+    fn layout(name: &str, base: &BaseDirs) -> Self {
+        if name == DEFAULT_PROFILE {
+            return Self {
+                name: name.to_string(),
+                config_dir: base.config.join("yuiclaw"),
+                state_dir: base.state.join("yuiclaw"),
+                socket_path: PathBuf::from(DEFAULT_SOCKET_PATH),
+                amem_root: None,
+                home: None,
+            };
+        }
+
+        let socket_file = format!("acomm-{}.sock", name);
+        let socket_path = match &base.runtime {
+            Some(dir) => dir.join(socket_file),
+            // /tmp is shared between users, so include the uid to avoid collisions.
+            None => PathBuf::from(format!("/tmp/acomm-{}-{}.sock", base.uid, name)),
+        };
+
+        Self {
+            name: name.to_string(),
+            config_dir: base.config.join("yuiclaw").join("profiles").join(name),
+            state_dir: base.state.join("yuiclaw").join("profiles").join(name),
+            socket_path,
+            amem_root: Some(base.home.join(format!(".amem-{}", name))),
+            home: Some(base.data.join("yuiclaw").join("profiles").join(name)),
+        }
+    }
+
+    /// Let explicit `ACOMM_SOCKET_PATH` / `AMEM_ROOT` / `YUICLAW_HOME` values win over the layout.
+    fn with_env_overrides(mut self, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let get = |key: &str| {
+            lookup(key)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+        };
+        if let Some(path) = get("ACOMM_SOCKET_PATH") {
+            self.socket_path = path;
+        }
+        if let Some(path) = get("AMEM_ROOT") {
+            self.amem_root = Some(path);
+        }
+        if let Some(path) = get("YUICLAW_HOME") {
+            self.home = Some(path);
+        }
+        self
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT_PROFILE
+    }
+
+    /// Path of the profile's `.env` file.
+    pub fn env_path(&self) -> PathBuf {
+        self.config_dir.join(".env")
+    }
+
+    /// Path of the profile's `config.toml`.
+    pub fn config_path(&self) -> PathBuf {
+        self.config_dir.join("config.toml")
+    }
+
+    /// Path of the profile's `jobs.toml`, which declares the abeat jobs yuiclaw manages.
+    pub fn jobs_path(&self) -> PathBuf {
+        self.config_dir.join("jobs.toml")
+    }
+
+    /// The memory store root: `amem_root`, or amem's own default `~/.amem`.
+    pub fn memory_root(&self) -> PathBuf {
+        self.amem_root.clone().unwrap_or_else(|| {
+            dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("/tmp"))
+                .join(".amem")
+        })
+    }
+
+    /// Path of `HEARTBEAT.md`, the heartbeat checks in the memory store root.
+    pub fn heartbeat_path(&self) -> PathBuf {
+        self.memory_root().join("HEARTBEAT.md")
+    }
+
+    /// abeat job ID for `suffix`, namespaced by profile (`yuiclaw-heartbeat`,
+    /// `yuiclaw-work-heartbeat`, ...).
+    pub fn job_id(&self, suffix: &str) -> String {
+        if self.is_default() {
+            format!("yuiclaw-{}", suffix)
+        } else {
+            format!("yuiclaw-{}-{}", self.name, suffix)
+        }
+    }
+
+    /// `yuiclaw` invocation that targets this profile, for use in shell commands run by abeat.
+    pub fn yuiclaw_command(&self) -> String {
+        if self.is_default() {
+            "yuiclaw".to_string()
+        } else {
+            format!("yuiclaw --profile {}", self.name)
+        }
+    }
+}
+
+/// Resolve the active profile, load its `config.toml` and `.env` and export its settings to
+/// the environment.
+///
+/// Precedence: `--profile` flag, then `YUICLAW_PROFILE`, then `default`. Within a profile,
+/// process environment variables win over `config.toml`, which wins over the profile `.env`,
+/// which wins over the layout defaults. The resolved `YUICLAW_PROFILE`, `ACOMM_SOCKET_PATH`,
+/// `AMEM_ROOT` and `YUICLAW_HOME` are exported so that every spawned process (acomm, amem,
+/// the background supervisor) sees the same profile.
+///
+/// Fails if the profile name is invalid. A `config.toml` that cannot be parsed is an error when
+/// `strict` is set; otherwise it is reported as a warning and ignored, so that `yuiclaw config`
+/// can still be used to repair it.
+pub fn init(flag: Option<&str>, strict: bool) -> Result<&'static Profile, String> {
+    let name = flag
+        .map(str::to_string)
+        .or_else(|| std::env::var("YUICLAW_PROFILE").ok())
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+    validate_name(&name)?;
+
+    let process_env = std::env::vars_os()
+        .filter_map(|(k, _)| k.into_string().ok())
+        .collect();
+    let layout = Profile::layout(&name, &BaseDirs::detect());
+    let config = match Config::load(&layout.config_path()) {
+        Ok(config) => config,
+        Err(e) if !strict => {
+            eprintln!("Warning: {}", e);
+            Config::default()
+        }
+        Err(e) => return Err(e),
+    };
+    for (key, value) in config.env_defaults() {
+        crate::env::set_default_var(&key, &value);
+    }
+    let dotenv = crate::env::load_config_dotenv(&layout.env_path())
+        .into_iter()
+        .collect();
+    config::install(
+        config,
+        Origins {
+            process_env,
+            dotenv,
+        },
+    );
+    let profile = layout.with_env_overrides(|key| std::env::var(key).ok());
+
+    // SAFETY: called once early in main, before any other task reads the environment.
+    unsafe {
+        if !profile.is_default() {
+            std::env::set_var("YUICLAW_PROFILE", &profile.name);
+        }
+        std::env::set_var("ACOMM_SOCKET_PATH", &profile.socket_path);
+        if let Some(root) = &profile.amem_root {
+            std::env::set_var("AMEM_ROOT", root);
+        }
+        if let Some(home) = &profile.home {
+            std::env::set_var("YUICLAW_HOME", home);
+        }
+    }
+
+    Ok(CURRENT.get_or_init(|| profile))
+}
+
+/// The active profile. Falls back to the default layout if [`init`] has not run (unit tests).
+pub fn current() -> &'static Profile {
+    CURRENT.get_or_init(|| Profile::layout(DEFAULT_PROFILE, &BaseDirs::detect()))
+}
+
+/// Bridge socket path of the active profile.
+pub fn socket_path() -> &'static Path {
+    &current().socket_path
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    let valid = name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid profile name '{}': use up to 64 ASCII letters, digits, '-' or '_'.",
+            name
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn base(runtime: Option<&str>) -> BaseDirs {
+        BaseDirs {
+            home: PathBuf::from("/home/u"),
+            config: PathBuf::from("/home/u/.config"),
+            state: PathBuf::from("/home/u/.local/state"),
+            data: PathBuf::from("/home/u/.local/share"),
+            runtime: runtime.map(PathBuf::from),
+            uid: 1000,
+        }
+    }
+
+    #[test]
+    fn default_profile_keeps_historical_layout() {
+        let p = Profile::layout(DEFAULT_PROFILE, &base(Some("/run/user/1000")));
+        assert_eq!(p.socket_path, PathBuf::from("/tmp/acomm.sock"));
+        assert_eq!(p.env_path(), PathBuf::from("/home/u/.config/yuiclaw/.env"));
+        assert_eq!(p.state_dir, PathBuf::from("/home/u/.local/state/yuiclaw"));
+        assert_eq!(p.amem_root, None);
+        assert_eq!(p.home, None);
+        assert_eq!(p.job_id("heartbeat"), "yuiclaw-heartbeat");
+        assert_eq!(p.yuiclaw_command(), "yuiclaw");
+    }
+
+    #[test]
+    fn named_profile_is_isolated() {
+        let p = Profile::layout("work", &base(Some("/run/user/1000")));
+        assert_eq!(
+            p.socket_path,
+            PathBuf::from("/run/user/1000/acomm-work.sock")
+        );
+        assert_eq!(
+            p.env_path(),
+            PathBuf::from("/home/u/.config/yuiclaw/profiles/work/.env")
+        );
+        assert_eq!(
+            p.state_dir,
+            PathBuf::from("/home/u/.local/state/yuiclaw/profiles/work")
+        );
+        assert_eq!(p.amem_root, Some(PathBuf::from("/home/u/.amem-work")));
+        assert_eq!(
+            p.heartbeat_path(),
+            PathBuf::from("/home/u/.amem-work/HEARTBEAT.md")
+        );
+        assert_eq!(
+            p.home,
+            Some(PathBuf::from("/home/u/.local/share/yuiclaw/profiles/work"))
+        );
+        assert_eq!(p.job_id("heartbeat"), "yuiclaw-work-heartbeat");
+        assert_eq!(p.yuiclaw_command(), "yuiclaw --profile work");
+    }
+
+    #[test]
+    fn named_profile_socket_falls_back_to_per_uid_tmp_path() {
+        let p = Profile::layout("work", &base(None));
+        assert_eq!(p.socket_path, PathBuf::from("/tmp/acomm-1000-work.sock"));
+    }
+
+    #[test]
+    fn env_overrides_win_over_layout() {
+        let env: HashMap<&str, &str> = [
+            ("ACOMM_SOCKET_PATH", "/srv/acomm.sock"),
+            ("AMEM_ROOT", " /data/amem "),
+            ("YUICLAW_HOME", ""),
+        ]
+        .into_iter()
+        .collect();
+        let p = Profile::layout("work", &base(None))
+            .with_env_overrides(|k| env.get(k).map(|v| v.to_string()));
+
+        assert_eq!(p.socket_path, PathBuf::from("/srv/acomm.sock"));
+        assert_eq!(p.amem_root, Some(PathBuf::from("/data/amem")));
+        // Blank values are ignored.
+        assert_eq!(
+            p.home,
+            Some(PathBuf::from("/home/u/.local/share/yuiclaw/profiles/work"))
+        );
+    }
+
+    #[test]
+    fn validate_name_rejects_path_like_names() {
+        assert!(validate_name("work").is_ok());
+        assert!(validate_name("persona_2-b").is_ok());
+        assert!(validate_name("../etc").is_err());
+        assert!(validate_name("a b").is_err());
+        assert!(validate_name(&"x".repeat(65)).is_err());
+    }
+}
@@ -0,0 +1,476 @@
+//! Quiet hours and do-not-disturb for proactive prompts: scheduled jobs and the heartbeat.
+//!
+//! Only prompts yuiclaw publishes on its own are held back (`yuiclaw pub --proactive`, which
+//! abeat runs for prompt jobs, and the built-in scheduler). Messages from users reach the
+//! bridge through the adapters and are never affected, nor is a plain `yuiclaw pub`.
+//!
+//! During quiet hours (`[quiet_hours]` in config.toml) or while `yuiclaw dnd on` is in
+//! effect, a proactive prompt is either queued, keeping only the latest one per channel, or
+//! dropped. Queued prompts are published once the quiet period is over: by the daemon, by
+//! the next `yuiclaw tick`, or by `yuiclaw dnd off`.
+
+use crate::config::{self, QuietAction, QuietHoursConfig, QuietPeriod};
+use crate::jobs;
+use crate::process;
+use crate::profile;
+use crate::state::{self, Dnd, QueuedPrompt};
+use crate::supervisor;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, TimeZone, Utc};
+
+/// Whether proactive prompts are held back, and until when.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quiet {
+    Off,
+    Until(DateTime<Utc>),
+    /// Do-not-disturb without an end: until `yuiclaw dnd off`.
+    UntilDndOff,
+}
+
+/// What became of a proactive prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Published,
+    Queued(Quiet),
+    Dropped(Quiet),
+}
+
+/// The quiet state right now.
+pub fn current() -> Quiet {
+    quiet_at(&config::current().quiet_hours, &state::dnd(), Utc::now())
+}
+
+/// Publish a proactive prompt, or hold it back during quiet hours.
+///
+/// A prompt that goes out replaces one queued earlier on the same channel, and whatever else
+/// is still queued is published first.
+pub async fn publish(
+    message: &str,
+    channel: Option<&str>,
+) -> Result<Delivery, Box<dyn std::error::Error>> {
+    let quiet = current();
+    if quiet != Quiet::Off {
+        return match config::current().quiet_hours.action {
+            QuietAction::Drop => Ok(Delivery::Dropped(quiet)),
+            QuietAction::Queue => {
+                let prompt = QueuedPrompt {
+                    channel: channel.map(str::to_string),
+                    message: message.to_string(),
+                    queued_at: Utc::now().timestamp(),
+                };
+                state::update_queued_prompts(|queue| enqueue(queue, prompt))?;
+                Ok(Delivery::Queued(quiet))
+            }
+        };
+    }
+
+    state::update_queued_prompts(|queue| queue.retain(|p| p.channel.as_deref() != channel))?;
+    if let Err(e) = flush().await {
+        eprintln!("Warning: {}", e);
+    }
+    process::publish(message, channel).await?;
+    Ok(Delivery::Published)
+}
+
+/// `yuiclaw pub --proactive`, which abeat runs for prompt jobs. Prints the no-op token when
+/// the prompt is held back.
+pub async fn publish_command(
+    message: &str,
+    channel: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if publish(message, channel).await? != Delivery::Published {
+        println!("{}", jobs::NO_OP_TOKEN);
+    }
+    Ok(())
+}
+
+/// Publish the queued prompts, oldest first, if quiet hours are over and the bridge is
+/// running. Returns how many were published.
+pub async fn flush() -> Result<usize, String> {
+    if current() != Quiet::Off || !profile::socket_path().exists() {
+        return Ok(0);
+    }
+    let queue_error = |e: std::io::Error| format!("{}: {}", state::state_dir().display(), e);
+    let queued = state::update_queued_prompts(std::mem::take).map_err(queue_error)?;
+    for (i, prompt) in queued.iter().enumerate() {
+        if let Err(e) = process::publish(&prompt.message, prompt.channel.as_deref()).await {
+            // Put back what was not published; prompts queued in the meantime are newer.
+            let rest = queued[i..].to_vec();
+            state::update_queued_prompts(|queue| {
+                for prompt in std::mem::replace(queue, rest) {
+                    enqueue(queue, prompt);
+                }
+            })
+            .map_err(queue_error)?;
+            return Err(format!("Failed to publish queued prompts: {}", e));
+        }
+    }
+    Ok(queued.len())
+}
+
+/// Publish the queued prompts once quiet hours end (run by the daemon).
+pub async fn watch() {
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+        match flush().await {
+            Ok(0) => {}
+            Ok(n) => supervisor::log(format!(
+                "quiet hours over: published {} queued prompt(s)",
+                n
+            )),
+            Err(e) => supervisor::log(e),
+        }
+    }
+}
+
+/// `yuiclaw dnd on [--for DURATION]`
+pub async fn dnd_on(
+    duration: Option<std::time::Duration>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let until = match duration {
+        Some(duration) => Some(dnd_until(Utc::now(), duration)?),
+        None => None,
+    };
+    state::set_dnd(Dnd {
+        on: true,
+        until: until.map(|t| t.timestamp()),
+    })?;
+    match until {
+        Some(until) => println!("Do not disturb until {}.", display_time(until)),
+        None => println!("Do not disturb until `yuiclaw dnd off`."),
+    }
+    Ok(())
+}
+
+/// When do-not-disturb for `duration` from `now` ends.
+fn dnd_until(now: DateTime<Utc>, duration: std::time::Duration) -> Result<DateTime<Utc>, String> {
+    Duration::from_std(duration)
+        .ok()
+        .and_then(|duration| now.checked_add_signed(duration))
+        .ok_or_else(|| "duration too long".to_string())
+}
+
+/// `yuiclaw dnd off`
+pub async fn dnd_off() -> Result<(), Box<dyn std::error::Error>> {
+    state::set_dnd(Dnd::default())?;
+    println!("Do not disturb is off.");
+    match current() {
+        Quiet::Off => match flush().await? {
+            0 => {}
+            n => println!("Published {} queued prompt(s).", n),
+        },
+        quiet => println!("Quiet hours continue {}.", describe(quiet)),
+    }
+    Ok(())
+}
+
+/// `yuiclaw dnd status`
+pub async fn dnd_status() -> Result<(), Box<dyn std::error::Error>> {
+    let config = &config::current().quiet_hours;
+    let dnd = state::dnd();
+    let now = Utc::now();
+
+    let dnd_line = match (
+        dnd.on,
+        dnd.until.and_then(|t| DateTime::from_timestamp(t, 0)),
+    ) {
+        (true, Some(until)) if until > now => format!("on until {}", display_time(until)),
+        (true, None) => "on until `yuiclaw dnd off`".to_string(),
+        _ => "off".to_string(),
+    };
+    println!("Do not disturb : {}", dnd_line);
+    if config.periods.is_empty() {
+        println!("Quiet hours    : not configured");
+    } else {
+        let zone = config
+            .timezone
+            .map_or_else(|| "local time".to_string(), |tz| tz.name().to_string());
+        println!(
+            "Quiet hours    : ({}, {})",
+            zone,
+            action_name(config.action)
+        );
+        for period in &config.periods {
+            println!("  {}", describe_period(period));
+        }
+    }
+    match quiet_at(config, &dnd, now) {
+        Quiet::Off => println!("Proactive      : delivered"),
+        quiet => println!("Proactive      : held back {}", describe(quiet)),
+    }
+    let queued = state::queued_prompts();
+    if !queued.is_empty() {
+        println!("Queued         : {}", queue_summary(&queued));
+    }
+    Ok(())
+}
+
+/// One line for `yuiclaw status` while prompts are held back or queued.
+pub fn summary() -> Option<String> {
+    let queued = state::queued_prompts();
+    match (current(), queued.is_empty()) {
+        (Quiet::Off, true) => None,
+        (Quiet::Off, false) => Some(format!("Queued: {}", queue_summary(&queued))),
+        (quiet, true) => Some(format!("Proactive prompts held back {}", describe(quiet))),
+        (quiet, false) => Some(format!(
+            "Proactive prompts held back {} (queued: {})",
+            describe(quiet),
+            queue_summary(&queued)
+        )),
+    }
+}
+
+/// Add `prompt` to the queue, replacing an older prompt on the same channel.
+fn enqueue(queue: &mut Vec<QueuedPrompt>, prompt: QueuedPrompt) {
+    queue.retain(|p| p.channel != prompt.channel);
+    queue.push(prompt);
+}
+
+/// Whether `now` falls in do-not-disturb or quiet hours, and when that ends. Back-to-back
+/// periods (and do-not-disturb running into quiet hours) count as one.
+fn quiet_at(config: &QuietHoursConfig, dnd: &Dnd, now: DateTime<Utc>) -> Quiet {
+    let mut t = now;
+    // Bounded, in case periods cover the whole week.
+    for _ in 0..16 {
+        let dnd_until = dnd.until.and_then(|u| DateTime::from_timestamp(u, 0));
+        t = if dnd.on && dnd_until.is_none() {
+            return Quiet::UntilDndOff;
+        } else if let Some(until) = dnd_until.filter(|until| dnd.on && t < *until) {
+            until
+        } else if let Some(end) = period_end(config, t) {
+            end
+        } else {
+            break;
+        };
+    }
+    if t > now { Quiet::Until(t) } else { Quiet::Off }
+}
+
+/// End of the quiet period `t` falls in, in the configured time zone.
+fn period_end(config: &QuietHoursConfig, t: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match config.timezone {
+        Some(tz) => period_end_in(&config.periods, &t.with_timezone(&tz)).map(|e| e.to_utc()),
+        None => period_end_in(&config.periods, &t.with_timezone(&Local)).map(|e| e.to_utc()),
+    }
+}
+
+fn period_end_in<Tz: TimeZone>(periods: &[QuietPeriod], t: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+    let local = t.naive_local();
+    let today = local.date();
+    let mut latest: Option<NaiveDateTime> = None;
+    for day in [today.pred_opt(), Some(today)].into_iter().flatten() {
+        let weekday = day.weekday().num_days_from_sunday();
+        for period in periods
+            .iter()
+            .filter(|p| p.weekdays() & (1 << weekday) != 0)
+        {
+            let start = day.and_time(period.start);
+            let mut end = day.and_time(period.end);
+            if end <= start {
+                end += Duration::days(1);
+            }
+            if start <= local && local < end && latest.is_none_or(|l| end > l) {
+                latest = Some(end);
+            }
+        }
+    }
+    latest.map(|end| resolve(&t.timezone(), end))
+}
+
+/// `t` in `tz`; a time skipped by a DST change resolves to the first time after the gap.
+fn resolve<Tz: TimeZone>(tz: &Tz, t: NaiveDateTime) -> DateTime<Tz> {
+    tz.from_local_datetime(&t)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(t + Duration::hours(1))).earliest())
+        .unwrap_or_else(|| tz.from_utc_datetime(&t))
+}
+
+fn describe(quiet: Quiet) -> String {
+    match quiet {
+        Quiet::Off => String::new(),
+        Quiet::Until(until) => format!("until {}", display_time(until)),
+        Quiet::UntilDndOff => "until `yuiclaw dnd off`".to_string(),
+    }
+}
+
+fn describe_period(period: &QuietPeriod) -> String {
+    format!(
+        "{} {}-{}",
+        period.days.as_deref().unwrap_or("every day"),
+        period.start.format("%H:%M"),
+        period.end.format("%H:%M")
+    )
+}
+
+fn action_name(action: QuietAction) -> &'static str {
+    match action {
+        QuietAction::Queue => "queue",
+        QuietAction::Drop => "drop",
+    }
+}
+
+fn queue_summary(queued: &[QueuedPrompt]) -> String {
+    let channels: Vec<String> = queued
+        .iter()
+        .map(|p| format!("#{}", p.channel.as_deref().unwrap_or("default")))
+        .collect();
+    format!("{} prompt(s) ({})", queued.len(), channels.join(", "))
+}
+
+/// `t` in the configured time zone, or local time.
+fn display_time(t: DateTime<Utc>) -> String {
+    match config::current().quiet_hours.timezone {
+        Some(tz) => t.with_timezone(&tz).format("%Y-%m-%d %H:%M %Z").to_string(),
+        None => t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn config(toml: &str) -> QuietHoursConfig {
+        Config::parse(toml).unwrap().quiet_hours
+    }
+
+    /// `s` in Asia/Tokyo (UTC+9).
+    fn tokyo(s: &str) -> DateTime<Utc> {
+        let t = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        resolve(&chrono_tz::Asia::Tokyo, t).with_timezone(&Utc)
+    }
+
+    const WEEKNIGHTS: &str = r#"
+[quiet_hours]
+timezone = "Asia/Tokyo"
+
+[[quiet_hours.periods]]
+days = "mon-fri"
+start = "22:00"
+end = "07:00"
+
+[[quiet_hours.periods]]
+days = "sat,sun"
+start = "00:00"
+end = "09:00"
+"#;
+
+    #[test]
+    fn quiet_hours_follow_weekdays_and_time_zone() {
+        let config = config(WEEKNIGHTS);
+        let off = Dnd::default();
+        // 2026-10-16 is a Friday.
+        assert_eq!(
+            quiet_at(&config, &off, tokyo("2026-10-16 21:59")),
+            Quiet::Off
+        );
+        // Friday night runs into Saturday morning's period.
+        assert_eq!(
+            quiet_at(&config, &off, tokyo("2026-10-16 23:30")),
+            Quiet::Until(tokyo("2026-10-17 09:00"))
+        );
+        assert_eq!(
+            quiet_at(&config, &off, tokyo("2026-10-17 09:00")),
+            Quiet::Off
+        );
+        // Saturday night is not quiet, Sunday night (start of Monday) is from midnight.
+        assert_eq!(
+            quiet_at(&config, &off, tokyo("2026-10-17 23:00")),
+            Quiet::Off
+        );
+        assert_eq!(
+            quiet_at(&config, &off, tokyo("2026-10-19 03:00")),
+            Quiet::Off
+        );
+        assert_eq!(
+            quiet_at(&config, &off, tokyo("2026-10-19 22:00")),
+            Quiet::Until(tokyo("2026-10-20 07:00"))
+        );
+    }
+
+    #[test]
+    fn do_not_disturb_extends_into_quiet_hours() {
+        let config = config(WEEKNIGHTS);
+        let until = |s: &str| Dnd {
+            on: true,
+            until: Some(tokyo(s).timestamp()),
+        };
+        assert_eq!(
+            quiet_at(
+                &config,
+                &until("2026-10-16 15:00"),
+                tokyo("2026-10-16 13:00")
+            ),
+            Quiet::Until(tokyo("2026-10-16 15:00"))
+        );
+        assert_eq!(
+            quiet_at(
+                &config,
+                &until("2026-10-16 23:00"),
+                tokyo("2026-10-16 20:00")
+            ),
+            Quiet::Until(tokyo("2026-10-17 09:00"))
+        );
+        // Expired do-not-disturb no longer counts.
+        assert_eq!(
+            quiet_at(
+                &config,
+                &until("2026-10-16 12:00"),
+                tokyo("2026-10-16 13:00")
+            ),
+            Quiet::Off
+        );
+        let indefinite = Dnd {
+            on: true,
+            until: None,
+        };
+        assert_eq!(
+            quiet_at(&QuietHoursConfig::default(), &indefinite, Utc::now()),
+            Quiet::UntilDndOff
+        );
+    }
+
+    #[test]
+    fn dnd_rejects_durations_past_the_end_of_time() {
+        let now = Utc::now();
+        let hour = std::time::Duration::from_secs(3600);
+        assert_eq!(dnd_until(now, hour), Ok(now + Duration::hours(1)));
+        let forever = crate::cli::parse_duration("9999999999d").unwrap();
+        assert_eq!(
+            dnd_until(now, forever),
+            Err("duration too long".to_string())
+        );
+    }
+
+    #[test]
+    fn equal_start_and_end_is_the_whole_day() {
+        let config = config(
+            "[quiet_hours]\ntimezone = \"UTC\"\n\n[[quiet_hours.periods]]\ndays = \"sun\"\nstart = \"00:00\"\nend = \"00:00\"\n",
+        );
+        let at = |s: &str| {
+            NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+                .unwrap()
+                .and_utc()
+        };
+        assert_eq!(
+            quiet_at(&config, &Dnd::default(), at("2026-10-18 12:00")),
+            Quiet::Until(at("2026-10-19 00:00"))
+        );
+    }
+
+    #[test]
+    fn queue_keeps_the_latest_prompt_per_channel() {
+        let prompt = |channel: &str, message: &str| QueuedPrompt {
+            channel: Some(channel.to_string()),
+            message: message.to_string(),
+            queued_at: 0,
+        };
+        let mut queue = Vec::new();
+        enqueue(&mut queue, prompt("heartbeat", "first"));
+        enqueue(&mut queue, prompt("standup", "standup"));
+        enqueue(&mut queue, prompt("heartbeat", "second"));
+        assert_eq!(
+            queue,
+            [prompt("standup", "standup"), prompt("heartbeat", "second")]
+        );
+    }
+}
@@ -0,0 +1,622 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+/// Ownership record for a process spawned by yuiclaw.
+///
+/// Stored as `<state_dir>/run/<name>.json`. A record only counts as live when
+/// `/proc/<pid>` still refers to the same process: the start time captured at spawn time
+/// must match, which guards against PID reuse. The command line is kept for diagnostics
+/// only, since wrappers such as `#!/usr/bin/env` shebangs change it after exec.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessRecord {
+    pub name: String,
+    pub pid: u32,
+    /// Start time in clock ticks since boot (field 22 of `/proc/<pid>/stat`).
+    pub start_time: u64,
+    /// Command line as seen right after spawn.
+    pub cmdline: Vec<String>,
+    /// RFC 3339 timestamp of when the record was written.
+    pub recorded_at: String,
+}
+
+/// Root of the active profile's runtime state (`~/.local/state/yuiclaw` for the default
+/// profile, `~/.local/state/yuiclaw/profiles/<name>` otherwise).
+pub fn state_dir() -> PathBuf {
+    crate::profile::current().state_dir.clone()
+}
+
+/// Directory holding one [`ProcessRecord`] per owned process.
+pub fn run_dir() -> PathBuf {
+    state_dir().join("run")
+}
+
+/// Record that `pid` is owned by yuiclaw under `name`, replacing any previous record.
+pub fn write_record(name: &str, pid: u32) -> std::io::Result<ProcessRecord> {
+    write_record_in(&run_dir(), name, pid)
+}
+
+/// Forget the record for `name`. Missing records are ignored.
+pub fn remove_record(name: &str) {
+    let _ = std::fs::remove_file(record_path(&run_dir(), name));
+}
+
+/// Return the record for `name` if it still refers to a running process.
+///
+/// Stale records (process gone or PID reused) are deleted.
+pub fn live_record(name: &str) -> Option<ProcessRecord> {
+    live_record_in(&run_dir(), name)
+}
+
+/// Return every live record, deleting stale ones along the way.
+pub fn live_records() -> Vec<ProcessRecord> {
+    live_records_in(&run_dir())
+}
+
+/// Whether `record` still describes the same running process.
+pub fn is_alive(record: &ProcessRecord) -> bool {
+    std::fs::read_to_string(format!("/proc/{}/stat", record.pid))
+        .ok()
+        .and_then(|stat| parse_stat(&stat))
+        .is_some_and(|(state, start_time)| state != 'Z' && start_time == record.start_time)
+}
+
+/// Send `signal` to the process described by `record` if it is still alive.
+///
+/// Returns `false` without signalling when the record is stale.
+pub fn signal(record: &ProcessRecord, signal: libc::c_int) -> bool {
+    if !is_alive(record) {
+        return false;
+    }
+    // SAFETY: kill(2) has no memory-safety preconditions; the target was verified above.
+    unsafe { libc::kill(record.pid as libc::pid_t, signal) == 0 }
+}
+
+/// Adapters stopped with `yuiclaw adapter stop`. The supervisor leaves them stopped until
+/// they are started again; the list is cleared whenever the supervisor starts.
+pub fn stopped_adapters() -> BTreeSet<String> {
+    read_name_list(&stopped_adapters_path())
+}
+
+/// Add `name` to (or remove it from) the [`stopped_adapters`] list.
+pub fn set_adapter_stopped(name: &str, stopped: bool) -> std::io::Result<()> {
+    update_name_list(&stopped_adapters_path(), name, stopped)
+}
+
+/// Forget every manually stopped adapter.
+pub fn clear_stopped_adapters() {
+    let _ = std::fs::remove_file(stopped_adapters_path());
+}
+
+fn stopped_adapters_path() -> PathBuf {
+    state_dir().join("stopped-adapters")
+}
+
+/// Counters reported by `yuiclaw metrics`, stored as `<state_dir>/counters.json`. They
+/// survive daemon restarts, so that Prometheus sees them increase monotonically.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Counters {
+    /// Restarts by the supervisor, by process name (`bridge` or an adapter).
+    pub restarts: BTreeMap<String, u64>,
+    /// Messages published to the bridge, by source (`cli`, `api`, `webhook`).
+    pub published: BTreeMap<String, u64>,
+    /// `yuiclaw tick` runs whose due jobs (in abeat or the built-in scheduler) succeeded.
+    pub ticks_succeeded: u64,
+    /// `yuiclaw tick` runs whose due jobs failed.
+    pub ticks_failed: u64,
+    /// Unix time of the last heartbeat prompt (`yuiclaw pub --channel heartbeat`).
+    pub last_heartbeat: Option<i64>,
+}
+
+pub fn counters() -> Counters {
+    read_counters(&counters_path())
+}
+
+/// Change the [`Counters`] under an exclusive lock, so that concurrent processes do not
+/// lose updates. Failures are ignored: recording a metric must never fail a command.
+pub fn update_counters(update: impl FnOnce(&mut Counters)) {
+    let _ = update_counters_in(&counters_path(), update);
+}
+
+/// `counters.json` in the state directory.
+pub fn counters_path() -> PathBuf {
+    state_dir().join("counters.json")
+}
+
+pub fn read_counters(path: &Path) -> Counters {
+    read_json(path)
+}
+
+pub fn update_counters_in(path: &Path, update: impl FnOnce(&mut Counters)) -> std::io::Result<()> {
+    update_json_in(path, update)
+}
+
+/// When a job declared in `jobs.toml` was last checked and last run by
+/// `yuiclaw jobs run --if-due` or the built-in scheduler, stored in `<state_dir>/job-runs.json` by job name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobRun {
+    /// Unix time of the last due check; cron schedules are checked from here on.
+    pub last_checked: Option<i64>,
+    /// Unix time of the last run.
+    pub last_run: Option<i64>,
+}
+
+pub fn job_runs() -> BTreeMap<String, JobRun> {
+    read_json(&job_runs_path())
+}
+
+/// Change the [`JobRun`] records under an exclusive lock and return what `update` decided,
+/// so that two concurrent checks cannot both run a job.
+pub fn update_job_runs<R>(
+    update: impl FnOnce(&mut BTreeMap<String, JobRun>) -> R,
+) -> std::io::Result<R> {
+    update_json_in(&job_runs_path(), update)
+}
+
+fn job_runs_path() -> PathBuf {
+    state_dir().join("job-runs.json")
+}
+
+/// `yuiclaw dnd on`, stored in `<state_dir>/dnd.json`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Dnd {
+    pub on: bool,
+    /// Unix time do-not-disturb ends; it lasts until `yuiclaw dnd off` when unset.
+    pub until: Option<i64>,
+}
+
+pub fn dnd() -> Dnd {
+    read_json(&dnd_path())
+}
+
+pub fn set_dnd(dnd: Dnd) -> std::io::Result<()> {
+    update_json_in(&dnd_path(), |current| *current = dnd)
+}
+
+fn dnd_path() -> PathBuf {
+    state_dir().join("dnd.json")
+}
+
+/// A proactive prompt held back during quiet hours, stored in `<state_dir>/quiet-queue.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedPrompt {
+    pub channel: Option<String>,
+    pub message: String,
+    /// Unix time the prompt was queued.
+    pub queued_at: i64,
+}
+
+pub fn queued_prompts() -> Vec<QueuedPrompt> {
+    read_json(&queue_path())
+}
+
+/// Change the queued prompts under an exclusive lock and return what `update` decided.
+pub fn update_queued_prompts<R>(
+    update: impl FnOnce(&mut Vec<QueuedPrompt>) -> R,
+) -> std::io::Result<R> {
+    update_json_in(&queue_path(), update)
+}
+
+fn queue_path() -> PathBuf {
+    state_dir().join("quiet-queue.json")
+}
+
+/// When each channel last received a prompt, as Unix time by channel name, stored in
+/// `<state_dir>/channel-activity.json` by the daemon.
+pub fn channel_activity() -> BTreeMap<String, i64> {
+    read_json(&channel_activity_path())
+}
+
+/// Record a prompt on `channel` now, forgetting channels that have been quiet since the last
+/// heartbeat. Failures are ignored, as for the counters.
+pub fn record_channel_activity(channel: &str) {
+    let _ = record_channel_activity_in(
+        &channel_activity_path(),
+        channel,
+        chrono::Utc::now().timestamp(),
+        counters().last_heartbeat,
+    );
+}
+
+fn record_channel_activity_in(
+    path: &Path,
+    channel: &str,
+    now: i64,
+    last_heartbeat: Option<i64>,
+) -> std::io::Result<()> {
+    update_json_in(path, |activity: &mut BTreeMap<String, i64>| {
+        activity.retain(|_, at| last_heartbeat.is_none_or(|last| *at > last));
+        activity.insert(channel.to_string(), now);
+    })
+}
+
+fn channel_activity_path() -> PathBuf {
+    state_dir().join("channel-activity.json")
+}
+
+fn read_json<T: Default + serde::de::DeserializeOwned>(path: &Path) -> T {
+    std::fs::read(path)
+        .ok()
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Read-modify-write the JSON file at `path` under an exclusive lock on a sibling `.lock`
+/// file, replacing it atomically.
+fn update_json_in<T, R>(path: &Path, update: impl FnOnce(&mut T) -> R) -> std::io::Result<R>
+where
+    T: Default + Serialize + serde::de::DeserializeOwned,
+{
+    let _lock = lock(path)?;
+    let mut value = read_json(path);
+    let result = update(&mut value);
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(&value)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(result)
+}
+
+/// Take an exclusive lock on the `.lock` file next to `path`, creating its directory. The
+/// lock is released when the returned file is closed.
+fn lock(path: &Path) -> std::io::Result<std::fs::File> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let lock = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path.with_extension("lock"))?;
+    // SAFETY: flock(2) on a descriptor we own; the lock is released when `lock` is closed.
+    if unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(lock)
+}
+
+fn read_name_list(path: &Path) -> BTreeSet<String> {
+    std::fs::read_to_string(path)
+        .map(|contents| {
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Add or remove `name` under the same lock as [`update_json_in`], so that concurrent
+/// `yuiclaw adapter start` / `stop` runs do not lose each other's changes.
+fn update_name_list(path: &Path, name: &str, listed: bool) -> std::io::Result<()> {
+    let _lock = lock(path)?;
+    let mut names = read_name_list(path);
+    if listed {
+        names.insert(name.to_string());
+    } else {
+        names.remove(name);
+    }
+    write_name_list(path, &names)
+}
+
+fn write_name_list(path: &Path, names: &BTreeSet<String>) -> std::io::Result<()> {
+    if names.is_empty() {
+        return match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let contents: String = names.iter().map(|n| format!("{}\n", n)).collect();
+    // Replaced atomically: the supervisor reads the list without taking the lock.
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)
+}
+
+fn write_record_in(dir: &Path, name: &str, pid: u32) -> std::io::Result<ProcessRecord> {
+    let (start_time, cmdline) = read_proc_identity(pid).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("process {} not found in /proc", pid),
+        )
+    })?;
+    let record = ProcessRecord {
+        name: name.to_string(),
+        pid,
+        start_time,
+        cmdline,
+        recorded_at: chrono::Local::now().to_rfc3339(),
+    };
+
+    std::fs::create_dir_all(dir)?;
+    let path = record_path(dir, name);
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(&record)?)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(record)
+}
+
+fn live_record_in(dir: &Path, name: &str) -> Option<ProcessRecord> {
+    let path = record_path(dir, name);
+    let record = read_record_file(&path)?;
+    if is_alive(&record) {
+        Some(record)
+    } else {
+        let _ = std::fs::remove_file(&path);
+        None
+    }
+}
+
+fn live_records_in(dir: &Path) -> Vec<ProcessRecord> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut records: Vec<ProcessRecord> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let record = read_record_file(&path)?;
+            if is_alive(&record) {
+                Some(record)
+            } else {
+                let _ = std::fs::remove_file(&path);
+                None
+            }
+        })
+        .collect();
+    records.sort_by(|a, b| a.name.cmp(&b.name));
+    records
+}
+
+/// Unreadable or corrupt record files are treated as stale and removed.
+fn read_record_file(path: &Path) -> Option<ProcessRecord> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(record) => Some(record),
+        Err(_) => {
+            let _ = std::fs::remove_file(path);
+            None
+        }
+    }
+}
+
+fn record_path(dir: &Path, name: &str) -> PathBuf {
+    let file_stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(format!("{}.json", file_stem))
+}
+
+/// Read `(start_time, cmdline)` for `pid` from `/proc`.
+fn read_proc_identity(pid: u32) -> Option<(u64, Vec<String>)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (_, start_time) = parse_stat(&stat)?;
+
+    let raw = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let cmdline = raw
+        .split(|b| *b == 0)
+        .filter(|part| !part.is_empty())
+        .map(|part| String::from_utf8_lossy(part).into_owned())
+        .collect();
+
+    Some((start_time, cmdline))
+}
+
+/// Extract field 3 (`state`) and field 22 (`starttime`) from the contents of `/proc/<pid>/stat`.
+///
+/// The second field (`comm`) is parenthesised and may itself contain spaces or parentheses,
+/// so parsing starts after the last `)`.
+fn parse_stat(stat: &str) -> Option<(char, u64)> {
+    let rest = &stat[stat.rfind(')')? + 1..];
+    // `rest` starts at field 3 (state), so field 22 is the 20th token.
+    let mut fields = rest.split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let start_time = fields.nth(18)?.parse().ok()?;
+    Some((state, start_time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn spawn_sleeper() -> std::process::Child {
+        std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .expect("sleep should spawn")
+    }
+
+    #[test]
+    fn parse_stat_handles_spaces_in_comm() {
+        let stat = "1234 (my (weird) cmd) S 1 1234 1234 0 -1 4194560 100 0 0 0 0 0 0 0 20 0 1 0 987654 1000 10";
+        assert_eq!(parse_stat(stat), Some(('S', 987654)));
+    }
+
+    #[test]
+    fn parse_stat_rejects_truncated_input() {
+        assert_eq!(parse_stat("1234 (cmd) S 1 2 3"), None);
+    }
+
+    #[test]
+    fn name_list_adds_and_removes_names() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("stopped-adapters");
+        update_name_list(&path, "slack", true).unwrap();
+        update_name_list(&path, "discord", true).unwrap();
+        update_name_list(&path, "slack", true).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "discord\nslack\n");
+
+        update_name_list(&path, "discord", false).unwrap();
+        update_name_list(&path, "slack", false).unwrap();
+        assert!(read_name_list(&path).is_empty());
+        assert!(!path.exists(), "an empty list removes the file");
+    }
+
+    #[test]
+    fn channel_activity_forgets_channels_quiet_since_the_last_heartbeat() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("channel-activity.json");
+        record_channel_activity_in(&path, "discord", 100, None).unwrap();
+        record_channel_activity_in(&path, "email", 200, None).unwrap();
+        record_channel_activity_in(&path, "slack", 300, Some(150)).unwrap();
+        let activity: BTreeMap<String, i64> = read_json(&path);
+        assert_eq!(
+            activity,
+            BTreeMap::from([("email".to_string(), 200), ("slack".to_string(), 300)])
+        );
+    }
+
+    #[test]
+    fn concurrent_name_list_updates_are_not_lost() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("stopped-adapters");
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || update_name_list(&path, &format!("a{}", i), true))
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap().unwrap();
+        }
+        assert_eq!(read_name_list(&path).len(), 8);
+    }
+
+    #[test]
+    fn concurrent_counter_updates_are_not_lost() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state").join("counters.json");
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        update_counters_in(&path, |c| {
+                            *c.restarts.entry("slack".to_string()).or_default() += 1;
+                        })
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(read_counters(&path).restarts["slack"], 80);
+        assert_eq!(
+            read_counters(&dir.path().join("missing.json")),
+            Counters::default()
+        );
+    }
+
+    #[test]
+    fn record_path_sanitizes_names() {
+        let dir = Path::new("/state/run");
+        assert_eq!(record_path(dir, "Discord"), dir.join("discord.json"));
+        assert_eq!(
+            record_path(dir, "../evil name"),
+            dir.join("___evil_name.json")
+        );
+    }
+
+    #[test]
+    fn written_record_is_live_while_process_runs() {
+        let dir = tempdir().unwrap();
+        let mut child = spawn_sleeper();
+
+        let record = write_record_in(dir.path(), "bridge", child.id()).unwrap();
+        assert_eq!(live_record_in(dir.path(), "bridge"), Some(record));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(live_record_in(dir.path(), "bridge").is_none());
+        assert!(!record_path(dir.path(), "bridge").exists());
+    }
+
+    #[test]
+    fn record_with_mismatched_start_time_is_stale() {
+        let dir = tempdir().unwrap();
+        let mut child = spawn_sleeper();
+
+        let mut record = write_record_in(dir.path(), "Slack", child.id()).unwrap();
+        record.start_time += 1;
+        std::fs::write(
+            record_path(dir.path(), "Slack"),
+            serde_json::to_vec(&record).unwrap(),
+        )
+        .unwrap();
+
+        assert!(!is_alive(&record));
+        assert!(!signal(&record, libc::SIGTERM));
+        assert!(live_record_in(dir.path(), "Slack").is_none());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn unreaped_zombie_is_not_alive() {
+        let dir = tempdir().unwrap();
+        let mut child = spawn_sleeper();
+        let record = write_record_in(dir.path(), "ntfy", child.id()).unwrap();
+
+        child.kill().unwrap();
+        // Give the kernel a moment to turn the child into a zombie before reaping it.
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!is_alive(&record));
+
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn corrupt_record_files_are_removed() {
+        let dir = tempdir().unwrap();
+        let path = record_path(dir.path(), "ntfy");
+        std::fs::write(&path, b"not json").unwrap();
+
+        assert!(live_records_in(dir.path()).is_empty());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn live_records_lists_only_running_processes() {
+        let dir = tempdir().unwrap();
+        let mut alive = spawn_sleeper();
+        let mut dead = spawn_sleeper();
+        write_record_in(dir.path(), "bridge", alive.id()).unwrap();
+        write_record_in(dir.path(), "ntfy", dead.id()).unwrap();
+        dead.kill().unwrap();
+        dead.wait().unwrap();
+
+        let names: Vec<String> = live_records_in(dir.path())
+            .into_iter()
+            .map(|r| r.name)
+            .collect();
+        assert_eq!(names, vec!["bridge".to_string()]);
+
+        alive.kill().unwrap();
+        alive.wait().unwrap();
+    }
+
+    #[test]
+    fn write_record_fails_for_missing_process() {
+        let dir = tempdir().unwrap();
+        assert!(write_record_in(dir.path(), "bridge", u32::MAX).is_err());
+    }
+}
@@ -1,52 +1,60 @@
-use crate::components::{self, SOCKET_PATH};
+use crate::adapters::{self, Adapter};
+use crate::components::{self, BridgeHealth, socket_path};
+use crate::config::{self, Config};
+use crate::jobs;
+use crate::profile;
+use crate::quiet;
+use crate::state;
+use crate::supervisor::SUPERVISOR_RECORD;
 use serde::Serialize;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
-#[derive(Debug, Clone, Copy)]
-struct ChannelSpec {
-    label: &'static str,
-    env_keys: &'static [&'static str],
-    adapter_flag: &'static str,
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct ChannelStatus {
+    pub(crate) label: String,
+    pub(crate) connected: bool,
+    /// `false` when disabled in config.toml (`yuiclaw adapter disable`).
+    pub(crate) enabled: bool,
+    /// Stopped with `yuiclaw adapter stop`.
+    pub(crate) stopped: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
-struct ChannelStatus {
-    label: &'static str,
-    connected: bool,
+impl ChannelStatus {
+    fn describe(&self) -> &'static str {
+        if !self.enabled {
+            "- disabled"
+        } else if self.stopped {
+            "- stopped"
+        } else if self.connected {
+            "✓ connected"
+        } else {
+            "✗ not connected"
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
-struct DaemonStatus {
-    bridge_running: bool,
-    socket_path: String,
-    channels: Vec<ChannelStatus>,
+pub(crate) struct DaemonStatus {
+    pub(crate) profile: String,
+    pub(crate) bridge_running: bool,
+    /// Result of the socket probe: `not_running`, `socket_file_present`, `accepting` or
+    /// `responding` (with `latency_ms`).
+    pub(crate) bridge: BridgeHealth,
+    pub(crate) socket_path: String,
+    /// PID of the `yuiclaw daemon run` supervisor, if one owned by yuiclaw is alive.
+    pub(crate) supervisor_pid: Option<u32>,
+    pub(crate) channels: Vec<ChannelStatus>,
 }
 
-const CHANNEL_SPECS: [ChannelSpec; 3] = [
-    ChannelSpec {
-        label: "ntfy",
-        env_keys: &["NTFY_TOPIC"],
-        adapter_flag: "--ntfy",
-    },
-    ChannelSpec {
-        label: "Discord",
-        env_keys: &["DISCORD_BOT_TOKEN"],
-        adapter_flag: "--discord",
-    },
-    ChannelSpec {
-        label: "Slack",
-        env_keys: &["SLACK_APP_TOKEN", "SLACK_BOT_TOKEN"],
-        adapter_flag: "--slack",
-    },
-];
-
 /// 全コンポーネントのステータスをターミナルに表示する
 pub async fn show_status() -> Result<(), Box<dyn std::error::Error>> {
     let s = components::detect().await;
     let channels = detect_channel_statuses(s.bridge_running).await;
+    let supervisor_pid = supervisor_pid();
 
     println!("=== YuiClaw Status ===");
     println!();
+    print_profile_line();
 
     println!("[Components]");
     println!(
@@ -76,46 +84,21 @@ pub async fn show_status() -> Result<(), Box<dyn std::error::Error>> {
     println!();
 
     println!("[Bridge]");
-    if s.bridge_running {
-        println!("  Socket: ✓ running ({})", SOCKET_PATH);
-    } else {
-        println!("  Socket: ✗ not running  (run `yuiclaw start` to launch)");
-    }
+    print_bridge_line(&s.bridge, "yuiclaw start");
+    print_supervisor_line(supervisor_pid);
     println!();
 
-    if !channels.is_empty() {
-        println!("[Channels]");
-        for ch in channels {
-            println!(
-                "  {:<7}: {}",
-                ch.label,
-                if ch.connected {
-                    "✓ connected"
-                } else {
-                    "✗ not connected"
-                }
-            );
-        }
-        println!();
-    }
+    print_channels(&channels);
 
-    if s.abeat_available {
-        println!("[Scheduled Jobs]");
-        match tokio::process::Command::new("abeat")
-            .arg("list")
-            .output()
-            .await
-        {
-            Ok(out) if out.status.success() => {
-                let text = String::from_utf8_lossy(&out.stdout);
-                for line in text.lines() {
-                    println!("  {}", line);
-                }
-            }
-            _ => println!("  (no jobs configured — run `yuiclaw init` to set up defaults)"),
-        }
-        println!();
+    println!("[Scheduled Jobs]");
+    match jobs::rows().await {
+        Ok(rows) => jobs::print_table(&rows, "  "),
+        Err(e) => println!("  ✗ {}", e),
     }
+    if let Some(summary) = quiet::summary() {
+        println!("  {}", summary);
+    }
+    println!();
 
     if s.amem_available {
         println!("[Memory]");
@@ -138,120 +121,141 @@ pub async fn show_status() -> Result<(), Box<dyn std::error::Error>> {
 
 /// デーモン（bridge + adapters）のステータスを表示する
 pub async fn show_daemon_status(json: bool) -> Result<(), Box<dyn std::error::Error>> {
-    let s = components::detect().await;
-    let channels = detect_channel_statuses(s.bridge_running).await;
+    let status = daemon_status().await;
 
     if json {
-        let status = DaemonStatus {
-            bridge_running: s.bridge_running,
-            socket_path: SOCKET_PATH.to_string(),
-            channels,
-        };
         println!("{}", serde_json::to_string_pretty(&status)?);
         return Ok(());
     }
 
     println!("=== YuiClaw Daemon Status ===");
     println!();
+    print_profile_line();
 
     println!("[Bridge]");
-    if s.bridge_running {
-        println!("  Socket: ✓ running ({})", SOCKET_PATH);
-    } else {
-        println!(
-            "  Socket: ✗ not running  (run `yuiclaw daemon start` to launch)"
-        );
-    }
+    print_bridge_line(&status.bridge, "yuiclaw daemon start");
+    print_supervisor_line(status.supervisor_pid);
     println!();
 
-    if !channels.is_empty() {
-        println!("[Channels]");
-        for ch in &channels {
-            println!(
-                "  {:<7}: {}",
-                ch.label,
-                if ch.connected {
-                    "✓ connected"
-                } else {
-                    "✗ not connected"
-                }
-            );
-        }
-        println!();
-    }
+    print_channels(&status.channels);
 
     Ok(())
 }
 
-async fn detect_channel_statuses(bridge_running: bool) -> Vec<ChannelStatus> {
-    let present_env_keys = present_nonempty_env_keys();
-    let process_list = read_process_list().await.unwrap_or_default();
+/// `yuiclaw daemon status --json` の内容を集める (制御 API からも使う)
+pub(crate) async fn daemon_status() -> DaemonStatus {
+    let s = components::detect().await;
+    let channels = detect_channel_statuses(s.bridge_running).await;
+    DaemonStatus {
+        bridge_running: s.bridge_running,
+        bridge: s.bridge,
+        profile: profile::current().name.clone(),
+        socket_path: socket_path().display().to_string(),
+        supervisor_pid: supervisor_pid(),
+        channels,
+    }
+}
 
-    channel_statuses_from_inputs(&present_env_keys, &process_list, bridge_running)
+/// bridge の死活確認の結果を表示する。停止中なら起動コマンドを案内する
+fn print_bridge_line(health: &BridgeHealth, start_command: &str) {
+    // 残骸のソケットファイルは起動時に削除されるので、どちらも起動コマンドを案内すればよい
+    let hint = if health.is_running() {
+        String::new()
+    } else {
+        format!("  (run `{}` to launch)", start_command)
+    };
+    println!(
+        "  Socket: {} ({}){}",
+        health.describe(),
+        socket_path().display(),
+        hint
+    );
 }
 
-fn present_nonempty_env_keys() -> HashSet<String> {
-    std::env::vars()
-        .filter_map(|(k, v)| if v.trim().is_empty() { None } else { Some(k) })
-        .collect()
+/// 設定済みのアダプターを表示する (無効化・一時停止中のものも含む)
+fn print_channels(channels: &[ChannelStatus]) {
+    if channels.is_empty() {
+        return;
+    }
+    let width = channels
+        .iter()
+        .map(|ch| ch.label.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(7);
+    println!("[Channels]");
+    for ch in channels {
+        println!("  {:<width$}: {}", ch.label, ch.describe());
+    }
+    println!();
 }
 
-async fn read_process_list() -> Option<String> {
-    let out = tokio::process::Command::new("ps")
-        .args(["-eo", "comm=,args="])
-        .output()
-        .await
-        .ok()?;
+/// default 以外のプロファイルを使用している場合のみ表示する
+fn print_profile_line() {
+    let p = profile::current();
+    if !p.is_default() {
+        println!("Profile: {}", p.name);
+        println!();
+    }
+}
 
-    if !out.status.success() {
-        return None;
+fn print_supervisor_line(supervisor_pid: Option<u32>) {
+    match supervisor_pid {
+        Some(pid) => println!("  Supervisor: ✓ running (pid {})", pid),
+        None => println!("  Supervisor: ✗ not running"),
     }
+}
 
-    Some(String::from_utf8_lossy(&out.stdout).into_owned())
+fn supervisor_pid() -> Option<u32> {
+    state::live_record(SUPERVISOR_RECORD).map(|r| r.pid)
+}
+
+/// Adapters count as connected only when yuiclaw owns a live process for them and their
+/// health check passes.
+async fn detect_channel_statuses(bridge_running: bool) -> Vec<ChannelStatus> {
+    let config = config::current();
+    let owned: HashSet<String> = state::live_records().into_iter().map(|r| r.name).collect();
+    let registry = adapters::registry(config);
+
+    let mut rows = channel_statuses_from_inputs(
+        &registry,
+        config,
+        &adapters::present_env_keys(),
+        &state::stopped_adapters(),
+        &owned,
+        bridge_running,
+    );
+    for row in rows.iter_mut().filter(|row| row.connected) {
+        if let Some(adapter) = registry.iter().find(|a| a.label == row.label) {
+            row.connected = adapter.is_healthy().await;
+        }
+    }
+    rows
 }
 
 fn channel_statuses_from_inputs(
+    registry: &[Adapter],
+    config: &Config,
     present_env_keys: &HashSet<String>,
-    process_list: &str,
+    stopped: &BTreeSet<String>,
+    owned_processes: &HashSet<String>,
     bridge_running: bool,
 ) -> Vec<ChannelStatus> {
-    CHANNEL_SPECS
+    registry
         .iter()
-        .filter(|spec| is_channel_configured(spec, present_env_keys))
-        .map(|spec| ChannelStatus {
-            label: spec.label,
-            connected: bridge_running && process_list_has_adapter(process_list, spec.adapter_flag),
+        .filter(|adapter| adapter.is_configured(present_env_keys))
+        .map(|adapter| {
+            let enabled = config.adapter_enabled(&adapter.name);
+            ChannelStatus {
+                label: adapter.label.clone(),
+                connected: enabled && bridge_running && owned_processes.contains(&adapter.name),
+                enabled,
+                stopped: stopped.contains(&adapter.name),
+            }
         })
         .collect()
 }
 
-fn is_channel_configured(spec: &ChannelSpec, present_env_keys: &HashSet<String>) -> bool {
-    spec.env_keys.iter().all(|k| present_env_keys.contains(*k))
-}
-
-fn process_list_has_adapter(process_list: &str, adapter_flag: &str) -> bool {
-    process_list
-        .lines()
-        .any(|line| process_line_matches_adapter(line, adapter_flag))
-}
-
-fn process_line_matches_adapter(line: &str, adapter_flag: &str) -> bool {
-    let trimmed = line.trim_start();
-    if trimmed.is_empty() {
-        return false;
-    }
-
-    let Some((comm, args)) = trimmed.split_once(char::is_whitespace) else {
-        return false;
-    };
-
-    if comm != "acomm" {
-        return false;
-    }
-
-    args.split_whitespace().any(|token| token == adapter_flag)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -260,81 +264,135 @@ mod tests {
         keys.iter().map(|k| (*k).to_string()).collect()
     }
 
+    fn owned(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|n| (*n).to_string()).collect()
+    }
+
+    fn channel_statuses(
+        present_env_keys: &HashSet<String>,
+        owned_processes: &HashSet<String>,
+        bridge_running: bool,
+    ) -> Vec<ChannelStatus> {
+        let config = Config::default();
+        let registry = adapters::registry(&config);
+        channel_statuses_from_inputs(
+            &registry,
+            &config,
+            present_env_keys,
+            &BTreeSet::new(),
+            owned_processes,
+            bridge_running,
+        )
+    }
+
     #[test]
     fn hides_unconfigured_channels() {
-        let rows = channel_statuses_from_inputs(&env_keys(&[]), "", true);
+        let rows = channel_statuses(&env_keys(&[]), &owned(&[]), true);
         assert!(rows.is_empty());
     }
 
     #[test]
     fn shows_configured_channel_as_not_connected_when_adapter_process_missing() {
-        let rows = channel_statuses_from_inputs(&env_keys(&["DISCORD_BOT_TOKEN"]), "", true);
+        let rows = channel_statuses(&env_keys(&["DISCORD_BOT_TOKEN"]), &owned(&[]), true);
         assert_eq!(
             rows,
             vec![ChannelStatus {
-                label: "Discord",
-                connected: false
+                label: "Discord".to_string(),
+                connected: false,
+                enabled: true,
+                stopped: false
             }]
         );
     }
 
     #[test]
     fn marks_channel_connected_only_when_bridge_and_adapter_process_are_running() {
-        let ps_output = "acomm           acomm --discord\n";
-        let rows = channel_statuses_from_inputs(&env_keys(&["DISCORD_BOT_TOKEN"]), ps_output, true);
+        let running = owned(&["bridge", "discord"]);
+        let rows = channel_statuses(&env_keys(&["DISCORD_BOT_TOKEN"]), &running, true);
         assert_eq!(
             rows,
             vec![ChannelStatus {
-                label: "Discord",
-                connected: true
+                label: "Discord".to_string(),
+                connected: true,
+                enabled: true,
+                stopped: false
             }]
         );
 
         let rows_without_bridge =
-            channel_statuses_from_inputs(&env_keys(&["DISCORD_BOT_TOKEN"]), ps_output, false);
+            channel_statuses(&env_keys(&["DISCORD_BOT_TOKEN"]), &running, false);
         assert_eq!(
             rows_without_bridge,
             vec![ChannelStatus {
-                label: "Discord",
-                connected: false
+                label: "Discord".to_string(),
+                connected: false,
+                enabled: true,
+                stopped: false
             }]
         );
     }
 
     #[test]
     fn slack_requires_both_tokens_to_be_configured() {
-        let ps_output = "acomm           acomm --slack\n";
-        let missing_bot =
-            channel_statuses_from_inputs(&env_keys(&["SLACK_APP_TOKEN"]), ps_output, true);
+        let running = owned(&["slack"]);
+        let missing_bot = channel_statuses(&env_keys(&["SLACK_APP_TOKEN"]), &running, true);
         assert!(missing_bot.is_empty());
 
-        let configured = channel_statuses_from_inputs(
+        let configured = channel_statuses(
             &env_keys(&["SLACK_APP_TOKEN", "SLACK_BOT_TOKEN"]),
-            ps_output,
+            &running,
             true,
         );
         assert_eq!(
             configured,
             vec![ChannelStatus {
-                label: "Slack",
-                connected: true
+                label: "Slack".to_string(),
+                connected: true,
+                enabled: true,
+                stopped: false
             }]
         );
     }
 
     #[test]
-    fn process_match_requires_acomm_executable_and_exact_flag() {
-        assert!(process_line_matches_adapter(
-            "acomm           /home/user/.cargo/bin/acomm --ntfy",
-            "--ntfy"
-        ));
-        assert!(!process_line_matches_adapter(
-            "cargo           cargo run -p acomm -- --ntfy",
-            "--ntfy"
-        ));
-        assert!(!process_line_matches_adapter(
-            "acomm           acomm --notify",
-            "--ntfy"
-        ));
+    fn adapter_processes_not_owned_by_yuiclaw_are_ignored() {
+        let rows = channel_statuses(
+            &env_keys(&["NTFY_TOPIC"]),
+            &owned(&["bridge", "discord"]),
+            true,
+        );
+        assert_eq!(
+            rows,
+            vec![ChannelStatus {
+                label: "ntfy".to_string(),
+                connected: false,
+                enabled: true,
+                stopped: false
+            }]
+        );
+    }
+
+    #[test]
+    fn disabled_and_stopped_channels_are_listed_but_not_connected() {
+        let config = Config::parse("[adapters.discord]\nenabled = false\n").unwrap();
+        let running = owned(&["discord", "slack"]);
+        let stopped: BTreeSet<String> = ["slack".to_string()].into();
+        let rows = channel_statuses_from_inputs(
+            &adapters::registry(&config),
+            &config,
+            &env_keys(&["DISCORD_BOT_TOKEN", "SLACK_APP_TOKEN", "SLACK_BOT_TOKEN"]),
+            &stopped,
+            &running,
+            true,
+        );
+        let described: Vec<(&str, &str)> = rows
+            .iter()
+            .map(|row| (row.label.as_str(), row.describe()))
+            .collect();
+        assert_eq!(
+            described,
+            vec![("Discord", "- disabled"), ("Slack", "- stopped")]
+        );
+        assert!(!rows[0].connected);
     }
 }
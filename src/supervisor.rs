@@ -0,0 +1,666 @@
+use crate::metrics;
+use crate::notify::{self, Event, EventKind};
+use crate::state;
+use chrono::Local;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::net::UnixStream;
+use tokio::process::{Child, Command};
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// How long the supervisor waits for the bridge socket before starting adapters anyway.
+const BRIDGE_READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a child gets to exit after SIGTERM before it is killed.
+const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How long shutdown waits for the `daemon_stopped` notification after the children are
+/// stopped, so that `yuiclaw daemon stop` does not time out on an unreachable webhook.
+const STOP_NOTIFY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A long-running process owned by the supervisor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildSpec {
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub workdir: Option<PathBuf>,
+    pub restart: RestartMode,
+}
+
+/// When a supervised child is restarted after it exits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartMode {
+    /// Restart after every exit (the default).
+    #[default]
+    Always,
+    /// Restart only after a non-zero exit status or a signal.
+    OnFailure,
+    /// Never restart.
+    Never,
+}
+
+impl RestartMode {
+    fn restarts_after(self, success: bool) -> bool {
+        match self {
+            Self::Always => true,
+            Self::OnFailure => !success,
+            Self::Never => false,
+        }
+    }
+}
+
+/// Restart timing applied to every supervised child.
+#[derive(Debug, Clone, Copy)]
+pub struct RestartPolicy {
+    /// Delay before the first restart; doubled after every consecutive crash.
+    pub initial_backoff: Duration,
+    /// Upper bound for the exponential backoff.
+    pub max_backoff: Duration,
+    /// A child that stays up at least this long is considered healthy again.
+    pub stable_after: Duration,
+    /// Sliding window used for crash-loop detection.
+    pub crash_loop_window: Duration,
+    /// Give up on a child that exits more than this many times within the window.
+    pub crash_loop_limit: usize,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            stable_after: Duration::from_secs(60),
+            crash_loop_window: Duration::from_secs(300),
+            crash_loop_limit: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RestartDecision {
+    RestartAfter(Duration),
+    GiveUp,
+}
+
+/// Tracks the exit history of one child and decides whether and when to restart it.
+struct RestartTracker {
+    policy: RestartPolicy,
+    exits: VecDeque<Instant>,
+    consecutive_crashes: u32,
+}
+
+impl RestartTracker {
+    fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy,
+            exits: VecDeque::new(),
+            consecutive_crashes: 0,
+        }
+    }
+
+    fn on_exit(&mut self, now: Instant, uptime: Duration) -> RestartDecision {
+        if uptime >= self.policy.stable_after {
+            self.consecutive_crashes = 0;
+        }
+
+        self.exits.push_back(now);
+        while let Some(&oldest) = self.exits.front() {
+            if now.duration_since(oldest) > self.policy.crash_loop_window {
+                self.exits.pop_front();
+            } else {
+                break;
+            }
+        }
+        if self.exits.len() > self.policy.crash_loop_limit {
+            return RestartDecision::GiveUp;
+        }
+
+        let delay = backoff_delay(&self.policy, self.consecutive_crashes);
+        self.consecutive_crashes = self.consecutive_crashes.saturating_add(1);
+        RestartDecision::RestartAfter(delay)
+    }
+}
+
+fn backoff_delay(policy: &RestartPolicy, consecutive_crashes: u32) -> Duration {
+    let factor = 1u32.checked_shl(consecutive_crashes).unwrap_or(u32::MAX);
+    policy
+        .initial_backoff
+        .saturating_mul(factor)
+        .min(policy.max_backoff)
+}
+
+/// Name under which the supervisor records its own PID (see [`state::write_record`]).
+pub const SUPERVISOR_RECORD: &str = "supervisor";
+
+/// Path of the log file that the background supervisor writes to.
+pub fn log_path() -> PathBuf {
+    state::state_dir().join("daemon.log")
+}
+
+/// Run the supervisor in the foreground until SIGTERM/SIGINT or until the bridge crash-loops.
+///
+/// The bridge is started first; adapters are started once its socket accepts connections.
+/// Every child is restarted with exponential backoff when it exits. `adapters` returns the
+/// adapters that should be running; it is called again on SIGHUP, and adapters that are no
+/// longer wanted (or whose spec changed) are stopped while missing ones are started.
+pub async fn run(
+    bridge: ChildSpec,
+    adapters: impl Fn() -> Vec<ChildSpec>,
+    socket_path: &Path,
+    policy: RestartPolicy,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sighup = signal(SignalKind::hangup())?;
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    let pid = std::process::id();
+    state::write_record(SUPERVISOR_RECORD, pid)?;
+    log(format!("supervisor started (pid {})", pid));
+    notify::spawn(Event::new(
+        EventKind::DaemonStarted,
+        format!("supervisor started (pid {})", pid),
+    ));
+
+    let mut bridge_task = tokio::spawn(supervise(bridge, policy, shutdown_rx));
+
+    if !wait_for_socket(socket_path, BRIDGE_READY_TIMEOUT).await {
+        log("bridge socket is not accepting connections yet; starting adapters anyway");
+    }
+    let mut adapter_set = AdapterSet::new(policy);
+    adapter_set.reconcile(adapters()).await;
+
+    let mut bridge_finished = false;
+    let outcome = loop {
+        tokio::select! {
+            _ = sigterm.recv() => break Ok(()),
+            _ = sigint.recv() => break Ok(()),
+            _ = sighup.recv() => {
+                log("reloading adapters");
+                adapter_set.reconcile(adapters()).await;
+            }
+            res = &mut bridge_task => {
+                bridge_finished = true;
+                break match res {
+                    Ok(Ok(())) => Ok(()),
+                    Ok(Err(msg)) => Err(msg),
+                    Err(err) => Err(err.to_string()),
+                };
+            }
+        }
+    };
+
+    log("shutting down supervised processes");
+    let reason = match &outcome {
+        Ok(()) => "supervisor stopped".to_string(),
+        Err(msg) => format!("supervisor stopped: {}", msg),
+    };
+    let notified = tokio::spawn(notify::send(Event::new(EventKind::DaemonStopped, reason)));
+    let _ = shutdown_tx.send(true);
+    adapter_set.stop_all().await;
+    if !bridge_finished {
+        let _ = bridge_task.await;
+    }
+    if socket_path.exists() {
+        let _ = std::fs::remove_file(socket_path);
+    }
+    let _ = tokio::time::timeout(STOP_NOTIFY_TIMEOUT, notified).await;
+    state::remove_record(SUPERVISOR_RECORD);
+    log("supervisor stopped");
+
+    outcome.map_err(Into::into)
+}
+
+/// Adapters owned by the supervisor, each with its own shutdown signal so that it can be
+/// stopped on its own.
+struct AdapterSet {
+    policy: RestartPolicy,
+    running: HashMap<String, SupervisedChild>,
+}
+
+struct SupervisedChild {
+    spec: ChildSpec,
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<Result<(), String>>,
+}
+
+impl AdapterSet {
+    fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy,
+            running: HashMap::new(),
+        }
+    }
+
+    /// Stop adapters missing from `desired` or whose spec changed, then start every desired
+    /// adapter that is not running (including ones that gave up after a crash loop).
+    async fn reconcile(&mut self, desired: Vec<ChildSpec>) {
+        let unwanted: Vec<String> = self
+            .running
+            .iter()
+            .filter(|(_, child)| !desired.contains(&child.spec))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in unwanted {
+            self.stop(&name).await;
+        }
+
+        for spec in desired {
+            let running = self
+                .running
+                .get(&spec.name)
+                .is_some_and(|child| !child.task.is_finished());
+            if !running {
+                self.start(spec);
+            }
+        }
+    }
+
+    fn start(&mut self, spec: ChildSpec) {
+        let (shutdown, rx) = watch::channel(false);
+        let task = tokio::spawn(supervise(spec.clone(), self.policy, rx));
+        self.running.insert(
+            spec.name.clone(),
+            SupervisedChild {
+                spec,
+                shutdown,
+                task,
+            },
+        );
+    }
+
+    async fn stop(&mut self, name: &str) {
+        if let Some(child) = self.running.remove(name) {
+            let _ = child.shutdown.send(true);
+            let _ = child.task.await;
+        }
+    }
+
+    /// Stop every adapter, letting them shut down in parallel.
+    async fn stop_all(&mut self) {
+        let children: Vec<SupervisedChild> = self.running.drain().map(|(_, c)| c).collect();
+        for child in &children {
+            let _ = child.shutdown.send(true);
+        }
+        for child in children {
+            let _ = child.task.await;
+        }
+    }
+
+    #[cfg(test)]
+    fn running_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .running
+            .iter()
+            .filter(|(_, child)| !child.task.is_finished())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+/// Keep one child running until shutdown is requested or it crash-loops.
+async fn supervise(
+    spec: ChildSpec,
+    policy: RestartPolicy,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<(), String> {
+    let mut tracker = RestartTracker::new(policy);
+    // How the previous run ended, if the child is being restarted.
+    let mut previous_exit: Option<String> = None;
+
+    loop {
+        if *shutdown.borrow() {
+            return Ok(());
+        }
+
+        let started = Instant::now();
+        let (exit, success) = match spawn_child(&spec) {
+            Ok(mut child) => {
+                let pid = child.id().unwrap_or_default();
+                log(format!("{}: started (pid {})", spec.name, pid));
+                if let Some(exit) = previous_exit.take() {
+                    metrics::record_restart(&spec.name);
+                    notify::spawn(
+                        Event::new(
+                            EventKind::Restarted,
+                            format!(
+                                "{}: restarted (pid {}) after it exited ({})",
+                                spec.name, pid, exit
+                            ),
+                        )
+                        .component(&spec.name),
+                    );
+                }
+                if let Err(err) = state::write_record(&spec.name, pid) {
+                    log(format!(
+                        "{}: failed to record pid {}: {}",
+                        spec.name, pid, err
+                    ));
+                }
+                let exit = tokio::select! {
+                    status = child.wait() => match status {
+                        Ok(status) => (status.to_string(), status.success()),
+                        Err(err) => (err.to_string(), false),
+                    },
+                    _ = shutdown.changed() => {
+                        terminate_child(&mut child, &spec.name).await;
+                        state::remove_record(&spec.name);
+                        return Ok(());
+                    }
+                };
+                state::remove_record(&spec.name);
+                exit
+            }
+            Err(err) => (format!("failed to spawn {}: {}", spec.program, err), false),
+        };
+
+        let crashed = |msg: &str| {
+            if !success {
+                notify::spawn(Event::new(EventKind::Crashed, msg).component(&spec.name));
+            }
+        };
+
+        if !spec.restart.restarts_after(success) {
+            let msg = format!("{}: exited ({}); not restarting", spec.name, exit);
+            log(&msg);
+            crashed(&msg);
+            return Ok(());
+        }
+
+        match tracker.on_exit(Instant::now(), started.elapsed()) {
+            RestartDecision::RestartAfter(delay) => {
+                let msg = format!(
+                    "{}: exited ({}); restarting in {}s",
+                    spec.name,
+                    exit,
+                    delay.as_secs()
+                );
+                log(&msg);
+                crashed(&msg);
+                previous_exit = Some(exit);
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = shutdown.changed() => return Ok(()),
+                }
+            }
+            RestartDecision::GiveUp => {
+                let msg = format!(
+                    "{}: exited ({}); crash loop detected ({} exits within {}s), giving up",
+                    spec.name,
+                    exit,
+                    policy.crash_loop_limit + 1,
+                    policy.crash_loop_window.as_secs()
+                );
+                log(&msg);
+                notify::spawn(Event::new(EventKind::GaveUp, &msg).component(&spec.name));
+                return Err(msg);
+            }
+        }
+    }
+}
+
+fn spawn_child(spec: &ChildSpec) -> std::io::Result<Child> {
+    let mut cmd = Command::new(&spec.program);
+    cmd.args(&spec.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true);
+    if let Some(dir) = &spec.workdir {
+        cmd.current_dir(dir);
+    }
+    cmd.spawn()
+}
+
+/// Ask a child to exit with SIGTERM, then SIGKILL it if it does not exit within the grace period.
+async fn terminate_child(child: &mut Child, name: &str) {
+    if let Some(pid) = child.id() {
+        // SAFETY: kill(2) has no memory-safety preconditions; the pid belongs to our own child.
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+    }
+    match tokio::time::timeout(TERMINATE_GRACE_PERIOD, child.wait()).await {
+        Ok(_) => log(format!("{}: stopped", name)),
+        Err(_) => {
+            let _ = child.kill().await;
+            log(format!(
+                "{}: killed after {}s",
+                name,
+                TERMINATE_GRACE_PERIOD.as_secs()
+            ));
+        }
+    }
+}
+
+async fn wait_for_socket(path: &Path, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if UnixStream::connect(path).await.is_ok() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    false
+}
+
+pub(crate) fn log(msg: impl Display) {
+    eprintln!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), msg);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RestartPolicy {
+        RestartPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(8),
+            stable_after: Duration::from_secs(60),
+            crash_loop_window: Duration::from_secs(300),
+            crash_loop_limit: 5,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_and_is_capped() {
+        let p = policy();
+        assert_eq!(backoff_delay(&p, 0), Duration::from_secs(1));
+        assert_eq!(backoff_delay(&p, 1), Duration::from_secs(2));
+        assert_eq!(backoff_delay(&p, 3), Duration::from_secs(8));
+        assert_eq!(backoff_delay(&p, 4), Duration::from_secs(8));
+        assert_eq!(backoff_delay(&p, 64), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn tracker_gives_up_after_crash_loop_limit() {
+        let mut tracker = RestartTracker::new(policy());
+        let start = Instant::now();
+        for i in 0..5 {
+            let decision = tracker.on_exit(start + Duration::from_secs(i), Duration::ZERO);
+            assert!(matches!(decision, RestartDecision::RestartAfter(_)));
+        }
+        assert_eq!(
+            tracker.on_exit(start + Duration::from_secs(5), Duration::ZERO),
+            RestartDecision::GiveUp
+        );
+    }
+
+    #[test]
+    fn tracker_forgets_exits_outside_the_window() {
+        let mut tracker = RestartTracker::new(policy());
+        let start = Instant::now();
+        for i in 0..5 {
+            tracker.on_exit(start + Duration::from_secs(i), Duration::ZERO);
+        }
+        let later = start + Duration::from_secs(400);
+        assert!(matches!(
+            tracker.on_exit(later, Duration::ZERO),
+            RestartDecision::RestartAfter(_)
+        ));
+    }
+
+    #[test]
+    fn tracker_resets_backoff_after_stable_run() {
+        let mut tracker = RestartTracker::new(policy());
+        let start = Instant::now();
+        tracker.on_exit(start, Duration::ZERO);
+        assert_eq!(
+            tracker.on_exit(start + Duration::from_secs(1), Duration::ZERO),
+            RestartDecision::RestartAfter(Duration::from_secs(2))
+        );
+        assert_eq!(
+            tracker.on_exit(start + Duration::from_secs(100), Duration::from_secs(90)),
+            RestartDecision::RestartAfter(Duration::from_secs(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn supervise_returns_when_shutdown_is_requested() {
+        let (tx, rx) = watch::channel(false);
+        let spec = ChildSpec {
+            name: "sleeper".into(),
+            program: "sleep".into(),
+            args: vec!["30".into()],
+            workdir: None,
+            restart: RestartMode::Always,
+        };
+        let task = tokio::spawn(supervise(spec, policy(), rx));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        tx.send(true).unwrap();
+
+        let res = tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .expect("supervise should stop promptly")
+            .unwrap();
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn supervise_gives_up_on_crash_looping_child() {
+        let (_tx, rx) = watch::channel(false);
+        let spec = ChildSpec {
+            name: "crasher".into(),
+            program: "false".into(),
+            args: vec![],
+            workdir: None,
+            restart: RestartMode::Always,
+        };
+        let fast = RestartPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            crash_loop_limit: 3,
+            ..policy()
+        };
+
+        let res = tokio::time::timeout(Duration::from_secs(5), supervise(spec, fast, rx))
+            .await
+            .expect("crash loop should be detected quickly");
+        assert!(res.unwrap_err().contains("crash loop"));
+    }
+
+    fn sleeper(name: &str, seconds: &str) -> ChildSpec {
+        ChildSpec {
+            name: name.into(),
+            program: "sleep".into(),
+            args: vec![seconds.into()],
+            workdir: None,
+            restart: RestartMode::Always,
+        }
+    }
+
+    #[tokio::test]
+    async fn reconcile_starts_stops_and_replaces_adapters() {
+        let mut set = AdapterSet::new(policy());
+        set.reconcile(vec![
+            sleeper("reconcile-a", "30"),
+            sleeper("reconcile-b", "30"),
+        ])
+        .await;
+        assert_eq!(set.running_names(), vec!["reconcile-a", "reconcile-b"]);
+
+        // b is no longer wanted and a changed its arguments.
+        set.reconcile(vec![sleeper("reconcile-a", "31")]).await;
+        assert_eq!(set.running_names(), vec!["reconcile-a"]);
+        assert_eq!(set.running["reconcile-a"].spec.args, vec!["31"]);
+
+        tokio::time::timeout(Duration::from_secs(10), set.stop_all())
+            .await
+            .expect("adapters should stop promptly");
+        assert!(set.running_names().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reconcile_keys_adapters_by_name_not_label() {
+        let registry = crate::adapters::registry(&crate::config::Config::default());
+        let mut relay = registry
+            .iter()
+            .find(|a| a.name == "discord")
+            .unwrap()
+            .clone();
+        relay.command = "sleep".into();
+        relay.args = vec!["30".into()];
+        let mut mirror = relay.clone();
+        relay.name = "relay-a".into();
+        mirror.name = "relay-b".into();
+        assert_eq!(relay.label, mirror.label);
+        let specs = || {
+            [&relay, &mirror]
+                .map(|adapter| crate::process::adapter_child_spec(adapter, None))
+                .to_vec()
+        };
+
+        let mut set = AdapterSet::new(policy());
+        set.reconcile(specs()).await;
+        assert_eq!(set.running_names(), vec!["relay-a", "relay-b"]);
+        let task_ids = |set: &AdapterSet| {
+            let mut ids: Vec<_> = set.running.values().map(|c| c.task.id()).collect();
+            ids.sort();
+            ids
+        };
+        let started = task_ids(&set);
+
+        // A second reconcile (SIGHUP) keeps both children as they are.
+        set.reconcile(specs()).await;
+        assert_eq!(set.running_names(), vec!["relay-a", "relay-b"]);
+        assert_eq!(task_ids(&set), started);
+
+        tokio::time::timeout(Duration::from_secs(10), set.stop_all())
+            .await
+            .expect("adapters should stop promptly");
+    }
+
+    #[tokio::test]
+    async fn restart_mode_decides_whether_an_exit_is_restarted() {
+        for (program, restart) in [
+            ("true", RestartMode::OnFailure),
+            ("false", RestartMode::Never),
+        ] {
+            let (_tx, rx) = watch::channel(false);
+            let spec = ChildSpec {
+                name: format!("{}-{:?}", program, restart),
+                program: program.into(),
+                args: vec![],
+                workdir: None,
+                restart,
+            };
+            let res = tokio::time::timeout(Duration::from_secs(5), supervise(spec, policy(), rx))
+                .await
+                .expect("a child that is not restarted should end supervision");
+            assert!(res.is_ok());
+        }
+
+        assert!(RestartMode::OnFailure.restarts_after(false));
+        assert!(RestartMode::Always.restarts_after(true));
+    }
+}
@@ -0,0 +1,388 @@
+//! Telegram channel adapter, run by the supervisor as `yuiclaw adapter run telegram`.
+//!
+//! It long-polls the Bot API for new messages, forwards text messages from the chats listed
+//! in `TELEGRAM_ALLOWED_CHAT_IDS` to the bridge and sends the agent's reply back to the
+//! chat. Messages from any other chat are ignored (and logged, so that the chat ID can be
+//! looked up). `TELEGRAM_API_URL` points the adapter at a different Bot API server.
+
+use crate::adapters::{self, required_env};
+use crate::components::socket_path;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
+/// How long one `getUpdates` call waits for new messages.
+const LONG_POLL_SECS: u64 = 30;
+
+/// Pause after a failed poll before trying again.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Longest text the Bot API accepts in one message.
+const MAX_MESSAGE_CHARS: usize = 4096;
+
+pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let token = required_env("TELEGRAM_BOT_TOKEN")?;
+    let allowed = parse_chat_ids(&required_env("TELEGRAM_ALLOWED_CHAT_IDS")?)?;
+    let api_url = std::env::var("TELEGRAM_API_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_API_URL.to_string());
+
+    let mut relay = Relay::new(
+        BotApi::new(&api_url, &token),
+        allowed,
+        socket_path().to_path_buf(),
+    );
+    // Fail fast on a bad token; the supervisor restarts the adapter with backoff.
+    let me: User = relay.api.call("getMe", serde_json::json!({})).await?;
+    eprintln!(
+        "telegram: connected as @{} (allowed chats: {})",
+        me.username.unwrap_or_default(),
+        relay.allowed.len()
+    );
+
+    loop {
+        if let Err(e) = relay.poll_once(LONG_POLL_SECS).await {
+            eprintln!("telegram: {}", e);
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+    }
+}
+
+/// Parse a list of chat IDs separated by commas and/or whitespace.
+fn parse_chat_ids(raw: &str) -> Result<HashSet<i64>, String> {
+    let ids = adapters::split_list(raw)
+        .map(|id| {
+            id.parse::<i64>().map_err(|_| {
+                format!(
+                    "Invalid chat ID '{}' in TELEGRAM_ALLOWED_CHAT_IDS (expected numbers such as 123456789 or -1001234567890).",
+                    id
+                )
+            })
+        })
+        .collect::<Result<HashSet<_>, _>>()?;
+    if ids.is_empty() {
+        return Err("TELEGRAM_ALLOWED_CHAT_IDS does not contain any chat ID.".to_string());
+    }
+    Ok(ids)
+}
+
+/// Minimal Bot API client: JSON requests to `<api_url>/bot<token>/<method>`.
+struct BotApi {
+    http: reqwest::Client,
+    base: String,
+}
+
+/// Envelope of every Bot API response.
+#[derive(Debug, Deserialize)]
+struct Response<T> {
+    ok: bool,
+    result: Option<T>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    #[serde(default)]
+    username: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    #[serde(default)]
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    message_id: i64,
+    chat: Chat,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+impl BotApi {
+    fn new(api_url: &str, token: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base: format!("{}/bot{}", api_url.trim_end_matches('/'), token),
+        }
+    }
+
+    /// Call `method`. Errors never include the request URL, which contains the token.
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T, String> {
+        let failed = |e: reqwest::Error| format!("{} failed: {}", method, e.without_url());
+        let response: Response<T> = self
+            .http
+            .post(format!("{}/{}", self.base, method))
+            .json(&params)
+            // Leave room for the server-side long poll.
+            .timeout(Duration::from_secs(LONG_POLL_SECS + 30))
+            .send()
+            .await
+            .map_err(failed)?
+            .json()
+            .await
+            .map_err(failed)?;
+        match response {
+            Response {
+                ok: true,
+                result: Some(result),
+                ..
+            } => Ok(result),
+            Response { description, .. } => Err(format!(
+                "{} failed: {}",
+                method,
+                description.unwrap_or_else(|| "no description".to_string())
+            )),
+        }
+    }
+
+    async fn send_message(&self, chat_id: i64, text: &str) -> Result<(), String> {
+        for part in split_message(text, MAX_MESSAGE_CHARS) {
+            let _: serde_json::Value = self
+                .call(
+                    "sendMessage",
+                    serde_json::json!({ "chat_id": chat_id, "text": part }),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Relays messages between the Bot API and the bridge.
+struct Relay {
+    api: BotApi,
+    allowed: HashSet<i64>,
+    /// Bridge socket the messages are forwarded to.
+    socket: PathBuf,
+    /// `update_id` of the next update to fetch; acknowledges everything before it.
+    offset: Option<i64>,
+}
+
+impl Relay {
+    fn new(api: BotApi, allowed: HashSet<i64>, socket: PathBuf) -> Self {
+        Self {
+            api,
+            allowed,
+            socket,
+            offset: None,
+        }
+    }
+
+    /// Fetch pending updates (waiting up to `timeout_secs` for one) and handle them in
+    /// order.
+    async fn poll_once(&mut self, timeout_secs: u64) -> Result<(), String> {
+        let mut params = serde_json::json!({
+            "timeout": timeout_secs,
+            "allowed_updates": ["message"],
+        });
+        if let Some(offset) = self.offset {
+            params["offset"] = offset.into();
+        }
+        let updates: Vec<Update> = self.api.call("getUpdates", params).await?;
+        for update in updates {
+            self.offset = Some(update.update_id + 1);
+            if let Some(message) = update.message {
+                self.handle(message).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle(&self, message: Message) {
+        let chat_id = message.chat.id;
+        if !self.allowed.contains(&chat_id) {
+            eprintln!(
+                "telegram: ignoring message from chat {} (not in TELEGRAM_ALLOWED_CHAT_IDS)",
+                chat_id
+            );
+            return;
+        }
+        let Some(text) = message.text.filter(|t| !t.trim().is_empty()) else {
+            return;
+        };
+
+        // A channel per message keeps replies apart when several chats are active.
+        let channel = format!("telegram-{}-{}", chat_id, message.message_id);
+        let text = match adapters::relay_to_bridge(&self.socket, &text, &channel).await {
+            Ok(reply) if reply.trim().is_empty() => return,
+            Ok(reply) => reply,
+            Err(e) => {
+                eprintln!("telegram: {}", e);
+                format!("⚠️ {}", e)
+            }
+        };
+        if let Err(e) = self.api.send_message(chat_id, &text).await {
+            eprintln!("telegram: {}", e);
+        }
+    }
+}
+
+/// Split `text` into parts of at most `max_chars` characters, at line breaks when possible.
+fn split_message(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while rest.chars().count() > max_chars {
+        let limit = rest
+            .char_indices()
+            .nth(max_chars)
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        let cut = match rest[..limit].rfind('\n') {
+            Some(newline) if newline > 0 => newline + 1,
+            _ => limit,
+        };
+        parts.push(&rest[..cut]);
+        rest = &rest[cut..];
+    }
+    if !rest.is_empty() {
+        parts.push(rest);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::Request;
+    use crate::bridge::fake::FakeBridge;
+    use crate::fake_http::FakeHttpServer;
+    use serde_json::{Value, json};
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    const TOKEN: &str = "123:secret";
+
+    /// Local stand-in for the Bot API: serves the queued `getUpdates` batches in order
+    /// (then empty ones) and answers other methods with `true`.
+    async fn fake_bot_api(batches: Vec<Value>) -> FakeHttpServer {
+        let batches = Mutex::new(batches);
+        FakeHttpServer::start(move |request| {
+            let Some(method) = request.path.strip_prefix(&format!("/bot{}/", TOKEN)) else {
+                return (401, json!({ "ok": false, "description": "Unauthorized" }));
+            };
+            let result = match method {
+                "getUpdates" => {
+                    let mut batches = batches.lock().unwrap();
+                    if batches.is_empty() {
+                        json!([])
+                    } else {
+                        batches.remove(0)
+                    }
+                }
+                "getMe" => json!({ "username": "yuiclaw_bot" }),
+                _ => json!(true),
+            };
+            (200, json!({ "ok": true, "result": result }))
+        })
+        .await
+    }
+
+    fn calls(api: &FakeHttpServer, method: &str) -> Vec<Value> {
+        let path = format!("/bot{}/{}", TOKEN, method);
+        api.requests()
+            .into_iter()
+            .filter(|r| r.path == path)
+            .map(|r| r.body)
+            .collect()
+    }
+
+    fn text_update(update_id: i64, chat_id: i64, text: &str) -> Value {
+        json!({
+            "update_id": update_id,
+            "message": { "message_id": update_id * 10, "chat": { "id": chat_id }, "text": text },
+        })
+    }
+
+    #[test]
+    fn chat_ids_are_separated_by_commas_or_whitespace() {
+        assert_eq!(
+            parse_chat_ids("42, -1001234567890\n7").unwrap(),
+            HashSet::from([42, -1001234567890, 7])
+        );
+        assert!(parse_chat_ids("42,@team").unwrap_err().contains("'@team'"));
+        assert!(parse_chat_ids(" , ").is_err());
+    }
+
+    #[test]
+    fn long_replies_are_split_at_line_breaks() {
+        assert_eq!(split_message("short", 10), ["short"]);
+        assert_eq!(
+            split_message("line one\nline two\n", 12),
+            ["line one\n", "line two\n"]
+        );
+        assert_eq!(split_message("ああああああ", 4), ["ああああ", "ああ"]);
+    }
+
+    #[tokio::test]
+    async fn relays_messages_from_allowed_chats_and_replies() {
+        let dir = tempdir().unwrap();
+        let bridge = FakeBridge::start(&dir.path().join("acomm.sock"), &["Hi ", "there"]);
+        let api = fake_bot_api(vec![json!([
+            text_update(5, 7, "who are you?"),
+            text_update(6, 42, "hello"),
+        ])])
+        .await;
+
+        let mut relay = Relay::new(
+            BotApi::new(&api.url, TOKEN),
+            HashSet::from([42]),
+            bridge.path.clone(),
+        );
+        relay.poll_once(0).await.unwrap();
+        relay.poll_once(0).await.unwrap();
+
+        let prompts: Vec<Request> = bridge
+            .requests()
+            .into_iter()
+            .filter(|r| matches!(r, Request::Prompt { .. }))
+            .collect();
+        assert_eq!(
+            prompts,
+            vec![Request::Prompt {
+                text: "hello".to_string(),
+                channel: Some("telegram-42-60".to_string()),
+                provider: None,
+            }]
+        );
+        assert_eq!(
+            calls(&api, "sendMessage"),
+            vec![json!({ "chat_id": 42, "text": "Hi there" })]
+        );
+        // The second poll acknowledges both updates, including the ignored one.
+        assert_eq!(calls(&api, "getUpdates")[1]["offset"], 7);
+    }
+
+    #[tokio::test]
+    async fn api_errors_are_reported_without_the_token() {
+        let api = fake_bot_api(Vec::new()).await;
+        let err = BotApi::new(&api.url, "999:wrong")
+            .call::<User>("getMe", json!({}))
+            .await
+            .unwrap_err();
+        assert_eq!(err, "getMe failed: Unauthorized");
+
+        let err = BotApi::new("http://127.0.0.1:1", "999:wrong")
+            .call::<User>("getMe", json!({}))
+            .await
+            .unwrap_err();
+        assert!(!err.contains("999:wrong"), "{}", err);
+    }
+}
@@ -0,0 +1,422 @@
+//! Inbound webhooks (`yuiclaw serve-webhook`): tools that can only send HTTP requests (CI,
+//! Grafana alerts, Home Assistant) publish messages to the bridge with
+//! `POST /webhook/<name>`.
+//!
+//! Requests must carry `Authorization: Bearer <WEBHOOK_TOKEN>` or an
+//! `X-Hub-Signature-256: sha256=<hex>` HMAC-SHA256 of the body keyed with `WEBHOOK_SECRET`.
+//! The `[webhook.routes.<name>]` section of config.toml picks the channel and a template
+//! that turns the JSON payload into the message. The daemon runs the server as the
+//! `webhook` adapter when `WEBHOOK_LISTEN` is set.
+
+use crate::bridge::BridgeClient;
+use crate::components::socket_path;
+use crate::config::{self, WebhookRoute};
+use crate::http::{self, Response, error, reply};
+use crate::message::MAX_MESSAGE_BYTES;
+use crate::metrics;
+use crate::state;
+use hmac::{Hmac, Mac};
+use hyper::body::Incoming;
+use hyper::header::{CONTENT_TYPE, HeaderMap};
+use hyper::{Method, Request, StatusCode};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+/// Address `serve-webhook` listens on without `--listen` or `WEBHOOK_LISTEN`.
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:8787";
+
+/// Headers checked for an HMAC signature, GitHub's first.
+const SIGNATURE_HEADERS: [&str; 2] = ["x-hub-signature-256", "x-signature-256"];
+
+/// `yuiclaw serve-webhook`: serve until killed.
+pub async fn serve(listen: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let env = |key: &str| {
+        std::env::var(key)
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let listen = listen
+        .map(str::to_string)
+        .or_else(|| env("WEBHOOK_LISTEN"))
+        .unwrap_or_else(|| DEFAULT_LISTEN.to_string());
+    let auth = Auth::new(env("WEBHOOK_TOKEN"), env("WEBHOOK_SECRET"))?;
+
+    let listener = TcpListener::bind(&listen)
+        .await
+        .map_err(|e| format!("Cannot listen on {}: {}", listen, e))?;
+    eprintln!(
+        "webhook: listening on http://{}/webhook/<name>",
+        listener.local_addr()?
+    );
+    let server = Server {
+        routes: config::current().webhook.routes.clone(),
+        auth,
+        socket: socket_path().to_path_buf(),
+        counters: state::counters_path(),
+    };
+    Ok(server.run(listener).await?)
+}
+
+/// Whether `name` can be used in `/webhook/<name>`.
+pub fn is_valid_route_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Accepted credentials. A request is authenticated by either of them.
+struct Auth {
+    token: Option<String>,
+    secret: Option<String>,
+}
+
+impl Auth {
+    fn new(token: Option<String>, secret: Option<String>) -> Result<Self, String> {
+        if token.is_none() && secret.is_none() {
+            return Err(
+                "Set WEBHOOK_TOKEN or WEBHOOK_SECRET; unauthenticated webhooks are not accepted."
+                    .to_string(),
+            );
+        }
+        Ok(Self { token, secret })
+    }
+
+    fn accepts(&self, headers: &HeaderMap, body: &[u8]) -> bool {
+        if let Some(token) = &self.token
+            && http::has_bearer_token(headers, token)
+        {
+            return true;
+        }
+
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        let signature = SIGNATURE_HEADERS.iter().find_map(|name| header(name));
+        if let (Some(secret), Some(signature)) = (&self.secret, signature) {
+            let hex = signature.trim().trim_start_matches("sha256=");
+            let Ok(signature) = hex::decode(hex) else {
+                return false;
+            };
+            return hmac(secret, body).verify_slice(&signature).is_ok();
+        }
+        false
+    }
+}
+
+/// `sha256=<hex>` HMAC-SHA256 of `body` keyed with `secret`, as sent in
+/// `X-Hub-Signature-256`.
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    format!(
+        "sha256={}",
+        hex::encode(hmac(secret, body).finalize().into_bytes())
+    )
+}
+
+fn hmac(secret: &str, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac
+}
+
+/// A request that could not be turned into a message.
+#[derive(Debug, PartialEq, Eq)]
+struct Rejection(StatusCode, String);
+
+struct Server {
+    routes: BTreeMap<String, WebhookRoute>,
+    auth: Auth,
+    /// Bridge socket the messages are published to.
+    socket: PathBuf,
+    /// `counters.json` that published messages are counted in.
+    counters: PathBuf,
+}
+
+impl Server {
+    async fn run(self, listener: TcpListener) -> std::io::Result<()> {
+        let server = Arc::new(self);
+        loop {
+            let (stream, _) = listener.accept().await?;
+            http::spawn_connection(stream, Arc::clone(&server));
+        }
+    }
+}
+
+impl http::Handler for Server {
+    async fn handle(&self, request: Request<Incoming>) -> Response {
+        let path = request.uri().path().to_string();
+        if path == "/healthz" && request.method() == Method::GET {
+            return reply(StatusCode::OK, json!({ "ok": true }));
+        }
+        let Some(name) = path
+            .strip_prefix("/webhook/")
+            .filter(|name| is_valid_route_name(name))
+            .map(str::to_string)
+        else {
+            return error(StatusCode::NOT_FOUND, "Not found; POST to /webhook/<name>.");
+        };
+        if request.method() != Method::POST {
+            return error(StatusCode::METHOD_NOT_ALLOWED, "Use POST.");
+        }
+
+        let (parts, body) = request.into_parts();
+        let body = match http::read_body(body, MAX_MESSAGE_BYTES).await {
+            Ok(body) => body,
+            Err(response) => return response,
+        };
+        if !self.auth.accepts(&parts.headers, &body) {
+            return error(StatusCode::UNAUTHORIZED, "Missing or invalid credentials.");
+        }
+        let (channel, message) = match self.message(&name, &parts.headers, &body) {
+            Ok(message) => message,
+            Err(Rejection(status, reason)) => return error(status, &reason),
+        };
+
+        let published = match BridgeClient::connect(&self.socket).await {
+            Ok(mut client) => client.prompt(&message, Some(&channel)).await,
+            Err(e) => Err(e),
+        };
+        match published {
+            Ok(()) => {
+                metrics::record_publish_in(&self.counters, "webhook", Some(&channel));
+                reply(
+                    StatusCode::ACCEPTED,
+                    json!({ "ok": true, "channel": channel }),
+                )
+            }
+            Err(e) => {
+                eprintln!("webhook: {}", e);
+                error(StatusCode::SERVICE_UNAVAILABLE, "Bridge is not running.")
+            }
+        }
+    }
+}
+
+impl Server {
+    /// The channel and message for a request to `/webhook/<name>`.
+    fn message(
+        &self,
+        name: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<(String, String), Rejection> {
+        let route = self.routes.get(name).cloned().unwrap_or_default();
+        let channel = route.channel.unwrap_or_else(|| name.to_string());
+        let bad_request = |reason: String| Rejection(StatusCode::BAD_REQUEST, reason);
+
+        let is_json = headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("json"));
+        let payload = serde_json::from_slice::<Value>(body).ok();
+        let message = match (&route.template, payload) {
+            (Some(template), Some(payload)) => render(template, &payload),
+            (Some(_), None) => {
+                return Err(bad_request(format!(
+                    "Route '{}' has a template, so the payload must be JSON.",
+                    name
+                )));
+            }
+            (None, Some(payload)) if is_json || payload.is_object() || payload.is_array() => {
+                serde_json::to_string_pretty(&payload).unwrap_or_default()
+            }
+            (None, _) => String::from_utf8(body.to_vec())
+                .map_err(|_| bad_request("Payload is not valid UTF-8.".to_string()))?,
+        };
+        if message.trim().is_empty() {
+            return Err(bad_request("Message is empty.".to_string()));
+        }
+        Ok((channel, message))
+    }
+}
+
+/// Fill `{{path.to.field}}` placeholders from `payload`. Array elements are addressed by
+/// index (`{{alerts.0.status}}`); missing fields become empty and non-string values are
+/// written as JSON.
+pub(crate) fn render(template: &str, payload: &Value) -> String {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        let path = rest[start + 2..start + end].trim();
+        let value = path.split('.').filter(|key| !key.is_empty()).try_fold(
+            payload,
+            |value, key| match value {
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => value.get(key),
+            },
+        );
+        match value {
+            Some(Value::String(s)) => output.push_str(s),
+            Some(Value::Null) | None => {}
+            Some(other) => output.push_str(&other.to_string()),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::Request as BridgeRequest;
+    use crate::bridge::fake::FakeBridge;
+    use tempfile::tempdir;
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    hyper::header::HeaderName::from_static(name),
+                    value.parse().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn templates_fill_in_payload_fields() {
+        let payload = json!({
+            "title": "High CPU",
+            "alerts": [{ "status": "firing", "value": 97.5 }],
+            "labels": { "host": "nas" },
+        });
+        assert_eq!(
+            render(
+                "{{title}} on {{ labels.host }}: {{alerts.0.status}} ({{alerts.0.value}}){{missing}}",
+                &payload
+            ),
+            "High CPU on nas: firing (97.5)"
+        );
+        assert_eq!(
+            render("{{labels}} {{unclosed", &payload),
+            "{\"host\":\"nas\"} {{unclosed"
+        );
+    }
+
+    #[test]
+    fn requests_need_a_bearer_token_or_a_valid_signature() {
+        assert!(Auth::new(None, None).is_err());
+
+        let auth = Auth::new(Some("t0ken".to_string()), Some("s3cret".to_string())).unwrap();
+        let body = br#"{"ok":1}"#;
+        assert!(auth.accepts(&headers(&[("authorization", "Bearer t0ken")]), body));
+        assert!(!auth.accepts(&headers(&[("authorization", "Bearer nope")]), body));
+        assert!(auth.accepts(
+            &headers(&[("x-hub-signature-256", &sign("s3cret", body))]),
+            body
+        ));
+        assert!(!auth.accepts(
+            &headers(&[("x-hub-signature-256", &sign("s3cret", b"tampered"))]),
+            body
+        ));
+        assert!(!auth.accepts(&HeaderMap::new(), body));
+    }
+
+    #[test]
+    fn payloads_without_a_template_are_published_as_they_are() {
+        let server = Server {
+            routes: BTreeMap::from([(
+                "ci".to_string(),
+                WebhookRoute {
+                    channel: Some("builds".to_string()),
+                    template: Some("Build {{status}}".to_string()),
+                },
+            )]),
+            auth: Auth::new(Some("t".to_string()), None).unwrap(),
+            socket: PathBuf::new(),
+            counters: PathBuf::new(),
+        };
+        assert_eq!(
+            server.message("ci", &HeaderMap::new(), br#"{"status":"failed"}"#),
+            Ok(("builds".to_string(), "Build failed".to_string()))
+        );
+        assert_eq!(
+            server.message("ha", &HeaderMap::new(), b"Front door opened"),
+            Ok(("ha".to_string(), "Front door opened".to_string()))
+        );
+        assert_eq!(
+            server
+                .message("ci", &HeaderMap::new(), b"not json")
+                .unwrap_err()
+                .0,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn publishes_authenticated_posts_to_the_bridge() {
+        let dir = tempdir().unwrap();
+        let bridge = FakeBridge::start(&dir.path().join("acomm.sock"), &[]);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = Server {
+            routes: BTreeMap::from([(
+                "grafana".to_string(),
+                WebhookRoute {
+                    channel: None,
+                    template: Some("Alert: {{title}}".to_string()),
+                },
+            )]),
+            auth: Auth::new(Some("t0ken".to_string()), None).unwrap(),
+            socket: bridge.path.clone(),
+            counters: dir.path().join("counters.json"),
+        };
+        tokio::spawn(server.run(listener));
+
+        let http = reqwest::Client::new();
+        let response = http
+            .post(format!("{}/webhook/grafana", url))
+            .bearer_auth("t0ken")
+            .json(&json!({ "title": "Disk full" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let unauthorized = http
+            .post(format!("{}/webhook/grafana", url))
+            .json(&json!({ "title": "Disk full" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+        let wrong_method = http
+            .get(format!("{}/webhook/grafana", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(wrong_method.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        // Give the fake bridge a moment to record the prompt.
+        let expected = BridgeRequest::Prompt {
+            text: "Alert: Disk full".to_string(),
+            channel: Some("grafana".to_string()),
+            provider: None,
+        };
+        for _ in 0..50 {
+            if bridge.requests().contains(&expected) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(bridge.requests(), vec![expected]);
+        let counters = state::read_counters(&dir.path().join("counters.json"));
+        assert_eq!(counters.published.get("webhook"), Some(&1));
+    }
+}
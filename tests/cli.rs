@@ -193,6 +193,17 @@ fn test_daemon_start_help() {
     assert!(output.status.success());
 }
 
+#[test]
+fn test_daemon_run_help() {
+    let output = yuiclaw_bin()
+        .arg("daemon")
+        .arg("run")
+        .arg("--help")
+        .output()
+        .expect("failed to run yuiclaw daemon run --help");
+    assert!(output.status.success());
+}
+
 #[test]
 fn test_daemon_stop_help() {
     let output = yuiclaw_bin()
@@ -392,3 +403,220 @@ fn test_provider_subcommands_appear_in_main_help() {
         "main help should list restart subcommand"
     );
 }
+
+// --- Profile tests ---
+
+#[test]
+fn test_profile_flag_is_reported_in_daemon_status_json() {
+    let output = yuiclaw_bin()
+        .args(["--profile", "cli-test", "daemon", "status", "--json"])
+        .output()
+        .expect("failed to run yuiclaw --profile cli-test daemon status --json");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json: serde_json::Value =
+        serde_json::from_str(&stdout).expect("output should be valid JSON");
+    assert_eq!(json["profile"], "cli-test");
+    assert_ne!(json["socket_path"], "/tmp/acomm.sock");
+}
+
+#[test]
+fn test_invalid_profile_name_is_rejected() {
+    let output = yuiclaw_bin()
+        .args(["--profile", "../etc", "status"])
+        .output()
+        .expect("failed to run yuiclaw --profile ../etc status");
+    assert!(
+        !output.status.success(),
+        "path-like profile names should be rejected"
+    );
+}
+
+// --- config.toml tests ---
+
+#[test]
+fn test_invalid_config_toml_is_reported_with_line_number() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_dir = dir.path().join("yuiclaw");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(
+        config_dir.join("config.toml"),
+        "provider = \"Claude\"\n\n[heartbeat]\ninterval = \"soon\"\n",
+    )
+    .unwrap();
+
+    let output = yuiclaw_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .env_remove("YUICLAW_PROFILE")
+        .arg("status")
+        .output()
+        .expect("failed to run yuiclaw status");
+    assert!(
+        !output.status.success(),
+        "an invalid config.toml should be rejected"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("config.toml"), "{}", stderr);
+    assert!(stderr.contains("line 4"), "{}", stderr);
+}
+
+// --- Config subcommand tests ---
+
+#[test]
+fn test_config_set_get_unset_roundtrip() {
+    let dir = tempfile::tempdir().unwrap();
+    let run = |args: &[&str]| {
+        yuiclaw_bin()
+            .env("XDG_CONFIG_HOME", dir.path())
+            .env_remove("YUICLAW_PROFILE")
+            .env_remove("GEMINI_API_KEY")
+            .args(args)
+            .output()
+            .expect("failed to run yuiclaw config")
+    };
+
+    let output = run(&["config", "path"]);
+    assert!(output.status.success());
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    assert!(path.ends_with("yuiclaw/config.toml"), "{}", path);
+
+    assert!(run(&["config", "set", "GEMINI_API_KEY", "secret-value-123"]).status.success());
+    let output = run(&["config", "get", "GEMINI_API_KEY"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "secret-value-123");
+
+    let output = run(&["config", "show"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("GEMINI_API_KEY"), "{}", stdout);
+    assert!(!stdout.contains("secret-value-123"), "secrets must be masked: {}", stdout);
+    assert!(stdout.contains("config file"), "{}", stdout);
+
+    assert!(run(&["config", "unset", "GEMINI_API_KEY"]).status.success());
+    assert!(!run(&["config", "get", "GEMINI_API_KEY"]).status.success());
+
+    let output = run(&["config", "set", "provider", "gpt"]);
+    assert!(!output.status.success(), "unknown providers should be rejected");
+}
+
+#[test]
+fn test_pub_reports_missing_bridge() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = yuiclaw_bin()
+        .env("ACOMM_SOCKET_PATH", dir.path().join("acomm.sock"))
+        .args(["pub", "hello"])
+        .output()
+        .expect("failed to run yuiclaw pub");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Bridge is not running"), "{}", stderr);
+}
+
+#[test]
+fn test_ask_rejects_invalid_timeout() {
+    let output = yuiclaw_bin()
+        .args(["ask", "hello", "--timeout", "soon"])
+        .output()
+        .expect("failed to run yuiclaw ask");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid duration"), "{}", stderr);
+}
+
+#[test]
+fn test_ask_fails_without_bridge() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = yuiclaw_bin()
+        .env("ACOMM_SOCKET_PATH", dir.path().join("acomm.sock"))
+        .args(["ask", "hello", "--timeout", "1s"])
+        .output()
+        .expect("failed to run yuiclaw ask");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_pub_reads_stdin_and_validates_utf8() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = yuiclaw_bin()
+        .args(["pub", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run yuiclaw pub -");
+    child.stdin.take().unwrap().write_all(b"log\xff").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("not valid UTF-8"), "{}", stderr);
+}
+
+#[test]
+fn test_pub_requires_message_or_file() {
+    let output = yuiclaw_bin()
+        .arg("pub")
+        .output()
+        .expect("failed to run yuiclaw pub");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_doctor_json_reports_broken_config_as_failed_check() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_dir = dir.path().join("yuiclaw");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(config_dir.join("config.toml"), "provider = 42\n").unwrap();
+
+    let output = yuiclaw_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .env("ACOMM_SOCKET_PATH", dir.path().join("acomm.sock"))
+        .env_remove("YUICLAW_PROFILE")
+        .args(["doctor", "--json"])
+        .output()
+        .expect("failed to run yuiclaw doctor --json");
+    assert!(!output.status.success(), "a failed check should exit 1");
+
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("doctor --json should print JSON");
+    assert_eq!(report["ok"], false);
+    let checks = report["checks"].as_array().unwrap();
+    let config = checks
+        .iter()
+        .find(|c| c["name"] == "config.toml")
+        .expect("config.toml check");
+    assert_eq!(config["status"], "fail");
+    assert!(config["fix"].as_str().unwrap().contains("yuiclaw config edit"));
+    let bridge = checks.iter().find(|c| c["name"] == "bridge").unwrap();
+    assert_eq!(bridge["status"], "warn");
+}
+
+#[test]
+fn test_adapter_disable_is_persisted_and_listed() {
+    let dir = tempfile::tempdir().unwrap();
+    let run = |args: &[&str]| {
+        yuiclaw_bin()
+            .env("XDG_CONFIG_HOME", dir.path().join("config"))
+            .env("XDG_STATE_HOME", dir.path().join("state"))
+            .env("ACOMM_SOCKET_PATH", dir.path().join("acomm.sock"))
+            .env_remove("YUICLAW_PROFILE")
+            .args(args)
+            .output()
+            .expect("failed to run yuiclaw adapter")
+    };
+
+    let output = run(&["adapter", "disable", "Discord"]);
+    assert!(output.status.success(), "{:?}", output);
+    let config = std::fs::read_to_string(dir.path().join("config/yuiclaw/config.toml")).unwrap();
+    assert!(config.contains("[adapters.discord]\nenabled = false"), "{}", config);
+
+    let output = run(&["adapter", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let discord = stdout.lines().find(|l| l.starts_with("discord")).unwrap();
+    assert!(discord.contains("no") && discord.contains("disabled"), "{}", stdout);
+
+    let output = run(&["adapter", "start", "discord"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Daemon is not running"));
+
+    assert!(!run(&["adapter", "stop", "matrix"]).status.success());
+}